@@ -0,0 +1,275 @@
+//! Annotate module for the calculator application.
+//!
+//! This module fills in the results of calculations kept in a text file, such as a
+//! Markdown note. Two kinds of lines are evaluated, from top to bottom, sharing their
+//! variables as the lines of a script do:
+//!
+//! - A line of prose ending in `=`, such as `- Rent each: rent / 3 =`. The calculation is
+//!   what follows the last `: `, or else the whole line after any list, heading or quote
+//!   marker, and it may assign a variable. Its result is written after the `=` in
+//!   backticks, as in ``rent / 3 = `400` ``, with `≈` in place of `=` when it is rounded.
+//!   A line whose calculation cannot be read, such as `What is 2 + 2 =`, is taken for
+//!   prose and left alone, unless it holds a result written before.
+//! - Every line of a fenced code block whose language is `fermat`. The result of an
+//!   expression, or of an assignment whose value is not what it says already, is written
+//!   in a trailing comment, as in `rent / 3  # = 400`.
+//!
+//! Results written by an earlier run are recognized and replaced, so annotating again
+//! only updates results that are out of date, and a file that is up to date comes back
+//! unchanged. Only a comment written exactly as a result is, after two spaces and holding
+//! a value, is replaced, so a remark such as `# = see below` is kept. Code blocks in other
+//! languages are left alone.
+
+use crate::datetime::WEEKDAYS;
+use crate::engine::EvalError;
+use crate::money::Currency;
+use crate::session::{Session, Statement, calculation};
+use crate::value::Computed;
+
+/// A line whose calculation could not be evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// The 1-based line number.
+    pub line: usize,
+    /// The calculation, without the rest of the line.
+    pub input: String,
+    pub error: EvalError,
+}
+
+/// The text with its results filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotated {
+    pub text: String,
+    /// The lines that failed, which are left without a result.
+    pub failures: Vec<Failure>,
+}
+
+impl Annotated {
+    /// Returns the 1-based numbers of the lines that differ from the original text.
+    ///
+    /// Annotating never adds or removes lines, so lines are compared one to one.
+    pub fn changed_lines(&self, original: &str) -> Vec<usize> {
+        original
+            .lines()
+            .zip(self.text.lines())
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(i, _)| i + 1)
+            .collect()
+    }
+}
+
+/// Where a line is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Prose,
+    /// Inside a `fermat` code block opened by a fence of this character and length.
+    Fermat(char, usize),
+    /// Inside a code block in another language.
+    Other(char, usize),
+}
+
+/// Evaluates the calculations of a text and fills in their results.
+///
+/// ```
+/// use fermat::annotate::annotate;
+/// use fermat::session::Session;
+///
+/// let notes = "Rent: 1200\n- Each: 1200 / 3 =\n";
+/// let annotated = annotate(notes, &mut Session::default());
+/// assert_eq!(annotated.text, "Rent: 1200\n- Each: 1200 / 3 = `400`\n");
+/// ```
+pub fn annotate(text: &str, session: &mut Session) -> Annotated {
+    let mut annotated = Annotated {
+        text: String::with_capacity(text.len()),
+        failures: Vec::new(),
+    };
+    let mut block = Block::Prose;
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let ending = &line[body.len()..];
+        let result = match block {
+            Block::Prose => {
+                if let Some((fence, length, language)) = fence(body) {
+                    block = if language == "fermat" {
+                        Block::Fermat(fence, length)
+                    } else {
+                        Block::Other(fence, length)
+                    };
+                    Ok(body.to_string())
+                } else {
+                    prose(body, session)
+                }
+            }
+            Block::Fermat(c, n) | Block::Other(c, n) if closes(body, c, n) => {
+                block = Block::Prose;
+                Ok(body.to_string())
+            }
+            Block::Fermat(..) => code(body, session),
+            Block::Other(..) => Ok(body.to_string()),
+        };
+        let annotated_line = match result {
+            Ok(line) => line,
+            Err((line, input, error)) => {
+                annotated.failures.push(Failure {
+                    line: i + 1,
+                    input,
+                    error,
+                });
+                line
+            }
+        };
+        annotated.text.push_str(&annotated_line);
+        annotated.text.push_str(ending);
+    }
+    annotated
+}
+
+/// A line to write in place of a failed one, the calculation and why it failed.
+type Failed = (String, String, EvalError);
+
+/// Annotates a line of prose that ends in `=`, or in a result written before.
+fn prose(body: &str, session: &mut Session) -> Result<String, Failed> {
+    let Some(stem) = without_result(body) else {
+        return Ok(body.to_string());
+    };
+    let stem = stem.trim_end();
+    let calculation = calculation(stem);
+    if calculation.is_empty() {
+        return Ok(body.to_string());
+    }
+    match session.run(calculation) {
+        Ok(Some(entry)) => {
+            let (relation, value) = shown(session, &entry.computed);
+            Ok(format!("{} {} `{}`", stem, relation, value))
+        }
+        Ok(None) => Ok(body.to_string()),
+        // Without a result written before, a line that cannot be read is prose
+        Err(EvalError::Syntax(_)) if !body.trim_end().ends_with('`') => Ok(body.to_string()),
+        Err(error) => {
+            // A result written before is out of date, so only the `=` is kept
+            let line = if body.trim_end().ends_with('=') {
+                body.to_string()
+            } else {
+                format!("{} =", stem)
+            };
+            Err((line, calculation.to_string(), error))
+        }
+    }
+}
+
+/// Returns the line up to its final `=` or `≈`, dropping a result in backticks after it,
+/// or `None` if the line does not end that way.
+///
+/// Lines ending in `==`, `<=`, `>=` or `!=`, or made only of `=` as a heading underline,
+/// are not calculations.
+fn without_result(body: &str) -> Option<&str> {
+    let mut rest = body.trim_end();
+    if let Some(inner) = rest.strip_suffix('`')
+        && let Some(open) = inner.rfind('`')
+    {
+        rest = inner[..open].trim_end();
+    }
+    let stem = rest.strip_suffix('=').or_else(|| rest.strip_suffix('≈'))?;
+    let operator = stem.ends_with(['=', '<', '>', '!']);
+    (!operator && !stem.trim().is_empty()).then_some(stem)
+}
+
+/// Annotates a line of a `fermat` code block.
+fn code(body: &str, session: &mut Session) -> Result<String, Failed> {
+    let line = without_comment_result(body);
+    match session.run(line) {
+        Ok(Some(entry)) => {
+            let (relation, value) = shown(session, &entry.computed);
+            let restates = match Statement::parse(line) {
+                Statement::Assign(_, expression) => expression == value && relation == "=",
+                _ => false,
+            };
+            if restates {
+                Ok(line.to_string())
+            } else {
+                Ok(format!("{}  # {} {}", line, relation, value))
+            }
+        }
+        Ok(None) => Ok(line.to_string()),
+        Err(error) => {
+            let input = match Statement::parse(line) {
+                Statement::Assign(_, expression) => expression,
+                _ => line.trim(),
+            };
+            Err((line.to_string(), input.to_string(), error))
+        }
+    }
+}
+
+/// Drops a trailing comment holding a result, written as [`code`] writes it: two spaces
+/// after the calculation, then `# = ` or `# ≈ ` and a value.
+fn without_comment_result(body: &str) -> &str {
+    let Some((line, comment)) = body.rsplit_once("  # ") else {
+        return body;
+    };
+    let value = comment
+        .strip_prefix("= ")
+        .or_else(|| comment.strip_prefix("≈ "));
+    let calculation = !line.trim().is_empty() && line.trim_end() == line;
+    match value {
+        Some(value) if calculation && is_value(value) => line,
+        _ => body,
+    }
+}
+
+/// Returns whether the text of a comment is a value as it is shown, rather than a remark:
+/// a number, an interval or an amount, a duration, or a date after its weekday.
+fn is_value(text: &str) -> bool {
+    if text.contains('#') || text.trim() != text {
+        return false;
+    }
+    let text = text.trim_start_matches(['-', '[']);
+    // A currency code or a weekday before the number
+    let text = match text.split_once(' ') {
+        Some((word, rest)) if Currency::new(word).is_some() || WEEKDAYS.contains(&word) => rest,
+        _ => text,
+    };
+    // A currency symbol before the number
+    let text = match text.chars().next() {
+        Some(c) if Currency::from_symbol(c.encode_utf8(&mut [0; 4])).is_some() => {
+            &text[c.len_utf8()..]
+        }
+        _ => text,
+    };
+    text.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Returns the relation and the value to write for a result.
+fn shown(session: &Session, computed: &Computed) -> (&'static str, String) {
+    let evaluator = session.evaluator();
+    (
+        computed.relation(&evaluator.rounding()),
+        evaluator.format(&computed.value),
+    )
+}
+
+/// Reads a line opening a code block, returning its fence character and length and the
+/// block's language.
+fn fence(body: &str) -> Option<(char, usize, &str)> {
+    let line = body.trim_start();
+    let c = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let info = line.trim_start_matches(c);
+    let length = line.len() - info.len();
+    if length < 3 {
+        return None;
+    }
+    let language = info.split_whitespace().next().unwrap_or("");
+    Some((
+        c,
+        length,
+        language.trim_start_matches('{').trim_end_matches('}'),
+    ))
+}
+
+/// Returns whether a line closes a code block opened by a fence of `c` of the given length.
+fn closes(body: &str, c: char, length: usize) -> bool {
+    let line = body.trim();
+    let rest = line.trim_start_matches(c);
+    rest.is_empty() && line.len() >= length
+}
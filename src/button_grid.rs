@@ -1,90 +1,124 @@
+use crate::settings::Theme;
 use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use fermat::evaluator::diagnose;
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
     prelude::Alignment,
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, Paragraph},
 };
 
+/// Height of a button in rows, with its border.
+const BUTTON_HEIGHT: usize = 3;
+
+/// The default button layout, row by row.
+pub const DEFAULT_LAYOUT: [[&str; 4]; 6] = [
+    // Row 1: Clear buttons and parentheses
+    ["C", "CE", "(", ")"],
+    // Row 2: Advanced operations
+    ["sqrt", "abs", "^", "%"],
+    // Row 3: Numbers 7-9 and division
+    ["7", "8", "9", "/"],
+    // Row 4: Numbers 4-6 and multiplication
+    ["4", "5", "6", "*"],
+    // Row 5: Numbers 1-3 and subtraction
+    ["1", "2", "3", "-"],
+    // Row 6: Zero, decimal, factorial, and addition
+    ["0", ".", "!", "+"],
+];
+
+/// Most rows a layout may have.
+pub const MAX_ROWS: usize = 10;
+
+/// Most buttons a row may have.
+pub const MAX_COLUMNS: usize = 8;
+
+/// Returns the key that presses the button with a label: the clear buttons and the
+/// functions of the default layout have a letter, and a single character is its own key.
+/// Other buttons can only be clicked.
+pub fn default_key(label: &str) -> Option<KeyCode> {
+    let key = match label {
+        "C" => 'c',
+        "CE" => 'e',
+        "sqrt" => 's',
+        "abs" => 'a',
+        _ => {
+            let mut chars = label.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return None,
+            }
+        }
+    };
+    Some(KeyCode::Char(key))
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Button {
     pub text: String,
-    pub key: KeyCode,
+    pub key: Option<KeyCode>,
     pub is_pressed: bool,
+    /// What pressing the button enters: `CLEAR_ALL`, `CLEAR_ENTRY`, or text for the input.
+    pub action: String,
 }
 
 impl Button {
-    fn new(text: &str, key: KeyCode) -> Self {
+    fn new(text: &str) -> Self {
+        let action = match text {
+            "C" => "CLEAR_ALL".to_string(),
+            "CE" => "CLEAR_ENTRY".to_string(),
+            // A function name opens a call
+            _ if text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && diagnose(&format!("{}(1)", text)).is_empty() =>
+            {
+                format!("{}(", text)
+            }
+            _ => text.to_string(),
+        };
         Self {
             text: text.to_string(),
-            key,
+            key: default_key(text),
             is_pressed: false,
+            action,
         }
     }
 }
 
 pub struct ButtonGrid {
     buttons: Vec<Button>,
-    selected: Option<usize>,
+    /// Buttons per row.
+    columns: usize,
     last_clicked_button: Option<usize>,
 }
 
 impl ButtonGrid {
-    pub fn new() -> Self {
-        let buttons = vec![
-            // Row 1: Clear buttons and parentheses
-            Button::new("C", KeyCode::Char('c')),
-            Button::new("CE", KeyCode::Char('e')),
-            Button::new("(", KeyCode::Char('(')),
-            Button::new(")", KeyCode::Char(')')),
-            // Row 2: Advanced operations
-            Button::new("sqrt", KeyCode::Char('s')),
-            Button::new("abs", KeyCode::Char('a')),
-            Button::new("^", KeyCode::Char('^')),
-            Button::new("%", KeyCode::Char('%')),
-            // Row 3: Numbers 7-9 and division
-            Button::new("7", KeyCode::Char('7')),
-            Button::new("8", KeyCode::Char('8')),
-            Button::new("9", KeyCode::Char('9')),
-            Button::new("/", KeyCode::Char('/')),
-            // Row 4: Numbers 4-6 and multiplication
-            Button::new("4", KeyCode::Char('4')),
-            Button::new("5", KeyCode::Char('5')),
-            Button::new("6", KeyCode::Char('6')),
-            Button::new("*", KeyCode::Char('*')),
-            // Row 5: Numbers 1-3 and subtraction
-            Button::new("1", KeyCode::Char('1')),
-            Button::new("2", KeyCode::Char('2')),
-            Button::new("3", KeyCode::Char('3')),
-            Button::new("-", KeyCode::Char('-')),
-            // Row 6: Zero, decimal, factorial, and addition
-            Button::new("0", KeyCode::Char('0')),
-            Button::new(".", KeyCode::Char('.')),
-            Button::new("!", KeyCode::Char('!')),
-            Button::new("+", KeyCode::Char('+')),
-        ];
-
+    /// Creates a grid with the buttons of `layout`, given row by row; every row has the
+    /// same number of buttons.
+    pub fn new(layout: &[Vec<String>]) -> Self {
         Self {
-            buttons,
-            selected: None,
+            buttons: layout
+                .iter()
+                .flatten()
+                .map(|text| Button::new(text))
+                .collect(),
+            columns: layout.first().map_or(1, Vec::len),
             last_clicked_button: None,
         }
     }
 
+    /// Returns the height the grid needs.
+    pub fn height(&self) -> u16 {
+        (self.buttons.len().div_ceil(self.columns) * BUTTON_HEIGHT) as u16
+    }
+
     pub fn handle_key_event(&mut self, key: KeyEvent) -> Option<String> {
         let key_code = key.code;
         for (idx, button) in self.buttons.iter_mut().enumerate() {
-            if button.key == key_code {
+            if button.key == Some(key_code) {
                 button.is_pressed = true;
                 self.last_clicked_button = Some(idx);
-                let result = match button.text.as_str() {
-                    "C" => "CLEAR_ALL".to_string(),
-                    "CE" => "CLEAR_ENTRY".to_string(),
-                    "sqrt" => "sqrt(".to_string(),
-                    "abs" => "abs(".to_string(),
-                    _ => button.text.clone(),
-                };
+                let result = button.action.clone();
                 // Reset button state immediately
                 button.is_pressed = false;
                 self.last_clicked_button = None;
@@ -95,21 +129,19 @@ impl ButtonGrid {
     }
 
     pub fn handle_mouse_event(&mut self, mouse: MouseEvent, area: Rect) -> Option<String> {
-        let x = mouse.column as u16;
-        let y = mouse.row as u16;
+        let x = mouse.column;
+        let y = mouse.row;
 
         if x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height {
             let relative_x = (x - area.x) as usize;
             let relative_y = (y - area.y) as usize;
 
-            let cols = 4;
-            let button_width = area.width as usize / cols;
-            let button_height = 3;
+            let button_width = (area.width as usize / self.columns).max(1);
 
             let col = relative_x / button_width;
-            let row = relative_y / button_height;
+            let row = relative_y / BUTTON_HEIGHT;
 
-            let index = row * cols + col;
+            let index = row * self.columns + col;
             if index < self.buttons.len() {
                 match mouse.kind {
                     MouseEventKind::Down(MouseButton::Left) => {
@@ -128,14 +160,7 @@ impl ButtonGrid {
                         // Only return the result if we're still over the same button
                         if let Some(last_idx) = self.last_clicked_button {
                             if last_idx == index {
-                                let result = match self.buttons[index].text.as_str() {
-                                    "C" => "CLEAR_ALL".to_string(),
-                                    "CE" => "CLEAR_ENTRY".to_string(),
-                                    "sqrt" => "sqrt(".to_string(),
-                                    "abs" => "abs(".to_string(),
-                                    _ => self.buttons[index].text.clone(),
-                                };
-                                Some(result)
+                                Some(self.buttons[index].action.clone())
                             } else {
                                 None
                             }
@@ -145,12 +170,12 @@ impl ButtonGrid {
                     }
                     MouseEventKind::Drag(MouseButton::Left) => {
                         // Update which button is pressed when dragging
-                        if let Some(last_idx) = self.last_clicked_button {
-                            if last_idx != index {
-                                self.buttons[last_idx].is_pressed = false;
-                                self.buttons[index].is_pressed = true;
-                                self.last_clicked_button = Some(index);
-                            }
+                        if let Some(last_idx) = self.last_clicked_button
+                            && last_idx != index
+                        {
+                            self.buttons[last_idx].is_pressed = false;
+                            self.buttons[index].is_pressed = true;
+                            self.last_clicked_button = Some(index);
                         }
                         None
                     }
@@ -171,39 +196,31 @@ impl ButtonGrid {
         }
     }
 
-    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
-        let button_style = Style::default().bg(Color::DarkGray).fg(Color::White);
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let button_style = Style::default().bg(theme.button).fg(theme.button_text);
         let pressed_style = Style::default()
-            .bg(Color::LightBlue) // Changed to light blue for better visibility
-            .fg(Color::Black)
+            .bg(theme.pressed)
+            .fg(theme.pressed_text)
             .add_modifier(Modifier::BOLD);
 
-        // Create a 4x6 grid layout
+        // Create a grid layout with one row per row of buttons
+        let row_count = self.buttons.len().div_ceil(self.columns);
         let rows = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(vec![
-                Constraint::Length(3), // Row height
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-            ])
+            .constraints(vec![Constraint::Length(BUTTON_HEIGHT as u16); row_count])
             .split(area);
 
         for (row_idx, row) in rows.iter().enumerate() {
             let cols = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints(vec![
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(25),
+                    Constraint::Ratio(1, self.columns as u32);
+                    self.columns
                 ])
                 .split(*row);
 
             for (col_idx, col) in cols.iter().enumerate() {
-                let button_idx = row_idx * 4 + col_idx;
+                let button_idx = row_idx * self.columns + col_idx;
                 if button_idx < self.buttons.len() {
                     let button = &self.buttons[button_idx];
                     let style = if button.is_pressed {
@@ -0,0 +1,683 @@
+//! Command-line module for the Calculator application.
+//!
+//! This module evaluates expressions given as arguments, or line by line from a script
+//! file or stdin, without starting the TUI, so that the calculator can be used from shell
+//! scripts, pipes and Makefiles. Results are printed to stdout and errors to stderr, and the
+//! exit status tells success and failure apart.
+
+use crate::repl;
+use crate::settings::Settings;
+use crate::{Start, run_tui};
+use fermat::Evaluator;
+use fermat::annotate;
+use fermat::config::Config;
+use fermat::diagnostics::{self, Diagnostic};
+use fermat::engine::EvalError;
+use fermat::json::{self, Json};
+use fermat::limits::Limits;
+use fermat::money::{Currency, RateTable};
+use fermat::notation::{self, Notation};
+use fermat::rounding::{Precision, RoundingContext};
+use fermat::server;
+use fermat::session::{Script, ScriptLine, Session, Statement};
+use fermat::value::{Computed, Value};
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Exit status when an expression cannot be evaluated.
+const EXIT_FAILURE: i32 = 1;
+
+/// Exit status when the arguments cannot be understood.
+const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "\
+Usage: fermat [OPTIONS] [--] EXPRESSION...
+       fermat [OPTIONS] SCRIPT.fermat
+       fermat [OPTIONS] -
+       fermat [OPTIONS] --repl
+       fermat [OPTIONS] --worksheet [FILE]
+       fermat [OPTIONS] serve [--socket PATH]
+       fermat [OPTIONS] annotate [--check] FILE
+
+Evaluates EXPRESSION and prints the result; without arguments the TUI is started.
+Several arguments are joined with spaces, so quoting is only needed for characters the
+shell treats specially, such as `*`, `(` and `!`. Options may be given anywhere before
+`--`, which makes every later argument part of the expression.
+
+A script file ending in .fermat or .fm, or stdin given as `-`, is evaluated line by line
+and the result of every expression is printed. Lines may assign variables with
+`name = expression`, `#` starts a comment, and `#include \"path\"` evaluates another
+script first. Stdin is also read when it or stdout is not a terminal.
+
+`serve` answers JSON-RPC 2.0 requests, one per line, on stdin and stdout, or on a Unix
+socket at PATH with a separate session for each connection. The methods are eval,
+define, complete, diagnostics and reset; see the documentation of the server module.
+
+`annotate` fills in the results of calculations in a text file such as a Markdown note:
+lines of prose ending in `=`, where the calculation follows the last `: ` or the list
+marker, and every line of fenced code blocks marked `fermat`. Lines are evaluated top to
+bottom and share their variables. Results written before are replaced, so running it
+again updates stale results; --check changes nothing and fails if any result is out of
+date. FILE may be `-` to annotate stdin to stdout.
+
+Defaults for the options, money mode and limits are read from
+$XDG_CONFIG_HOME/fermat/config.toml, or ~/.config/fermat/config.toml.
+
+--json describes each evaluation as a JSON object on its own line, with the input, its
+normalized form, the exact and formatted value, its unit and exactness, or the error with
+its kind and location. Script lines also name their file, line and assigned variable;
+failed lines are included. The \"schema\" field gives the format's version.
+
+--worksheet starts the TUI with a worksheet: a multi-line sheet where every line shows
+its result on the right, updated as it is typed. A line may assign a variable, start with
+a label such as `Rent: `, and use the result of line N as `lineN`. FILE is read if it
+exists and written with Ctrl-S.
+
+--repl starts a plain prompt with line editing and history instead of the TUI, for SSH
+sessions, dumb terminals and screen readers; enter :help there for its commands.
+
+Options:
+  -p, --precision N     Show N decimal places (default 10)
+  -s, --significant N   Show N significant digits
+  -b, --base N          Show whole-number results in base N, from 2 to 36
+  -n, --notation NAME   plain, scientific or engineering (default plain)
+  -f, --final           Print only the value of the last line of a script
+      --json            Write each result as a JSON object, one per line
+      --repl            Read expressions at a prompt
+      --worksheet       Edit a worksheet of calculations in the TUI
+      --socket PATH     With serve, listen on a Unix socket instead of stdio
+      --check           With annotate, fail if any result is out of date
+  -h, --help            Show this help
+  -V, --version         Show the version";
+
+/// What the command line asks for.
+enum Command {
+    Evaluate(String),
+    /// Evaluate a script file, or stdin if there is no path.
+    Script(Option<PathBuf>),
+    /// Read expressions at a prompt.
+    Repl,
+    /// Start the TUI with a worksheet, opened from a file if there is a path.
+    Worksheet(Option<PathBuf>),
+    /// Answer JSON-RPC requests on stdio, or on a Unix socket if there is a path.
+    Serve(Option<PathBuf>),
+    /// Fill in the results of a text file, or stdin if the path is `-`, or only check
+    /// that they are up to date.
+    Annotate {
+        path: PathBuf,
+        check: bool,
+    },
+    Help,
+    Version,
+}
+
+/// How results are shown.
+struct Options {
+    rounding: RoundingContext,
+    base: Option<u32>,
+    notation: Notation,
+    /// Whether only the last value of a script is printed.
+    last_only: bool,
+    /// Whether results are written as JSON.
+    json: bool,
+    /// The currency of money mode, if it is on.
+    money: Option<Currency>,
+    limits: Limits,
+}
+
+/// Runs the command line and returns the exit status.
+pub fn run(args: &[String], config: &Config, settings: &Settings) -> i32 {
+    let (command, options) = match parse_args(args, config) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("Try 'fermat --help' for more information.");
+            return EXIT_USAGE;
+        }
+    };
+    let input = match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Command::Version => {
+            println!("fermat {}", env!("CARGO_PKG_VERSION"));
+            return 0;
+        }
+        Command::Evaluate(input) => input,
+        Command::Script(path) => return run_script(path.as_deref(), &options),
+        Command::Serve(socket) => return run_server(socket.as_deref(), &options),
+        Command::Annotate { path, check } => return run_annotate(&path, check, &options),
+        Command::Worksheet(path) => {
+            return match run_tui(config, settings, Start::Worksheet(path)) {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    EXIT_FAILURE
+                }
+            };
+        }
+        Command::Repl => {
+            return match evaluator(&options) {
+                Ok(evaluator) => repl::run(Session::new(evaluator)),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    EXIT_FAILURE
+                }
+            };
+        }
+    };
+    let evaluator = match evaluator(&options) {
+        Ok(evaluator) => evaluator,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_FAILURE;
+        }
+    };
+    if options.json {
+        let report = describe(&evaluator, &input, evaluator.compute(&input), &options);
+        println!("{}", report);
+        return if report.get("ok") == Some(&Json::Bool(true)) {
+            0
+        } else {
+            EXIT_FAILURE
+        };
+    }
+    let shown = evaluator
+        .eval(&input)
+        .map_err(|e| report(&input, &e, None))
+        .and_then(|value| {
+            format_value(&evaluator, &value, &options).map_err(|e| eprintln!("error: {}", e))
+        });
+    match shown {
+        Ok(text) => {
+            println!("{}", text);
+            0
+        }
+        Err(()) => EXIT_FAILURE,
+    }
+}
+
+/// Creates an evaluator with the requested precision, the money mode and limits of the
+/// configuration file, and the exchange rates named by the environment.
+fn evaluator(options: &Options) -> Result<Evaluator, Box<dyn Error>> {
+    let mut evaluator = Evaluator::builder().limits(options.limits).build();
+    evaluator.set_rounding(options.rounding);
+    evaluator.set_money(options.money);
+    if let Some(path) = std::env::var_os(crate::RATES_VARIABLE) {
+        evaluator.set_rates(Some(RateTable::load(Path::new(&path))?));
+    }
+    Ok(evaluator)
+}
+
+/// Evaluates a script file, or stdin line by line as it arrives, and returns the exit
+/// status.
+///
+/// A line that fails is reported and the rest of the script still runs, but the exit
+/// status is then a failure.
+fn run_script(path: Option<&Path>, options: &Options) -> i32 {
+    let mut session = match evaluator(options) {
+        Ok(evaluator) => Session::new(evaluator),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_FAILURE;
+        }
+    };
+    let mut batch = Batch {
+        options,
+        last: None,
+        failed: false,
+    };
+    match path {
+        Some(path) => match Script::load(path) {
+            Ok(script) => batch.run_lines(&mut session, &script.lines),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return EXIT_FAILURE;
+            }
+        },
+        None => {
+            let stdin = Path::new("<stdin>");
+            for (i, text) in io::stdin().lock().lines().enumerate() {
+                let text = match text {
+                    Ok(text) => text,
+                    Err(e) => {
+                        eprintln!("error: cannot read stdin: {}", e);
+                        return EXIT_FAILURE;
+                    }
+                };
+                if let Statement::Include(target) = Statement::parse(&text) {
+                    // Included paths are relative to the working directory
+                    match Script::load(Path::new(target)) {
+                        Ok(mut script) => {
+                            for line in &mut script.lines {
+                                line.included = true;
+                            }
+                            batch.run_lines(&mut session, &script.lines);
+                        }
+                        Err(e) => {
+                            eprintln!("{}:{}: error: {}", stdin.display(), i + 1, e);
+                            batch.failed = true;
+                        }
+                    }
+                    continue;
+                }
+                let line = ScriptLine {
+                    path: stdin.to_path_buf(),
+                    number: i + 1,
+                    text,
+                    included: false,
+                };
+                batch.run_lines(&mut session, std::slice::from_ref(&line));
+            }
+        }
+    }
+    if options.last_only
+        && let Some(text) = &batch.last
+    {
+        println!("{}", text);
+    }
+    if batch.failed { EXIT_FAILURE } else { 0 }
+}
+
+/// Answers JSON-RPC requests until the input ends, or forever on a socket, and returns the
+/// exit status.
+fn run_server(socket: Option<&Path>, options: &Options) -> i32 {
+    let evaluator = match evaluator(options) {
+        Ok(evaluator) => evaluator,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_FAILURE;
+        }
+    };
+    let served = match socket {
+        None => server::serve(evaluator, io::stdin().lock(), io::stdout().lock()),
+        #[cfg(unix)]
+        Some(path) => remove_on_signal(path)
+            .and_then(|()| server::listen(path, evaluator))
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+        #[cfg(not(unix))]
+        Some(_) => Err(io::Error::other(
+            "Unix sockets are not available on this system",
+        )),
+    };
+    match served {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Fills in the results of a text file, or checks that they are up to date, and returns
+/// the exit status.
+///
+/// Lines that fail are reported and left without a result, and the exit status is then
+/// a failure; the other results are still written.
+fn run_annotate(path: &Path, check: bool, options: &Options) -> i32 {
+    let stdin = path == Path::new("-");
+    let read = if stdin {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(path)
+    };
+    let (name, text) = match read {
+        Ok(text) if stdin => ("<stdin>".to_string(), text),
+        Ok(text) => (path.display().to_string(), text),
+        Err(e) => {
+            eprintln!("error: cannot read {}: {}", path.display(), e);
+            return EXIT_FAILURE;
+        }
+    };
+    let mut session = match evaluator(options) {
+        Ok(evaluator) => Session::new(evaluator),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_FAILURE;
+        }
+    };
+    let annotated = annotate::annotate(&text, &mut session);
+    for failure in &annotated.failures {
+        let location = format!("{}:{}", name, failure.line);
+        report(&failure.input, &failure.error, Some(&location));
+    }
+    let mut status = if annotated.failures.is_empty() {
+        0
+    } else {
+        EXIT_FAILURE
+    };
+    if check {
+        for line in annotated.changed_lines(&text) {
+            eprintln!("{}:{}: result is out of date", name, line);
+            status = EXIT_FAILURE;
+        }
+        return status;
+    }
+    let written = if stdin {
+        io::stdout().write_all(annotated.text.as_bytes())
+    } else if annotated.text != text {
+        fs::write(path, &annotated.text)
+    } else {
+        Ok(())
+    };
+    if let Err(e) = written {
+        eprintln!("error: cannot write {}: {}", name, e);
+        return EXIT_FAILURE;
+    }
+    status
+}
+
+/// Removes the server's socket file when the process is stopped by SIGINT, SIGTERM or
+/// SIGHUP, which would otherwise end it without any cleanup, and exits.
+#[cfg(unix)]
+fn remove_on_signal(path: &Path) -> io::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+    let mut signals = signal_hook::iterator::Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            server::remove_socket(&path);
+            std::process::exit(128 + signal);
+        }
+    });
+    Ok(())
+}
+
+/// The state of a script being evaluated.
+struct Batch<'a> {
+    options: &'a Options,
+    /// The formatted value of the last line that had one.
+    last: Option<String>,
+    /// Whether any line failed.
+    failed: bool,
+}
+
+impl Batch<'_> {
+    /// Evaluates lines in order, printing the value of each expression in the script
+    /// itself unless only the last value is wanted.
+    ///
+    /// With `--json` every evaluated line of the script itself is described instead,
+    /// including failures, with its file, line number and the variable it assigned.
+    fn run_lines(&mut self, session: &mut Session, lines: &[ScriptLine]) {
+        for line in lines {
+            let location = format!("{}:{}", line.path.display(), line.number);
+            let entry = match session.run(&line.text) {
+                Ok(None) => continue,
+                entry => entry.map(Option::unwrap),
+            };
+            let (name, input) = match Statement::parse(&line.text) {
+                Statement::Assign(name, expression) => (Some(name), expression),
+                _ => (None, line.text.trim()),
+            };
+            if self.options.json {
+                let computed = entry.map(|entry| entry.computed);
+                let mut report = describe(session.evaluator(), input, computed, self.options);
+                report.insert("file", Json::String(line.path.display().to_string()));
+                report.insert("line", Json::from(line.number));
+                report.insert("name", Json::from(name));
+                let failed = report.get("ok") != Some(&Json::Bool(true));
+                self.failed |= failed;
+                if failed || (!line.included && !self.options.last_only) {
+                    println!("{}", report);
+                }
+                if !failed {
+                    self.last = Some(report.to_string());
+                }
+                continue;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    report(input, &e, Some(&location));
+                    self.failed = true;
+                    continue;
+                }
+            };
+            let value = &entry.computed.value;
+            match format_value(session.evaluator(), value, self.options) {
+                Ok(text) => {
+                    if name.is_none() && !line.included && !self.options.last_only {
+                        println!("{}", text);
+                    }
+                    self.last = Some(text);
+                }
+                Err(e) => {
+                    eprintln!("{}: error: {}", location, e);
+                    self.failed = true;
+                }
+            }
+        }
+    }
+}
+
+/// Reads the options and the expression from the arguments, starting from the defaults of
+/// the configuration file.
+///
+/// Options may come before, between or after the words of the expression, so
+/// `fermat 1/3 -p 3` shows three decimal places. An argument that starts with `-` but is
+/// not an option, such as `-2^2` or `-x`, is a word of the expression, and `--` makes
+/// every later argument part of the expression.
+fn parse_args(args: &[String], config: &Config) -> Result<(Command, Options), Box<dyn Error>> {
+    let mut options = Options {
+        rounding: config.rounding,
+        base: None,
+        notation: Notation::Plain,
+        last_only: false,
+        json: false,
+        money: config.money,
+        limits: config.limits(Limits::default()),
+    };
+    let mut words = Vec::new();
+    let mut repl = false;
+    let mut worksheet = false;
+    let mut socket = None;
+    let mut check = false;
+    // Whether `--` made every later argument part of the expression
+    let mut literal = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            words.push(arg.clone());
+            continue;
+        }
+        if arg == "--" {
+            literal = true;
+            words.extend(args.by_ref().cloned());
+            break;
+        }
+        // Options may be given as `--name value` or `--name=value`
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || -> Result<String, Box<dyn Error>> {
+            match &inline {
+                Some(value) => Ok(value.clone()),
+                None => args
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name).into()),
+            }
+        };
+        match name {
+            "-h" | "--help" => return Ok((Command::Help, options)),
+            "-V" | "--version" => return Ok((Command::Version, options)),
+            "--repl" => repl = true,
+            "--worksheet" => worksheet = true,
+            "--socket" => socket = Some(PathBuf::from(value()?)),
+            "--check" => check = true,
+            "-p" | "--precision" => {
+                options.rounding.precision = Precision::DecimalPlaces(number(name, &value()?)?);
+            }
+            "-s" | "--significant" => {
+                let digits = number(name, &value()?)?.max(1);
+                options.rounding.precision = Precision::SignificantDigits(digits);
+            }
+            "-b" | "--base" => {
+                let base = number(name, &value()?)?;
+                if !(2..=36).contains(&base) {
+                    return Err(format!("{} must be from 2 to 36, not {}", name, base).into());
+                }
+                options.base = Some(base);
+            }
+            "-n" | "--notation" => options.notation = value()?.parse()?,
+            "-f" | "--final" => options.last_only = true,
+            "--json" => options.json = true,
+            _ if name.starts_with("--")
+                && name[2..].starts_with(|c: char| c.is_ascii_alphabetic()) =>
+            {
+                return Err(format!("Unknown option: {}", name).into());
+            }
+            // Anything else, such as `-2^2`, `-x` or `--3`, is part of the expression
+            _ => words.push(arg.clone()),
+        }
+    }
+    if worksheet {
+        if repl || options.json {
+            return Err("--worksheet cannot be used with --repl or --json".into());
+        }
+        return match words.as_slice() {
+            [] => Ok((Command::Worksheet(None), options)),
+            [path] => Ok((Command::Worksheet(Some(PathBuf::from(path))), options)),
+            _ => Err("--worksheet takes at most one file".into()),
+        };
+    }
+    if words == ["serve"] && !repl && !literal {
+        return Ok((Command::Serve(socket), options));
+    }
+    if socket.is_some() {
+        return Err("--socket is only used with serve".into());
+    }
+    if words.first().is_some_and(|word| word == "annotate") && !repl && !literal {
+        if options.json {
+            return Err("--json cannot be used with annotate".into());
+        }
+        return match words.as_slice() {
+            [_, path] => Ok((
+                Command::Annotate {
+                    path: PathBuf::from(path),
+                    check,
+                },
+                options,
+            )),
+            _ => Err("annotate takes one file".into()),
+        };
+    }
+    if check {
+        return Err("--check is only used with annotate".into());
+    }
+    if repl {
+        if !words.is_empty() {
+            return Err("--repl takes no expression".into());
+        }
+        if options.json {
+            return Err("--json cannot be used with --repl".into());
+        }
+        return Ok((Command::Repl, options));
+    }
+    if let [word] = words.as_slice() {
+        if word == "-" {
+            return Ok((Command::Script(None), options));
+        }
+        if word.ends_with(".fermat") || word.ends_with(".fm") {
+            return Ok((Command::Script(Some(PathBuf::from(word))), options));
+        }
+    }
+    if words.is_empty() {
+        return Err("No expression given".into());
+    }
+    Ok((Command::Evaluate(words.join(" ")), options))
+}
+
+/// Reads a whole number given to an option.
+fn number(name: &str, value: &str) -> Result<u32, Box<dyn Error>> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a whole number, not '{}'", name, value).into())
+}
+
+/// Formats a result in the requested base or notation.
+///
+/// The base and notation only apply to plain numbers; other values are shown as usual.
+fn format_value(
+    evaluator: &Evaluator,
+    value: &Value,
+    options: &Options,
+) -> Result<String, Box<dyn Error>> {
+    match (value, options.base) {
+        (Value::Number(n), Some(base)) => notation::format_radix(*n, base),
+        (Value::Number(n), None) => Ok(notation::format_number(
+            *n,
+            options.notation,
+            &options.rounding,
+        )),
+        _ => Ok(evaluator.format(value)),
+    }
+}
+
+/// Describes an evaluation as JSON, with the value formatted as requested.
+///
+/// A value that cannot be shown in the requested base is described as an evaluation error.
+fn describe(
+    evaluator: &Evaluator,
+    input: &str,
+    result: Result<Computed, EvalError>,
+    options: &Options,
+) -> Json {
+    let formatted = match &result {
+        Ok(computed) => Some(format_value(evaluator, &computed.value, options)),
+        Err(_) => None,
+    };
+    let result = match &formatted {
+        Some(Err(e)) => Err(EvalError::Evaluation(e.to_string())),
+        _ => result,
+    };
+    let mut report = json::evaluation(evaluator, input, &result);
+    if let Some(Ok(text)) = formatted
+        && let Some(value) = report.get_mut("value")
+    {
+        value.insert("formatted", Json::String(text));
+    }
+    report
+}
+
+/// Writes an error to stderr, naming its kind and, for syntax errors, where it is.
+///
+/// Syntax errors show the input with carets under each problem, followed by one line per
+/// problem with its 1-based character columns. Messages start with the script location,
+/// such as `notes.fermat:3`, if there is one.
+pub(crate) fn report(input: &str, error: &EvalError, location: Option<&str>) {
+    let prefix = location.map_or(String::new(), |location| format!("{}: ", location));
+    match error {
+        EvalError::Syntax(problems) => {
+            eprintln!("{}", input);
+            eprintln!("{}", diagnostics::caret_line(input, problems));
+            for problem in problems {
+                eprintln!(
+                    "{}{} error at {}: {}",
+                    prefix,
+                    error.kind(),
+                    columns(input, problem),
+                    problem
+                );
+            }
+        }
+        _ => eprintln!("{}{} error: {}", prefix, error.kind(), error),
+    }
+}
+
+/// Returns the 1-based character columns a diagnostic points at, as `3` or `3-5`.
+fn columns(input: &str, problem: &Diagnostic) -> String {
+    let column = |offset: usize| input.get(..offset).map_or(0, |s| s.chars().count());
+    let start = column(problem.span.start) + 1;
+    let end = column(problem.span.end);
+    if end > start {
+        format!("{}-{}", start, end)
+    } else {
+        start.to_string()
+    }
+}
@@ -0,0 +1,577 @@
+//! Config module for the calculator application.
+//!
+//! This module reads the configuration file, `fermat/config.toml` in the directory named by
+//! `XDG_CONFIG_HOME` or in `~/.config`. The file is written in a subset of TOML: `[table]`
+//! headers, `key = value` lines and `#` comments, with strings, whole numbers, booleans and
+//! arrays as values. Arrays may span several lines.
+//!
+//! The `[calculator]` and `[limits]` tables set the defaults for evaluation and are read
+//! here into a [`Config`]. The `[theme]`, `[keys]` and `[grid]` tables belong to the TUI,
+//! which reads them through [`Config::table`]. Anything the calculator does not know, and
+//! any value it cannot use, is an error naming the line, so that a mistake is never
+//! silently ignored.
+
+use crate::engine::EvaluatorBuilder;
+use crate::limits::Limits;
+use crate::money::Currency;
+use crate::rounding::{Precision, RoundingContext};
+use rust_decimal::Decimal;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The tables a configuration file may contain.
+const TABLES: &[&str] = &["calculator", "limits", "theme", "keys", "grid"];
+
+/// Returns where the configuration file is looked for: `$XDG_CONFIG_HOME/fermat/config.toml`,
+/// or `~/.config/fermat/config.toml` if `XDG_CONFIG_HOME` is not set.
+///
+/// Returns `None` if neither `XDG_CONFIG_HOME` nor `HOME` is set.
+pub fn default_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("fermat").join("config.toml"))
+}
+
+/// A value in a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Setting {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Setting>),
+}
+
+impl Setting {
+    /// Returns the text of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Setting::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the items of an array.
+    pub fn as_array(&self) -> Option<&[Setting]> {
+        match self {
+            Setting::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Setting {
+    /// Writes the value as it would appear in the file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Setting::String(s) => write!(f, "{:?}", s),
+            Setting::Integer(n) => write!(f, "{}", n),
+            Setting::Boolean(b) => write!(f, "{}", b),
+            Setting::Array(items) => {
+                let items: Vec<String> = items.iter().map(ToString::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// A `key = value` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Setting,
+    /// The line the entry starts on, counting from 1.
+    pub line: usize,
+}
+
+/// A `[name]` table and the entries below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub name: String,
+    /// The line of the header, counting from 1.
+    pub line: usize,
+    pub entries: Vec<Entry>,
+}
+
+/// The settings read from a configuration file.
+///
+/// ```
+/// use fermat::config::Config;
+/// use fermat::limits::Limits;
+///
+/// let config = Config::parse("[calculator]\nprecision = 3\n").unwrap();
+/// let evaluator = config.evaluator_builder(Limits::default()).build();
+/// assert_eq!(evaluator.format(&evaluator.eval("1 / 3").unwrap()), "0.333");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// The display precision and rounding strategy.
+    pub rounding: RoundingContext,
+    /// The currency of money mode, if it starts on.
+    pub money: Option<Currency>,
+    limits: LimitSettings,
+    tables: Vec<Table>,
+}
+
+/// The limits set in the file; the others keep the defaults of the caller.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct LimitSettings {
+    max_input_length: Option<usize>,
+    max_magnitude: Option<Decimal>,
+    max_depth: Option<usize>,
+    max_steps: Option<usize>,
+    max_time: Option<Duration>,
+}
+
+impl Config {
+    /// Reads the configuration file, returning the defaults if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e).into()),
+        };
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Parses the contents of a configuration file.
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let tables = Parser::new(text).document()?;
+        let mut config = Config::default();
+        let table = |name: &str| tables.iter().find(|table| table.name == name);
+        for table in &tables {
+            if !TABLES.contains(&table.name.as_str()) {
+                return Err(format!(
+                    "line {}: unknown table [{}] (expected {})",
+                    table.line,
+                    table.name,
+                    TABLES.join(", ")
+                )
+                .into());
+            }
+        }
+        if let Some(table) = table("calculator") {
+            for entry in &table.entries {
+                config.calculator_setting(entry)?;
+            }
+            let precisions: Vec<&Entry> = table
+                .entries
+                .iter()
+                .filter(|entry| entry.key == "precision" || entry.key == "significant")
+                .collect();
+            if let [_, second] = precisions[..] {
+                return Err(format!(
+                    "line {}: set either precision or significant, not both",
+                    second.line
+                )
+                .into());
+            }
+        }
+        if let Some(table) = table("limits") {
+            for entry in &table.entries {
+                config.limits_setting(entry)?;
+            }
+        }
+        config.tables = tables;
+        Ok(config)
+    }
+
+    /// Returns a table of the file, if it is there.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+
+    /// Returns `defaults` with the limits set in the file replaced.
+    pub fn limits(&self, defaults: Limits) -> Limits {
+        let set = self.limits;
+        Limits {
+            max_input_length: set.max_input_length.unwrap_or(defaults.max_input_length),
+            max_magnitude: set.max_magnitude.unwrap_or(defaults.max_magnitude),
+            max_depth: set.max_depth.unwrap_or(defaults.max_depth),
+            max_steps: set.max_steps.unwrap_or(defaults.max_steps),
+            max_time: set.max_time.unwrap_or(defaults.max_time),
+        }
+    }
+
+    /// Starts configuring an evaluator with the settings of the file, using
+    /// `default_limits` for the limits it does not set.
+    pub fn evaluator_builder(&self, default_limits: Limits) -> EvaluatorBuilder {
+        let builder = EvaluatorBuilder::default()
+            .precision(self.rounding.precision)
+            .rounding(self.rounding.rounding)
+            .limits(self.limits(default_limits));
+        match self.money {
+            Some(currency) => builder.money(currency),
+            None => builder,
+        }
+    }
+
+    /// Applies an entry of the `[calculator]` table.
+    fn calculator_setting(&mut self, entry: &Entry) -> Result<(), Box<dyn Error>> {
+        match entry.key.as_str() {
+            "precision" => {
+                self.rounding.precision = Precision::DecimalPlaces(whole(entry, 0, 28)? as u32);
+            }
+            "significant" => {
+                self.rounding.precision = Precision::SignificantDigits(whole(entry, 1, 28)? as u32);
+            }
+            "rounding" => {
+                self.rounding.rounding = string(entry)?
+                    .parse()
+                    .map_err(|e| format!("line {}: {}", entry.line, e))?;
+            }
+            "money" => {
+                let code = string(entry)?;
+                self.money = match code {
+                    "" | "off" => None,
+                    code => Some(Currency::new(&code.to_ascii_uppercase()).ok_or_else(|| {
+                        format!("line {}: unknown currency '{}'", entry.line, code)
+                    })?),
+                };
+            }
+            "angle" => {
+                return Err(format!(
+                    "line {}: angle modes are not supported: the calculator has no \
+                     trigonometric functions",
+                    entry.line
+                )
+                .into());
+            }
+            "backend" => {
+                let backend = string(entry)?;
+                if backend != "decimal" {
+                    return Err(format!(
+                        "line {}: unknown number backend '{}' (only decimal is available)",
+                        entry.line, backend
+                    )
+                    .into());
+                }
+            }
+            _ => {
+                return Err(unknown(
+                    entry,
+                    "calculator",
+                    "precision, significant, rounding, money, angle, backend",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies an entry of the `[limits]` table.
+    fn limits_setting(&mut self, entry: &Entry) -> Result<(), Box<dyn Error>> {
+        let count = || whole(entry, 1, i64::MAX).map(|n| n as usize);
+        match entry.key.as_str() {
+            "max_input_length" => self.limits.max_input_length = Some(count()?),
+            "max_depth" => self.limits.max_depth = Some(count()?),
+            "max_steps" => self.limits.max_steps = Some(count()?),
+            "max_time_ms" => {
+                self.limits.max_time = Some(Duration::from_millis(count()? as u64));
+            }
+            "max_magnitude" => {
+                let magnitude = match &entry.value {
+                    Setting::Integer(n) => Some(Decimal::from(*n)),
+                    Setting::String(s) => Decimal::from_str(s)
+                        .or_else(|_| Decimal::from_scientific(s))
+                        .ok(),
+                    _ => None,
+                };
+                self.limits.max_magnitude =
+                    Some(magnitude.filter(|m| *m > Decimal::ZERO).ok_or_else(|| {
+                        format!(
+                            "line {}: max_magnitude must be a positive number up to about \
+                                 7.9e28, such as 1000000 or \"1e20\", not {}",
+                            entry.line, entry.value
+                        )
+                    })?);
+            }
+            _ => {
+                return Err(unknown(
+                    entry,
+                    "limits",
+                    "max_input_length, max_magnitude, max_depth, max_steps, max_time_ms",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the text of a string entry, or an error naming the line.
+pub fn string(entry: &Entry) -> Result<&str, Box<dyn Error>> {
+    entry.value.as_str().ok_or_else(|| {
+        format!(
+            "line {}: {} must be a string, not {}",
+            entry.line, entry.key, entry.value
+        )
+        .into()
+    })
+}
+
+/// Returns the value of a whole-number entry between `min` and `max`, or an error naming
+/// the line.
+pub fn whole(entry: &Entry, min: i64, max: i64) -> Result<i64, Box<dyn Error>> {
+    match entry.value {
+        Setting::Integer(n) if (min..=max).contains(&n) => Ok(n),
+        _ if max == i64::MAX => Err(format!(
+            "line {}: {} must be a whole number of at least {}, not {}",
+            entry.line, entry.key, min, entry.value
+        )
+        .into()),
+        _ => Err(format!(
+            "line {}: {} must be a whole number from {} to {}, not {}",
+            entry.line, entry.key, min, max, entry.value
+        )
+        .into()),
+    }
+}
+
+/// Returns the error for a key a table does not have.
+pub fn unknown(entry: &Entry, table: &str, expected: &str) -> Box<dyn Error> {
+    format!(
+        "line {}: unknown setting {} in [{}] (expected {})",
+        entry.line, entry.key, table, expected
+    )
+    .into()
+}
+
+/// Reads the TOML subset, keeping track of the line.
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn error<T>(&self, message: impl fmt::Display) -> Result<T, Box<dyn Error>> {
+        Err(format!("line {}: {}", self.line, message).into())
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skips spaces and tabs, and a comment up to the end of the line.
+    fn skip_blank(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {
+                    self.next();
+                }
+                '#' => {
+                    while self.chars.peek().is_some_and(|&c| c != '\n') {
+                        self.next();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Skips blanks, comments and line breaks.
+    fn skip_lines(&mut self) {
+        loop {
+            self.skip_blank();
+            if self.chars.peek() != Some(&'\n') {
+                break;
+            }
+            self.next();
+        }
+    }
+
+    /// Requires the rest of the line to be blank.
+    fn end_of_line(&mut self) -> Result<(), Box<dyn Error>> {
+        self.skip_blank();
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some(c) => self.error(format!("unexpected '{}' after the value", c)),
+        }
+    }
+
+    fn document(&mut self) -> Result<Vec<Table>, Box<dyn Error>> {
+        let mut tables: Vec<Table> = Vec::new();
+        loop {
+            self.skip_lines();
+            let Some(&c) = self.chars.peek() else {
+                return Ok(tables);
+            };
+            if c == '[' {
+                self.next();
+                if self.chars.peek() == Some(&'[') {
+                    return self.error("arrays of tables ([[...]]) are not supported");
+                }
+                self.skip_blank();
+                let name = self.key()?;
+                self.skip_blank();
+                if self.next() != Some(']') {
+                    return self.error(format!("expected ']' after [{}", name));
+                }
+                if tables.iter().any(|table| table.name == name) {
+                    return self.error(format!("table [{}] appears twice", name));
+                }
+                tables.push(Table {
+                    name,
+                    line: self.line,
+                    entries: Vec::new(),
+                });
+                self.end_of_line()?;
+                continue;
+            }
+            let line = self.line;
+            let key = self.key()?;
+            let Some(table) = tables.last_mut() else {
+                return self.error(format!(
+                    "{} must be inside a table, such as [calculator]",
+                    key
+                ));
+            };
+            if table.entries.iter().any(|entry| entry.key == key) {
+                return self.error(format!("{} is set twice in [{}]", key, table.name));
+            }
+            self.skip_blank();
+            if self.next() != Some('=') {
+                return self.error(format!("expected '=' after {}", key));
+            }
+            self.skip_blank();
+            let value = self.value()?;
+            tables
+                .last_mut()
+                .expect("checked above")
+                .entries
+                .push(Entry { key, value, line });
+            self.end_of_line()?;
+        }
+    }
+
+    /// Reads a bare key of letters, digits, `_` and `-`.
+    fn key(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut key = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                break;
+            }
+            key.push(c);
+            self.next();
+        }
+        match self.chars.peek().copied() {
+            Some('.') => self.error("dotted keys are not supported"),
+            _ if !key.is_empty() => Ok(key),
+            Some('"' | '\'') => self.error("quoted keys are not supported"),
+            Some(c) => self.error(format!("expected a name, found '{}'", c)),
+            None => self.error("expected a name"),
+        }
+    }
+
+    fn value(&mut self) -> Result<Setting, Box<dyn Error>> {
+        match self.chars.peek().copied() {
+            Some('"') => self.basic_string().map(Setting::String),
+            Some('\'') => self.literal_string().map(Setting::String),
+            Some('[') => self.array(),
+            Some(c) if c.is_ascii_alphanumeric() || c == '+' || c == '-' => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || "+-_.:".contains(c)) {
+                        break;
+                    }
+                    word.push(c);
+                    self.next();
+                }
+                match word.as_str() {
+                    "true" => Ok(Setting::Boolean(true)),
+                    "false" => Ok(Setting::Boolean(false)),
+                    _ => match word.replace('_', "").parse() {
+                        Ok(n) => Ok(Setting::Integer(n)),
+                        Err(_) if word.contains(['.', 'e', 'E']) => self.error(format!(
+                            "{} is not a whole number; write decimals as strings, such as \"{}\"",
+                            word, word
+                        )),
+                        Err(_) => self.error(format!(
+                            "expected a value, found {} (strings need quotes)",
+                            word
+                        )),
+                    },
+                }
+            }
+            Some('\n') | None => self.error("expected a value"),
+            Some(c) => self.error(format!("expected a value, found '{}'", c)),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, Box<dyn Error>> {
+        self.next();
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex: String = (0..4).filter_map(|_| self.next()).collect();
+                            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                                Some(c) => c,
+                                None => return self.error(format!("invalid escape \\u{}", hex)),
+                            }
+                        }
+                        Some(c) => return self.error(format!("invalid escape \\{}", c)),
+                        None => return self.error("unterminated string"),
+                    };
+                    s.push(c);
+                }
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, Box<dyn Error>> {
+        self.next();
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(s),
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Reads an array, whose items may be spread over several lines.
+    fn array(&mut self) -> Result<Setting, Box<dyn Error>> {
+        self.next();
+        let mut items = Vec::new();
+        loop {
+            self.skip_lines();
+            if self.chars.peek() == Some(&']') {
+                self.next();
+                return Ok(Setting::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_lines();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Setting::Array(items)),
+                Some(c) => return self.error(format!("expected ',' or ']', found '{}'", c)),
+                None => return self.error("unterminated array"),
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Console module for the calculator application.
+//!
+//! This module holds the parts of the plain read-eval-print loop that do not need a
+//! terminal: the commands starting with `:` that change a session's settings, and a
+//! [`LineEditor`] with the line being edited and the history of the lines entered before
+//! it. The loop itself reads keys in raw mode and applies each one to the editor.
+
+use crate::money::Currency;
+use crate::rounding::Precision;
+use crate::session::{Entry, Session};
+use std::error::Error;
+
+/// The help shown by `:help`.
+pub const HELP: &str = "\
+Enter an expression, or `name = expression` to define a variable.
+Commands:
+  :vars              List the variables
+  :prec N            Show N decimal places
+  :sig N             Show N significant digits
+  :round NAME        half-even, half-up, toward-zero, floor or ceiling
+  :mode money [CUR]  Read plain numbers as amounts in CUR (default USD)
+  :mode plain        Read plain numbers as numbers
+  :reset             Forget every variable
+  :help              Show this help
+  :quit              Leave (also Ctrl-D)";
+
+/// What the loop does after a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// Print the text and read the next line.
+    Show(String),
+    /// Leave the loop.
+    Quit,
+}
+
+/// Returns how the result of a line is printed: after `=` or `≈`, as in the TUI, and after
+/// the variable it defined for an assignment.
+///
+/// ```
+/// use fermat::console::show;
+/// use fermat::session::Session;
+///
+/// let mut session = Session::default();
+/// let entry = session.run("x = 1 / 4").unwrap().unwrap();
+/// assert_eq!(show(&session, &entry), "x = 0.25");
+/// ```
+pub fn show(session: &Session, entry: &Entry) -> String {
+    let shown = session.evaluator().format_computed(&entry.computed);
+    match &entry.name {
+        Some(name) => format!("{} {}", name, shown),
+        None => shown,
+    }
+}
+
+/// Carries out a command, given without its `:`.
+///
+/// ```
+/// use fermat::console::{Reply, command};
+/// use fermat::session::Session;
+///
+/// let mut session = Session::default();
+/// assert_eq!(
+///     command(&mut session, "prec 2").unwrap(),
+///     Reply::Show("Showing 2 dp".to_string())
+/// );
+/// assert_eq!(command(&mut session, "quit").unwrap(), Reply::Quit);
+/// ```
+pub fn command(session: &mut Session, command: &str) -> Result<Reply, Box<dyn Error>> {
+    let mut words = command.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let argument = words.collect::<Vec<_>>().join(" ");
+    let mut rounding = session.evaluator().rounding();
+    let digits = || -> Result<u32, Box<dyn Error>> {
+        argument
+            .parse()
+            .map_err(|_| format!(":{} expects a whole number", name).into())
+    };
+    let text = match name {
+        "q" | "quit" | "exit" => return Ok(Reply::Quit),
+        "help" | "h" => HELP.to_string(),
+        "vars" => {
+            let evaluator = session.evaluator();
+            let lines: Vec<String> = session
+                .variables()
+                .map(|(name, computed)| format!("{} {}", name, evaluator.format_computed(computed)))
+                .collect();
+            if lines.is_empty() {
+                "No variables defined".to_string()
+            } else {
+                lines.join("\n")
+            }
+        }
+        "prec" => {
+            rounding.precision = Precision::DecimalPlaces(0).with_digits(digits()?);
+            session.evaluator_mut().set_rounding(rounding);
+            format!("Showing {}", rounding.precision)
+        }
+        "sig" => {
+            rounding.precision = Precision::SignificantDigits(1).with_digits(digits()?);
+            session.evaluator_mut().set_rounding(rounding);
+            format!("Showing {}", rounding.precision)
+        }
+        "round" => {
+            rounding.rounding = argument.parse()?;
+            session.evaluator_mut().set_rounding(rounding);
+            format!("Rounding {}", rounding.rounding)
+        }
+        "mode" => match argument.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["money", rest @ ..] if rest.len() <= 1 => {
+                let code = rest.first().copied().unwrap_or("USD");
+                let currency = Currency::new(&code.to_ascii_uppercase())
+                    .ok_or_else(|| format!("Unknown currency: {}", code))?;
+                session.evaluator_mut().set_money(Some(currency));
+                format!("Money mode in {}", currency)
+            }
+            ["plain"] => {
+                session.evaluator_mut().set_money(None);
+                "Money mode off".to_string()
+            }
+            ["deg" | "rad" | "grad"] => {
+                return Err("Angle modes are not supported: the calculator has no \
+                            trigonometric functions"
+                    .into());
+            }
+            _ => return Err(":mode expects money [CURRENCY] or plain".into()),
+        },
+        "reset" => {
+            session.reset();
+            "Variables cleared".to_string()
+        }
+        _ => return Err(format!("Unknown command :{} (try :help)", name).into()),
+    };
+    Ok(Reply::Show(text))
+}
+
+/// A line being edited, with the lines entered before it.
+///
+/// Each method is one edit, such as a key of the terminal would make: moving the cursor,
+/// inserting or deleting characters, or recalling a line from the history.
+///
+/// ```
+/// use fermat::console::LineEditor;
+///
+/// let mut editor = LineEditor::new();
+/// "1 + 2".chars().for_each(|c| editor.insert(c));
+/// assert_eq!(editor.finish(), "1 + 2");
+/// editor.older();
+/// assert_eq!(editor.line(), "1 + 2");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LineEditor {
+    buffer: Vec<char>,
+    cursor: usize,
+    history: Vec<String>,
+    /// Position in the history of the recalled line, or its length below the history
+    recalled: usize,
+    /// The unfinished line to return to below the history
+    draft: Vec<char>,
+}
+
+impl LineEditor {
+    /// Creates an editor with an empty line and no history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the line being edited.
+    pub fn line(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    /// Returns the position of the cursor, in characters from the start of the line.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Returns whether the line being edited is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns the lines entered, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Inserts a character at the cursor.
+    pub fn insert(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    /// Deletes the character before the cursor.
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    /// Deletes the character at the cursor.
+    pub fn delete(&mut self) {
+        if self.cursor < self.buffer.len() {
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    /// Moves the cursor one character to the left.
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    /// Moves the cursor one character to the right.
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.buffer.len());
+    }
+
+    /// Moves the cursor to the start of the line.
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Moves the cursor to the end of the line.
+    pub fn end(&mut self) {
+        self.cursor = self.buffer.len();
+    }
+
+    /// Deletes from the start of the line to the cursor.
+    pub fn delete_to_start(&mut self) {
+        self.buffer.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    /// Deletes from the cursor to the end of the line.
+    pub fn delete_to_end(&mut self) {
+        self.buffer.truncate(self.cursor);
+    }
+
+    /// Deletes the word before the cursor and the spaces after it.
+    pub fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.buffer[start - 1] == ' ' {
+            start -= 1;
+        }
+        while start > 0 && self.buffer[start - 1] != ' ' {
+            start -= 1;
+        }
+        self.buffer.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Replaces the line with the one entered before the line shown, keeping an unfinished
+    /// line to return to.
+    pub fn older(&mut self) {
+        if self.recalled == 0 {
+            return;
+        }
+        if self.recalled == self.history.len() {
+            self.draft = self.buffer.clone();
+        }
+        self.recalled -= 1;
+        self.buffer = self.history[self.recalled].chars().collect();
+        self.cursor = self.buffer.len();
+    }
+
+    /// Replaces the line with the one entered after the line shown, or with the unfinished
+    /// line below the history.
+    pub fn newer(&mut self) {
+        if self.recalled == self.history.len() {
+            return;
+        }
+        self.recalled += 1;
+        self.buffer = match self.history.get(self.recalled) {
+            Some(line) => line.chars().collect(),
+            None => self.draft.clone(),
+        };
+        self.cursor = self.buffer.len();
+    }
+
+    /// Discards the line, starting an empty one below the history.
+    pub fn cancel(&mut self) {
+        self.buffer.clear();
+        self.draft.clear();
+        self.cursor = 0;
+        self.recalled = self.history.len();
+    }
+
+    /// Enters the line, returning it and starting an empty one.
+    ///
+    /// The line is added to the history unless it is blank or repeats the last line.
+    pub fn finish(&mut self) -> String {
+        let line = self.line();
+        self.remember(&line);
+        self.cancel();
+        line
+    }
+
+    /// Adds a line read some other way to the history, unless it is blank or repeats the
+    /// last line.
+    pub fn remember(&mut self, line: &str) {
+        if !line.trim().is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        self.recalled = self.history.len();
+    }
+}
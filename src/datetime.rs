@@ -0,0 +1,222 @@
+//! Date and time module for the calculator application.
+//!
+//! This module implements the calendar arithmetic behind date, time and duration values.
+//! Dates use the proleptic Gregorian calendar and are counted in days since 1970-01-01;
+//! times of day and durations are counted in seconds. Times are naive: no time zone is
+//! attached, and the system clock is read in UTC.
+
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of seconds in a day.
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Earliest supported date, 0001-01-01, in days since the epoch.
+const MIN_DAYS: i64 = -719_162;
+
+/// Latest supported date, 9999-12-31, in days since the epoch.
+const MAX_DAYS: i64 = 2_932_896;
+
+/// The abbreviated weekdays dates are shown with, starting on Monday.
+pub(crate) const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// A unit of time that a duration can be written in or converted to.
+///
+/// Months and years are not included, as their length depends on the date they start at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+}
+
+impl TimeUnit {
+    /// Looks up a unit by any of its accepted spellings, such as `h`, `hr` or `hours`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let unit = match name {
+            "s" | "sec" | "secs" | "second" | "seconds" => TimeUnit::Second,
+            "min" | "mins" | "minute" | "minutes" => TimeUnit::Minute,
+            "h" | "hr" | "hrs" | "hour" | "hours" => TimeUnit::Hour,
+            "d" | "day" | "days" => TimeUnit::Day,
+            "w" | "wk" | "wks" | "week" | "weeks" => TimeUnit::Week,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// Returns the length of the unit in seconds.
+    pub fn seconds(self) -> Decimal {
+        Decimal::from(match self {
+            TimeUnit::Second => 1,
+            TimeUnit::Minute => 60,
+            TimeUnit::Hour => 3_600,
+            TimeUnit::Day => SECONDS_PER_DAY,
+            TimeUnit::Week => 7 * SECONDS_PER_DAY,
+        })
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TimeUnit::Second => "seconds",
+            TimeUnit::Minute => "minutes",
+            TimeUnit::Hour => "hours",
+            TimeUnit::Day => "days",
+            TimeUnit::Week => "weeks",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the number of days in the given month.
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+/// Returns the days since 1970-01-01 of a calendar date, or `None` if the date is invalid.
+pub fn date(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year, month)
+    {
+        return None;
+    }
+    // Count from 0000-03-01 so that the leap day falls at the end of each year
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let m = month as i64;
+    let day_of_year = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+/// Returns the `(year, month, day)` of a date given in days since 1970-01-01.
+pub fn civil(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Returns the ISO weekday of a date: 1 for Monday through 7 for Sunday.
+pub fn weekday(days: i64) -> u32 {
+    // 1970-01-01 was a Thursday
+    ((days + 3).rem_euclid(7) + 1) as u32
+}
+
+/// Returns the time of day in seconds, or `None` if any component is out of range.
+pub fn time(hour: u32, minute: u32, second: u32) -> Option<i64> {
+    if hour >= 24 || minute >= 60 || second >= 60 {
+        return None;
+    }
+    Some(hour as i64 * 3_600 + minute as i64 * 60 + second as i64)
+}
+
+/// Checks that a date, in days since the epoch, lies within years 1 to 9999.
+pub fn check_date(days: i64) -> Result<i64, Box<dyn Error>> {
+    if (MIN_DAYS..=MAX_DAYS).contains(&days) {
+        Ok(days)
+    } else {
+        Err("Date out of range".into())
+    }
+}
+
+/// Returns the current time in seconds since 1970-01-01 00:00 UTC.
+pub fn system_now() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    }
+}
+
+/// Formats a date as `YYYY-MM-DD`.
+pub fn format_date(days: i64) -> String {
+    let (year, month, day) = civil(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats a date with its abbreviated weekday, as in `Fri 2026-10-16`.
+pub fn format_date_with_weekday(days: i64) -> String {
+    format!(
+        "{} {}",
+        WEEKDAYS[weekday(days) as usize - 1],
+        format_date(days)
+    )
+}
+
+/// Formats a time of day as `HH:MM`, or `HH:MM:SS` when the seconds are not zero.
+pub fn format_time(seconds: i64) -> String {
+    let (hour, minute, second) = (seconds / 3_600, seconds / 60 % 60, seconds % 60);
+    if second == 0 {
+        format!("{:02}:{:02}", hour, minute)
+    } else {
+        format!("{:02}:{:02}:{:02}", hour, minute, second)
+    }
+}
+
+/// Splits a date and time, in seconds since the epoch, into days and seconds of the day.
+pub fn split_datetime(seconds: i64) -> (i64, i64) {
+    (
+        seconds.div_euclid(SECONDS_PER_DAY),
+        seconds.rem_euclid(SECONDS_PER_DAY),
+    )
+}
+
+/// Formats a duration as days, hours, minutes and seconds, such as `3d 4h`.
+///
+/// Zero components are left out, and a negative duration with several components is
+/// parenthesised so that the output reads back as the same value. `seconds` formats the
+/// (possibly fractional) seconds component.
+pub fn format_duration(total: Decimal, seconds: impl Fn(Decimal) -> String) -> String {
+    let magnitude = total.abs();
+    let whole = magnitude.trunc();
+    let mut parts = Vec::new();
+    let mut rest = whole;
+    for (unit, suffix) in [
+        (TimeUnit::Day, "d"),
+        (TimeUnit::Hour, "h"),
+        (TimeUnit::Minute, "min"),
+    ] {
+        let count = (rest / unit.seconds()).trunc();
+        if !count.is_zero() {
+            parts.push(format!("{}{}", count, suffix));
+            rest -= count * unit.seconds();
+        }
+    }
+    let rest = seconds(rest + (magnitude - whole));
+    if rest != "0" || parts.is_empty() {
+        parts.push(format!("{}s", rest));
+    }
+
+    let joined = parts.join(" ");
+    match (total < Decimal::ZERO, parts.len()) {
+        (false, _) => joined,
+        (true, 1) => format!("-{}", joined),
+        (true, _) => format!("-({})", joined),
+    }
+}
+
+/// Converts a whole number of seconds to `i64`, reporting out of range values.
+pub fn whole_seconds(seconds: Decimal) -> Result<i64, Box<dyn Error>> {
+    seconds.to_i64().ok_or_else(|| "Date out of range".into())
+}
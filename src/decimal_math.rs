@@ -0,0 +1,310 @@
+//! Decimal math module for the calculator application.
+//!
+//! This module implements transcendental functions directly on `Decimal`, so that results
+//! carry the full 28 digits of precision instead of the ~16 an `f64` round-trip would give.
+//! Domain violations and overflow are reported as errors rather than as NaN or infinity.
+
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use rust_decimal_macros::dec;
+use std::error::Error;
+
+/// Euler's number rounded to the 28 decimal places a `Decimal` can hold.
+const E: Decimal = dec!(2.7182818284590452353602874714);
+
+/// ln(2) rounded to the 28 decimal places a `Decimal` can hold.
+const LN_2: Decimal = dec!(0.6931471805599453094172321215);
+
+/// Largest argument for which `exp` fits in a `Decimal` (ln of `Decimal::MAX`).
+const MAX_EXP: Decimal = dec!(66.542129333754749704054283659);
+
+/// Below this argument `exp` is smaller than the smallest positive `Decimal`.
+const MIN_EXP: Decimal = dec!(-66);
+
+/// Upper bound on series and Newton iterations, as a guard against non-convergence.
+const MAX_ITERATIONS: usize = 200;
+
+/// Sums a power series `Σ term_k`, where each term is derived from the previous one.
+///
+/// Stops once a term no longer changes the sum at `Decimal` precision.
+fn sum_series(first: Decimal, mut next: impl FnMut(Decimal, usize) -> Decimal) -> Decimal {
+    let mut sum = first;
+    let mut term = first;
+    for k in 1..MAX_ITERATIONS {
+        term = next(term, k);
+        let new_sum = sum + term;
+        if new_sum == sum {
+            break;
+        }
+        sum = new_sum;
+    }
+    sum
+}
+
+/// Returns `e^x`.
+pub fn exp(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x.is_zero() {
+        return Ok(Decimal::ONE);
+    }
+    if x < Decimal::ZERO {
+        if x < MIN_EXP {
+            return Ok(Decimal::ZERO);
+        }
+        return Ok(Decimal::ONE / exp(-x)?);
+    }
+    if x > MAX_EXP {
+        return Err("Result too large".into());
+    }
+
+    // e^x = e^whole * e^frac, with the fractional part summed as a Taylor series
+    let whole = x.trunc();
+    let frac = x - whole;
+    let mut result = sum_series(Decimal::ONE, |term, k| term * frac / Decimal::from(k));
+
+    let mut base = E;
+    let mut n = whole.to_u32().ok_or("Result too large")?;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result.checked_mul(base).ok_or("Result too large")?;
+        }
+        n >>= 1;
+        if n > 0 {
+            base = base.checked_mul(base).ok_or("Result too large")?;
+        }
+    }
+    Ok(result)
+}
+
+/// Returns the natural logarithm of `x`.
+pub fn ln(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x <= Decimal::ZERO {
+        return Err("ln is only defined for positive numbers".into());
+    }
+
+    // Scale x by powers of two into [1, 2), then ln(y) = 2 * atanh((y - 1) / (y + 1))
+    let two = Decimal::TWO;
+    let mut y = x;
+    let mut k = 0i64;
+    while y >= two {
+        y /= two;
+        k += 1;
+    }
+    while y < Decimal::ONE {
+        y *= two;
+        k -= 1;
+    }
+    let z = (y - Decimal::ONE) / (y + Decimal::ONE);
+    let z2 = z * z;
+    let mut power = z;
+    let series = sum_series(z, |_, k| {
+        power *= z2;
+        power / Decimal::from(2 * k + 1)
+    });
+    Ok(two * series + LN_2 * Decimal::from(k))
+}
+
+/// Returns the square root of a non-negative `x`.
+pub fn sqrt(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x < Decimal::ZERO {
+        return Err("Cannot compute square root of negative number".into());
+    }
+    root(x, 2)
+}
+
+/// Returns the cube root of `x`, which is negative for negative `x`.
+pub fn cbrt(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    root(x, 3)
+}
+
+/// Returns the `n`th root of `x` using Newton's method.
+///
+/// A negative `x` only has a real root of odd degree. Roots that are short enough to be
+/// represented exactly, such as `sqrt(2.25) = 1.5`, are returned exactly.
+pub fn root(x: Decimal, n: u32) -> Result<Decimal, Box<dyn Error>> {
+    if n == 0 {
+        return Err("The root of degree 0 is undefined".into());
+    }
+    if x < Decimal::ZERO {
+        if n.is_multiple_of(2) {
+            return Err("Cannot compute an even root of a negative number".into());
+        }
+        return root(-x, n).map(|r| -r);
+    }
+    if x.is_zero() || n == 1 {
+        return Ok(x);
+    }
+    // Iterate on a number of at least one, whose powers keep full relative precision
+    let result = if x < Decimal::ONE {
+        Decimal::ONE
+            .checked_div(newton_root(Decimal::ONE / x, n)?)
+            .ok_or("Result too large")?
+    } else {
+        newton_root(x, n)?
+    };
+    Ok(exact_root(result, x, n).unwrap_or(result))
+}
+
+/// Finds the `n`th root of `x >= 1` with Newton's method, seeded from `f64`.
+fn newton_root(x: Decimal, n: u32) -> Result<Decimal, Box<dyn Error>> {
+    let degree = Decimal::from(n);
+    let mut guess = x
+        .to_f64()
+        .and_then(|f| Decimal::from_f64(f.powf(1.0 / n as f64)))
+        .filter(|g| *g >= Decimal::ONE)
+        .unwrap_or(Decimal::ONE);
+    let mut before = guess;
+    for _ in 0..MAX_ITERATIONS {
+        // g' = ((n - 1) g + x / g^(n-1)) / n
+        let next = powi(guess, n - 1)
+            .and_then(|power| x.checked_div(power))
+            .and_then(|quotient| {
+                (degree - Decimal::ONE)
+                    .checked_mul(guess)?
+                    .checked_add(quotient)
+            })
+            .map(|sum| sum / degree)
+            .ok_or("Result too large")?;
+        // Rounding can leave the iteration alternating between two neighbours
+        if next == guess || next == before {
+            break;
+        }
+        before = guess;
+        guess = next;
+    }
+    Ok(guess)
+}
+
+/// Returns the shortest rounding of `approx` whose `n`th power is exactly `x`, if any.
+fn exact_root(approx: Decimal, x: Decimal, n: u32) -> Option<Decimal> {
+    // An exact root with d decimal places has a power with n * d places, at most 28
+    (0..=28 / n).find_map(|places| {
+        let candidate = approx.round_dp(places).normalize();
+        is_root(candidate, x, n).then_some(candidate)
+    })
+}
+
+/// Returns whether `r` is exactly the `n`th root of `x`.
+pub(crate) fn is_root(r: Decimal, x: Decimal, n: u32) -> bool {
+    // Only a power with at most 28 digits is computed without rounding
+    let r = r.normalize();
+    let digits = r.mantissa().unsigned_abs().checked_ilog10().unwrap_or(0) + 1;
+    digits.saturating_mul(n) <= 28 && powi(r, n) == Some(x)
+}
+
+/// Raises `base` to a non-negative integer power, or returns `None` on overflow.
+pub(crate) fn powi(base: Decimal, exponent: u32) -> Option<Decimal> {
+    let mut result = Decimal::ONE;
+    let mut base = base;
+    let mut exponent = exponent;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.checked_mul(base)?;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = base.checked_mul(base)?;
+        }
+    }
+    Some(result)
+}
+
+/// Returns the hyperbolic sine of `x`.
+pub fn sinh(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x.abs() < Decimal::ONE {
+        // The Taylor series avoids cancellation in e^x - e^-x near zero
+        let x2 = x * x;
+        return Ok(sum_series(x, |term, k| {
+            term * x2 / Decimal::from((2 * k) * (2 * k + 1))
+        }));
+    }
+    let ex = exp(x.abs())?;
+    let result = (ex - Decimal::ONE / ex) / Decimal::TWO;
+    Ok(if x < Decimal::ZERO { -result } else { result })
+}
+
+/// Returns the hyperbolic cosine of `x`.
+pub fn cosh(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    let ex = exp(x.abs())?;
+    Ok((ex + Decimal::ONE / ex) / Decimal::TWO)
+}
+
+/// Returns the hyperbolic tangent of `x`.
+pub fn tanh(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x.abs() < Decimal::ONE {
+        return Ok(sinh(x)? / cosh(x)?);
+    }
+    // tanh|x| = (1 - e^-2|x|) / (1 + e^-2|x|), which cannot overflow; past MAX_EXP the
+    // exponential is below Decimal precision anyway
+    let e = exp(-Decimal::TWO * x.abs().min(MAX_EXP))?;
+    let result = (Decimal::ONE - e) / (Decimal::ONE + e);
+    Ok(if x < Decimal::ZERO { -result } else { result })
+}
+
+/// Returns the hyperbolic secant of `x`.
+pub fn sech(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x.abs() > MAX_EXP {
+        return Ok(Decimal::ZERO);
+    }
+    Ok(Decimal::ONE / cosh(x)?)
+}
+
+/// Returns the hyperbolic cosecant of `x`.
+pub fn csch(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x.is_zero() {
+        return Err("csch is undefined at 0".into());
+    }
+    if x.abs() > MAX_EXP {
+        return Ok(Decimal::ZERO);
+    }
+    Ok(Decimal::ONE / sinh(x)?)
+}
+
+/// Returns the hyperbolic cotangent of `x`.
+pub fn coth(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x.is_zero() {
+        return Err("coth is undefined at 0".into());
+    }
+    Ok(Decimal::ONE / tanh(x)?)
+}
+
+/// Threshold above which `x^2` is dropped next to 1 in the inverse functions.
+const LARGE_ARGUMENT: Decimal = dec!(1e10);
+
+/// Returns the inverse hyperbolic sine of `x`.
+pub fn asinh(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    let a = x.abs();
+    let result = if a > LARGE_ARGUMENT {
+        // ln(2a) + 1/(4a^2), avoiding overflow in a^2
+        ln(a)? + LN_2 + Decimal::ONE / a / a / dec!(4)
+    } else {
+        ln(a + sqrt(a * a + Decimal::ONE)?)?
+    };
+    Ok(if x < Decimal::ZERO { -result } else { result })
+}
+
+/// Returns the inverse hyperbolic cosine of `x`, defined for `x >= 1`.
+pub fn acosh(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x < Decimal::ONE {
+        return Err("acosh is only defined for x >= 1".into());
+    }
+    if x > LARGE_ARGUMENT {
+        return Ok(ln(x)? + LN_2 - Decimal::ONE / x / x / dec!(4));
+    }
+    ln(x + sqrt(x * x - Decimal::ONE)?)
+}
+
+/// Returns the inverse hyperbolic tangent of `x`, defined for `-1 < x < 1`.
+pub fn atanh(x: Decimal) -> Result<Decimal, Box<dyn Error>> {
+    if x.abs() >= Decimal::ONE {
+        return Err("atanh is only defined for -1 < x < 1".into());
+    }
+    if x.abs() < dec!(0.5) {
+        let x2 = x * x;
+        let mut power = x;
+        return Ok(sum_series(x, |_, k| {
+            power *= x2;
+            power / Decimal::from(2 * k + 1)
+        }));
+    }
+    Ok(ln((Decimal::ONE + x) / (Decimal::ONE - x))? / Decimal::TWO)
+}
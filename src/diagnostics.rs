@@ -0,0 +1,98 @@
+//! Diagnostics module for the calculator application.
+//!
+//! This module describes problems found in an expression. Each diagnostic points at the
+//! part of the input it concerns and may suggest a fix, and a set of diagnostics can be
+//! rendered as a line of carets under the input.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+/// A problem found in an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// What is wrong.
+    pub message: String,
+    /// Byte range of the input the problem concerns.
+    ///
+    /// Empty when it marks the position where something is missing.
+    pub span: Range<usize>,
+    /// A suggested fix, if one is known.
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic without a suggested fix.
+    pub fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Diagnostic {
+            message: message.into(),
+            span,
+            suggestion: None,
+        }
+    }
+
+    /// Adds a suggested fix.
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.suggestion {
+            Some(suggestion) => write!(f, "{} ({})", self.message, suggestion),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for Diagnostic {}
+
+/// Returns the column of a byte offset, counting characters rather than bytes.
+fn column(input: &str, offset: usize) -> usize {
+    match input.get(..offset) {
+        Some(prefix) => prefix.chars().count(),
+        None => input.chars().count(),
+    }
+}
+
+/// Renders a line with carets under the part of `input` each diagnostic points at.
+///
+/// A diagnostic with an empty span gets a single caret at its position, which may be just
+/// past the end of the input.
+pub fn caret_line(input: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut line = vec![' '; input.chars().count() + 1];
+    for diagnostic in diagnostics {
+        let start = column(input, diagnostic.span.start).min(line.len() - 1);
+        let end = column(input, diagnostic.span.end).clamp(start + 1, line.len());
+        line[start..end].fill('^');
+    }
+    line.into_iter().collect::<String>().trim_end().to_string()
+}
+
+/// Returns the candidate closest to `name`, if it is close enough to be a likely typo.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
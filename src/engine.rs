@@ -0,0 +1,277 @@
+//! Engine module for the calculator application.
+//!
+//! This module is the high-level entry point for embedding the calculator. An [`Evaluator`]
+//! holds every setting an evaluation depends on, is configured through an
+//! [`EvaluatorBuilder`], and turns an input string straight into a [`Value`] or an
+//! [`EvalError`] that tells syntax problems, exceeded limits and evaluation failures apart.
+
+use crate::diagnostics::Diagnostic;
+use crate::evaluator::{self, EvalOptions};
+use crate::functions::Registry;
+use crate::limits::{LimitError, Limits};
+use crate::money::{Currency, RateTable};
+use crate::rounding::{Precision, Rounding, RoundingContext};
+use crate::value::{Computed, Value};
+use std::error::Error;
+use std::fmt;
+
+/// Evaluates expressions under a fixed set of options.
+///
+/// ```
+/// use fermat::engine::Evaluator;
+/// use fermat::rounding::Precision;
+///
+/// let evaluator = Evaluator::builder()
+///     .precision(Precision::DecimalPlaces(3))
+///     .build();
+/// let value = evaluator.eval("1 / 3").unwrap();
+/// assert_eq!(evaluator.format(&value), "0.333");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Evaluator {
+    rounding: RoundingContext,
+    limits: Limits,
+    money: Option<Currency>,
+    rates: Option<RateTable>,
+    now: Option<i64>,
+    functions: Registry,
+}
+
+impl Evaluator {
+    /// Creates an evaluator with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts configuring an evaluator.
+    pub fn builder() -> EvaluatorBuilder {
+        EvaluatorBuilder::default()
+    }
+
+    /// Evaluates an expression.
+    pub fn eval(&self, input: &str) -> Result<Value, EvalError> {
+        self.compute(input).map(|computed| computed.value)
+    }
+
+    /// Evaluates an expression, also reporting whether the result is exact.
+    pub fn compute(&self, input: &str) -> Result<Computed, EvalError> {
+        self.limits.check_input(input)?;
+        let diagnostics = self.diagnose(input);
+        if !diagnostics.is_empty() {
+            return Err(EvalError::Syntax(diagnostics));
+        }
+        let options = EvalOptions {
+            rounding: self.rounding,
+            now: self.now,
+            money: self.money,
+            rates: self.rates.as_ref(),
+            limits: self.limits,
+        };
+        let tokens = evaluator::tokenize_with_functions(input, &self.limits, &self.functions)
+            .map_err(EvalError::from)?;
+        evaluator::compute(&tokens, &options).map_err(EvalError::from)
+    }
+
+    /// Checks an expression for syntax problems, reporting all of them.
+    pub fn diagnose(&self, input: &str) -> Vec<Diagnostic> {
+        evaluator::diagnose_with_functions(input, &self.functions)
+    }
+
+    /// Completes an unfinished expression so that a preview of its result can be shown.
+    ///
+    /// See [`evaluator::complete`].
+    pub fn complete(&self, input: &str) -> Option<String> {
+        evaluator::complete_with_functions(input, &self.functions)
+    }
+
+    /// Formats a value with the evaluator's display precision and rounding strategy.
+    pub fn format(&self, value: &Value) -> String {
+        value.format(&self.rounding)
+    }
+
+    /// Formats a computed value after `=` if it is shown exactly, or after `≈` otherwise.
+    pub fn format_computed(&self, computed: &Computed) -> String {
+        computed.format(&self.rounding)
+    }
+
+    /// Returns the names starting with `prefix` that an expression may use, in order of
+    /// name, with what each one is: a `function`, a `variable`, or a `value` for `today`
+    /// and `now`.
+    ///
+    /// ```
+    /// use fermat::engine::Evaluator;
+    ///
+    /// let evaluator = Evaluator::new();
+    /// assert_eq!(evaluator.completions("sq"), vec![("sqrt", "function")]);
+    /// ```
+    pub fn completions(&self, prefix: &str) -> Vec<(&str, &'static str)> {
+        let mut names: Vec<_> = evaluator::names(&self.functions)
+            .filter(|(name, _)| name.starts_with(prefix))
+            .collect();
+        names.sort_by_key(|(name, _)| *name);
+        names.dedup_by_key(|(name, _)| *name);
+        names
+    }
+
+    /// Returns the display precision and rounding strategy.
+    pub fn rounding(&self) -> RoundingContext {
+        self.rounding
+    }
+
+    /// Changes the display precision and rounding strategy.
+    pub fn set_rounding(&mut self, rounding: RoundingContext) {
+        self.rounding = rounding;
+    }
+
+    /// Returns the resource limits.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Returns the money mode currency, if money mode is on.
+    pub fn money(&self) -> Option<Currency> {
+        self.money
+    }
+
+    /// Turns money mode on with the given currency, or off with `None`.
+    pub fn set_money(&mut self, money: Option<Currency>) {
+        self.money = money;
+    }
+
+    /// Replaces the exchange rates used for currency conversions.
+    pub fn set_rates(&mut self, rates: Option<RateTable>) {
+        self.rates = rates;
+    }
+
+    /// Returns the functions added or removed by the host application.
+    pub fn functions(&self) -> &Registry {
+        &self.functions
+    }
+
+    /// Returns the function registry for adding, overriding or removing functions.
+    pub fn functions_mut(&mut self) -> &mut Registry {
+        &mut self.functions
+    }
+}
+
+/// Configures an [`Evaluator`].
+///
+/// Every setting starts at the same default as [`Evaluator::new`].
+#[derive(Debug, Clone, Default)]
+pub struct EvaluatorBuilder {
+    evaluator: Evaluator,
+}
+
+impl EvaluatorBuilder {
+    /// Sets the display precision.
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.evaluator.rounding.precision = precision;
+        self
+    }
+
+    /// Sets the rounding strategy for display and inexact intermediate results.
+    pub fn rounding(mut self, rounding: Rounding) -> Self {
+        self.evaluator.rounding.rounding = rounding;
+        self
+    }
+
+    /// Sets the resource limits.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.evaluator.limits = limits;
+        self
+    }
+
+    /// Turns on money mode, in which plain numbers are amounts in `currency`.
+    pub fn money(mut self, currency: Currency) -> Self {
+        self.evaluator.money = Some(currency);
+        self
+    }
+
+    /// Sets the exchange rates used for currency conversions.
+    pub fn rates(mut self, rates: RateTable) -> Self {
+        self.evaluator.rates = Some(rates);
+        self
+    }
+
+    /// Fixes the moment `now` and `today` refer to, in seconds since 1970-01-01 00:00.
+    ///
+    /// Without it the system clock is read at every evaluation.
+    pub fn now(mut self, now: i64) -> Self {
+        self.evaluator.now = Some(now);
+        self
+    }
+
+    /// Sets the functions added or removed on top of the built-in ones.
+    pub fn functions(mut self, functions: Registry) -> Self {
+        self.evaluator.functions = functions;
+        self
+    }
+
+    /// Creates the configured evaluator.
+    pub fn build(self) -> Evaluator {
+        self.evaluator
+    }
+}
+
+/// An error from evaluating an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// The expression is malformed; every problem found is listed.
+    Syntax(Vec<Diagnostic>),
+    /// A resource limit was exceeded.
+    Limit(LimitError),
+    /// The expression is well-formed but cannot be evaluated, as in `1 / 0`.
+    Evaluation(String),
+}
+
+impl EvalError {
+    /// Returns the kind of error as a single word: `syntax`, `limit` or `evaluation`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EvalError::Syntax(_) => "syntax",
+            EvalError::Limit(_) => "limit",
+            EvalError::Evaluation(_) => "evaluation",
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Syntax(diagnostics) => {
+                let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                f.write_str(&messages.join("; "))
+            }
+            EvalError::Limit(error) => write!(f, "{}", error),
+            EvalError::Evaluation(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for EvalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EvalError::Limit(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<LimitError> for EvalError {
+    fn from(error: LimitError) -> Self {
+        EvalError::Limit(error)
+    }
+}
+
+impl From<Box<dyn Error>> for EvalError {
+    /// Sorts an error from the low-level evaluator into the matching variant.
+    fn from(error: Box<dyn Error>) -> Self {
+        match error.downcast::<LimitError>() {
+            Ok(limit) => EvalError::Limit(*limit),
+            Err(error) => match error.downcast::<Diagnostic>() {
+                Ok(diagnostic) => EvalError::Syntax(vec![*diagnostic]),
+                Err(error) => EvalError::Evaluation(error.to_string()),
+            },
+        }
+    }
+}
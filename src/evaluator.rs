@@ -1,21 +1,35 @@
 //! Evaluator module for the calculator application.
 //!
 //! This module provides functionality to parse and evaluate mathematical expressions
-//! using Decimal arithmetic. It includes parsers for numbers and operators,
-//! and evaluates expressions with correct operator precedence.
+//! using Decimal arithmetic. It includes parsers for numbers, dates, times, durations and
+//! operators, and evaluates expressions with correct operator precedence.
 
+use crate::datetime::{self, SECONDS_PER_DAY, TimeUnit};
+use crate::decimal_math;
+use crate::diagnostics::{self, Diagnostic};
+use crate::functions::{Function, Registry};
+use crate::limits::{Deadline, Limits, Meter};
+use crate::money::{self, Currency, RateTable};
+use crate::number_theory;
+use crate::operators::{self, Associativity, Fixity, Operator};
+use crate::rounding::{Rounding, RoundingContext, round_places};
+use crate::value::{Computed, Value};
 use nom::{
     IResult, Parser,
     branch::alt,
-    character::complete::{char, digit1, space0},
+    bytes::complete::{take_while, take_while_m_n},
+    character::complete::{alpha1, char, digit1, space0},
     combinator::{opt, recognize},
-    multi::many0,
-    sequence::{delimited, pair},
+    error::ErrorKind,
+    sequence::{delimited, pair, preceded},
 };
 use rust_decimal::Decimal;
 use rust_decimal::prelude::*;
+use std::cell::Cell;
 use std::error::Error;
+use std::ops::Range;
 use std::str::FromStr;
+use std::sync::Arc;
 
 /// Enum representing a token in the mathematical expression.
 #[derive(Debug, Clone)]
@@ -26,6 +40,11 @@ pub enum Token {
     Plus,
     /// The '-' operator.
     Minus,
+    /// A minus sign before an operand other than a number, negating that operand.
+    ///
+    /// It binds tighter than any operator, as the sign of a negative number does, so
+    /// `-x^2` is read as `(-x)^2` just as `-2^2` is read as `(-2)^2`.
+    Negate,
     /// The '*' operator.
     Multiply,
     /// The '/' operator.
@@ -44,28 +63,253 @@ pub enum Token {
     RightParen,
     /// The '^' operator for exponentiation.
     Exponentiation,
+    /// The '±' (or '+-') operator attaching a standard uncertainty to a value.
+    PlusMinus,
+    /// A named function taking comma-separated arguments, such as `gcd`.
+    Function(&'static str),
+    /// A function added by the host application through a [`Registry`].
+    Custom(Arc<dyn Function>),
+    /// An operator added by the host application through a [`Registry`].
+    Operator(Arc<dyn Operator>),
+    /// A variable defined through a [`Registry`], with its value.
+    Variable(Computed),
+    /// A ',' separating function arguments.
+    Comma,
+    /// A date literal such as `2026-10-16`, in days since 1970-01-01.
+    Date(i64),
+    /// A time literal such as `14:30`, in seconds since midnight.
+    Time(i64),
+    /// A date followed by a time, in seconds since 1970-01-01 00:00.
+    DateTime(i64),
+    /// A duration literal such as `3d 4h` or `90min`, in seconds.
+    Duration(Decimal),
+    /// A unit after a parenthesised expression, as in `(1 + 2) days`.
+    Unit(TimeUnit),
+    /// The `in` conversion, as in `... in weeks`.
+    In(TimeUnit),
+    /// A money literal such as `$12.50` or `12.50 EUR`, rounded to the currency's scale.
+    Money(Decimal, Currency),
+    /// The `in` conversion between currencies, as in `... in EUR`.
+    InCurrency(Currency),
+    /// The current date.
+    Today,
+    /// The current date and time.
+    Now,
 }
 
+/// Options controlling how tokens are evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EvalOptions<'a> {
+    /// Precision and rounding strategy for inexact intermediate results.
+    pub rounding: RoundingContext,
+    /// The moment `now` and `today` refer to, in seconds since 1970-01-01 00:00.
+    ///
+    /// When `None`, the system clock is read (in UTC) at evaluation time.
+    pub now: Option<i64>,
+    /// Money mode: plain numbers are amounts in this currency.
+    ///
+    /// A plain number result is shown as money, and dividing a plain number by a whole
+    /// number allocates it into shares.
+    pub money: Option<Currency>,
+    /// Exchange rates used by `in` conversions between currencies.
+    pub rates: Option<&'a RateTable>,
+    /// Resources the evaluation may use.
+    pub limits: Limits,
+}
+
+/// Names of the built-in functions that take comma-separated arguments.
+const FUNCTIONS: &[&str] = &[
+    "gcd",
+    "lcm",
+    "is_prime",
+    "next_prime",
+    "totient",
+    "powmod",
+    "modinv",
+    "crt",
+    "fermat",
+    "sinh",
+    "cosh",
+    "tanh",
+    "asinh",
+    "acosh",
+    "atanh",
+    "sech",
+    "csch",
+    "coth",
+    "cbrt",
+    "root",
+    "round",
+    "floor",
+    "ceil",
+    "trunc",
+    "frac",
+    "interval",
+    "weekday",
+];
+
+/// Functions that only accept exact integers, so intervals and uncertainties are rejected.
+const INTEGER_FUNCTIONS: &[&str] = &[
+    "gcd",
+    "lcm",
+    "is_prime",
+    "next_prime",
+    "totient",
+    "powmod",
+    "modinv",
+    "crt",
+    "fermat",
+];
+
 // Parser combinators
-fn parse_keyword(input: &str) -> IResult<&str, Token> {
-    alt((
-        nom::bytes::complete::tag("sqrt").map(|_| Token::Sqrt),
-        nom::bytes::complete::tag("abs").map(|_| Token::Abs),
+fn parse_identifier(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        alpha1,
+        take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
     ))
     .parse(input)
 }
 
+fn parse_unit(input: &str) -> IResult<&str, TimeUnit> {
+    let (rest, name) = parse_identifier(input)?;
+    match TimeUnit::from_name(name) {
+        Some(unit) => Ok((rest, unit)),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Tag,
+        ))),
+    }
+}
+
+fn parse_keyword(input: &str) -> IResult<&str, Token> {
+    let (rest, name) = parse_identifier(input)?;
+    let token = match name {
+        "sqrt" => Token::Sqrt,
+        "abs" => Token::Abs,
+        "today" => Token::Today,
+        "now" => Token::Now,
+        "in" => {
+            return preceded(
+                space0,
+                alt((
+                    parse_unit.map(Token::In),
+                    parse_currency.map(Token::InCurrency),
+                )),
+            )
+            .parse(rest);
+        }
+        _ => match FUNCTIONS.iter().find(|f| **f == name) {
+            Some(f) => Token::Function(f),
+            None => return parse_unit.map(Token::Unit).parse(input),
+        },
+    };
+    Ok((rest, token))
+}
+
+fn parse_currency(input: &str) -> IResult<&str, Currency> {
+    let (rest, code) = parse_identifier(input)?;
+    match Currency::new(code) {
+        Some(currency) => Ok((rest, currency)),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Tag,
+        ))),
+    }
+}
+
+fn parse_decimal(input: &str) -> IResult<&str, &str> {
+    recognize(pair(digit1, opt(pair(char('.'), digit1)))).parse(input)
+}
+
 fn parse_number(input: &str) -> IResult<&str, Token> {
-    recognize(pair(
-        opt(char('-')),
-        pair(digit1, opt(pair(char('.'), digit1))),
+    let (rest, number) = parse_decimal(input)?;
+    match Decimal::from_str(number) {
+        Ok(n) => Ok((rest, Token::Number(n))),
+        Err(_) => Err(too_large_literal(input)),
+    }
+}
+
+/// Parses between `min` and `max` decimal digits as an integer.
+fn parse_digits<'a>(
+    min: usize,
+    max: usize,
+) -> impl Parser<&'a str, Output = u32, Error = nom::error::Error<&'a str>> {
+    take_while_m_n(min, max, |c: char| c.is_ascii_digit()).map_res(u32::from_str)
+}
+
+/// Rejects a literal that has the shape of a date or time but is not a valid one.
+fn invalid_literal(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Failure(nom::error::Error::new(input, ErrorKind::Verify))
+}
+
+/// Rejects a numeric literal that does not fit in a `Decimal`.
+fn too_large_literal(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Failure(nom::error::Error::new(input, ErrorKind::TooLarge))
+}
+
+fn parse_date(input: &str) -> IResult<&str, Token> {
+    let (rest, (year, _, month, _, day)) = (
+        parse_digits(4, 4),
+        char('-'),
+        parse_digits(2, 2),
+        char('-'),
+        parse_digits(2, 2),
+    )
+        .parse(input)?;
+    match datetime::date(year as i64, month, day) {
+        Some(days) => Ok((rest, Token::Date(days))),
+        None => Err(invalid_literal(input)),
+    }
+}
+
+fn parse_time(input: &str) -> IResult<&str, Token> {
+    let (rest, (hour, _, minute, second)) = (
+        parse_digits(1, 2),
+        char(':'),
+        parse_digits(2, 2),
+        opt(preceded(char(':'), parse_digits(2, 2))),
+    )
+        .parse(input)?;
+    match datetime::time(hour, minute, second.unwrap_or(0)) {
+        Some(seconds) => Ok((rest, Token::Time(seconds))),
+        None => Err(invalid_literal(input)),
+    }
+}
+
+fn parse_duration(input: &str) -> IResult<&str, Token> {
+    let (rest, (count, _, unit)) =
+        (parse_decimal.map_res(Decimal::from_str), space0, parse_unit).parse(input)?;
+    match count.checked_mul(unit.seconds()) {
+        Some(seconds) => Ok((rest, Token::Duration(seconds))),
+        None => Err(invalid_literal(input)),
+    }
+}
+
+fn parse_currency_symbol(input: &str) -> IResult<&str, Currency> {
+    alt((char('$'), char('€'), char('£'), char('¥')))
+        .map_opt(|c: char| Currency::from_symbol(c.encode_utf8(&mut [0; 4])))
+        .parse(input)
+}
+
+fn parse_money(input: &str) -> IResult<&str, Token> {
+    let (rest, (amount, currency)) = alt((
+        (parse_currency_symbol, parse_decimal).map(|(c, a)| (a, c)),
+        (parse_decimal, preceded(space0, parse_currency)),
     ))
-    .map(|num_str: &str| Token::Number(Decimal::from_str(num_str).unwrap()))
-    .parse(input)
+    .parse(input)?;
+    match Decimal::from_str(amount)
+        .ok()
+        .and_then(|a| money::round(a, currency).ok())
+    {
+        Some(amount) => Ok((rest, Token::Money(amount, currency))),
+        None => Err(too_large_literal(input)),
+    }
 }
 
 fn parse_operator(input: &str) -> IResult<&str, Token> {
     alt((
+        nom::bytes::complete::tag("+-").map(|_| Token::PlusMinus),
+        char('±').map(|_| Token::PlusMinus),
         char('+').map(|_| Token::Plus),
         char('-').map(|_| Token::Minus),
         char('*').map(|_| Token::Multiply),
@@ -75,6 +319,7 @@ fn parse_operator(input: &str) -> IResult<&str, Token> {
         char('^').map(|_| Token::Exponentiation),
         char('(').map(|_| Token::LeftParen),
         char(')').map(|_| Token::RightParen),
+        char(',').map(|_| Token::Comma),
     ))
     .parse(input)
 }
@@ -82,50 +327,544 @@ fn parse_operator(input: &str) -> IResult<&str, Token> {
 fn parse_token(input: &str) -> IResult<&str, Token> {
     delimited(
         space0,
-        alt((parse_keyword, parse_number, parse_operator)),
+        alt((
+            parse_date,
+            parse_time,
+            parse_duration,
+            parse_money,
+            parse_keyword,
+            parse_number,
+            parse_operator,
+        )),
         space0,
     )
     .parse(input)
 }
 
+/// Names that may be written before a '(' or on their own, for suggesting fixes to typos.
+fn known_names(functions: &Registry) -> impl Iterator<Item = &str> {
+    ["sqrt", "abs", "today", "now"]
+        .into_iter()
+        .chain(FUNCTIONS.iter().copied())
+        .filter(|name| !functions.is_removed(name))
+        .chain(functions.functions().map(|f| f.name()))
+        .chain(functions.variables().map(|(name, _)| name))
+}
+
+/// Names that may be written in an expression, with what each one is: a `function`, a
+/// `variable`, or a `value` for the clock names.
+pub(crate) fn names(functions: &Registry) -> impl Iterator<Item = (&str, &'static str)> {
+    ["sqrt", "abs"]
+        .into_iter()
+        .chain(FUNCTIONS.iter().copied())
+        .map(|name| (name, "function"))
+        .chain(["today", "now"].map(|name| (name, "value")))
+        .filter(|(name, _)| !functions.is_removed(name))
+        .chain(functions.functions().map(|f| (f.name(), "function")))
+        .chain(functions.variables().map(|(name, _)| (name, "variable")))
+}
+
+/// Returns whether a name is one of the built-in functions or clock names.
+fn is_builtin(name: &str) -> bool {
+    ["sqrt", "abs", "today", "now"].contains(&name) || FUNCTIONS.contains(&name)
+}
+
+/// Returns whether a name already means something in an expression, as a built-in
+/// function, a unit or the word `in`, so that it cannot name a variable.
+pub(crate) fn is_reserved(name: &str) -> bool {
+    is_builtin(name) || name == "in" || is_unit(name)
+}
+
+/// Returns whether a name is a duration unit, such as `w` or `min`.
+pub(crate) fn is_unit(name: &str) -> bool {
+    matches!(parse_token(name), Ok((rest, Token::Unit(_))) if rest.trim().is_empty())
+}
+
+/// Reports an unknown name, suggesting the closest known one. The name is reported as a
+/// function when `after`, the input following it, calls it.
+fn unknown_name(name: &str, span: Range<usize>, after: &str, functions: &Registry) -> Diagnostic {
+    let kind = if after.trim_start().starts_with('(') {
+        "function"
+    } else {
+        "name"
+    };
+    let problem = Diagnostic::new(format!("Unknown {}: {}", kind, name), span);
+    match diagnostics::closest(name, known_names(functions)) {
+        Some(known) => problem.with_suggestion(format!("did you mean '{}'?", known)),
+        None => problem,
+    }
+}
+
+/// A piece of the input read by [`lex`].
+pub(crate) enum Lexeme {
+    /// A valid token.
+    Token(Token),
+    /// An unknown name or malformed literal, standing where an operand would.
+    BadOperand,
+    /// A character that is not part of any token, standing where an operator would.
+    BadSymbol,
+}
+
+/// Splits the input into lexemes with the byte range each was read from.
+///
+/// Unreadable input does not stop the scan: each unreadable piece is reported and kept
+/// as a placeholder, so that all such problems are found in one pass. Names are resolved
+/// against the registry before the built-in ones.
+pub(crate) fn lex(
+    input: &str,
+    functions: &Registry,
+) -> (Vec<(Lexeme, Range<usize>)>, Vec<Diagnostic>) {
+    let mut tokens = Vec::new();
+    let mut problems = Vec::new();
+    let mut rest = input;
+    loop {
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            break;
+        }
+        let start = input.len() - trimmed.len();
+        if let Some(operator) = functions.operator_at(trimmed) {
+            let length = operator.symbol().len();
+            tokens.push((
+                Lexeme::Token(Token::Operator(operator.clone())),
+                start..start + length,
+            ));
+            rest = &trimmed[length..];
+            continue;
+        }
+        if let Ok((after, name)) = parse_identifier(trimmed) {
+            let span = start..start + name.len();
+            if let Some(function) = functions.get(name) {
+                tokens.push((Lexeme::Token(Token::Custom(function.clone())), span));
+                rest = after;
+                continue;
+            }
+            if let Some(computed) = functions.variable(name) {
+                tokens.push((Lexeme::Token(Token::Variable(*computed)), span));
+                rest = after;
+                continue;
+            }
+            if functions.is_removed(name) && is_builtin(name) {
+                problems.push(unknown_name(name, span.clone(), after, functions));
+                tokens.push((Lexeme::BadOperand, span));
+                rest = after;
+                continue;
+            }
+        }
+        match parse_token(trimmed) {
+            Ok((after, token)) => {
+                let text = trimmed[..trimmed.len() - after.len()].trim_end();
+                tokens.push((Lexeme::Token(token), start..start + text.len()));
+                rest = after;
+                continue;
+            }
+            Err(nom::Err::Failure(e)) if e.code == ErrorKind::Verify => {
+                let literal = trimmed.split_whitespace().next().unwrap_or_default();
+                problems.push(Diagnostic::new(
+                    format!("Invalid date, time or duration: {}", literal),
+                    start..start + literal.len(),
+                ));
+                rest = &trimmed[literal.len()..];
+            }
+            Err(nom::Err::Failure(e)) if e.code == ErrorKind::TooLarge => {
+                let literal = trimmed.split_whitespace().next().unwrap_or_default();
+                problems.push(Diagnostic::new(
+                    format!("Number too large: {}", literal),
+                    start..start + literal.len(),
+                ));
+                rest = &trimmed[literal.len()..];
+            }
+            Err(_) => match parse_identifier(trimmed) {
+                Ok((after, "in")) => {
+                    // A conversion to something that is neither a unit nor a currency
+                    let target = after.trim_start();
+                    let offset = input.len() - target.len();
+                    let (after, name) = parse_identifier(target).unwrap_or((target, ""));
+                    problems.push(
+                        Diagnostic::new(
+                            format!("Unknown unit or currency: {}", name),
+                            offset..offset + name.len(),
+                        )
+                        .with_suggestion(
+                            "use a unit such as 'days' or a currency code such as 'EUR'",
+                        ),
+                    );
+                    rest = after;
+                }
+                Ok((after, name)) => {
+                    problems.push(unknown_name(
+                        name,
+                        start..start + name.len(),
+                        after,
+                        functions,
+                    ));
+                    rest = after;
+                }
+                Err(_) => {
+                    let c = trimmed.chars().next().unwrap_or_default();
+                    problems.push(
+                        Diagnostic::new(
+                            format!("Unexpected character '{}'", c),
+                            start..start + c.len_utf8(),
+                        )
+                        .with_suggestion("remove it"),
+                    );
+                    rest = &trimmed[c.len_utf8()..];
+                    tokens.push((Lexeme::BadSymbol, start..input.len() - rest.len()));
+                    continue;
+                }
+            },
+        }
+        tokens.push((Lexeme::BadOperand, start..input.len() - rest.len()));
+    }
+    (tokens, problems)
+}
+
+/// Checks an expression for syntax problems, reporting all of them rather than the first.
+///
+/// Each diagnostic carries the span of the input it concerns and, where possible, a
+/// suggested fix. Problems that only show up during evaluation, such as division by zero,
+/// are not reported. An empty input has no problems.
+pub fn diagnose(input: &str) -> Vec<Diagnostic> {
+    diagnose_with_functions(input, &Registry::new())
+}
+
+/// Checks an expression for syntax problems, resolving names against a function registry.
+pub fn diagnose_with_functions(input: &str, functions: &Registry) -> Vec<Diagnostic> {
+    let (tokens, mut problems) = lex(input, functions);
+    let text = |span: &Range<usize>| &input[span.clone()];
+
+    // Spans of the open parentheses, and whether each belongs to a function call
+    let mut open: Vec<(Range<usize>, bool)> = Vec::new();
+    let mut expect_operand = true;
+    let mut pending_call: Option<Range<usize>> = None;
+    let mut previous: Option<&Token> = None;
+    let mut previous_unreadable = false;
+
+    for (token, span) in &tokens {
+        if let Some(call) = pending_call.take()
+            && !matches!(token, Lexeme::Token(Token::LeftParen))
+        {
+            let name = text(&call);
+            problems.push(
+                Diagnostic::new(format!("Expected '(' after {}", name), call.end..call.end)
+                    .with_suggestion(format!("write {}(...)", name)),
+            );
+        }
+        // Placeholders were already reported; they stand in for an operand or an operator
+        // so that they cause no further problems
+        let token = match token {
+            Lexeme::Token(token) => token,
+            Lexeme::BadOperand => {
+                expect_operand = false;
+                previous = None;
+                previous_unreadable = true;
+                continue;
+            }
+            Lexeme::BadSymbol => {
+                expect_operand = true;
+                previous = None;
+                previous_unreadable = false;
+                continue;
+            }
+        };
+        let missing_operator = || {
+            Diagnostic::new(
+                format!("Missing operator before '{}'", text(span)),
+                span.start..span.start,
+            )
+            .with_suggestion("insert an operator such as '*'")
+        };
+        let missing_operand = || {
+            Diagnostic::new(
+                format!("Missing operand before '{}'", text(span)),
+                span.clone(),
+            )
+            .with_suggestion(format!("add a value before '{}' or remove it", text(span)))
+        };
+
+        match token {
+            Token::Number(_)
+            | Token::Date(_)
+            | Token::Time(_)
+            | Token::DateTime(_)
+            | Token::Duration(_)
+            | Token::Money(..)
+            | Token::Variable(_)
+            | Token::Today
+            | Token::Now => {
+                // Adjacent durations, or a date and a time, form a single value
+                let joined = matches!(
+                    (previous, token),
+                    (Some(Token::Duration(_)), Token::Duration(_))
+                        | (Some(Token::Date(_)), Token::Time(_))
+                );
+                if !expect_operand && !joined {
+                    problems.push(missing_operator());
+                }
+                expect_operand = false;
+            }
+            Token::Sqrt | Token::Abs | Token::Function(_) | Token::Custom(_) => {
+                if !expect_operand {
+                    problems.push(missing_operator());
+                }
+                pending_call = Some(span.clone());
+                expect_operand = true;
+            }
+            Token::LeftParen => {
+                let is_call = matches!(previous, Some(Token::Function(_) | Token::Custom(_)))
+                    || previous_unreadable;
+                if !expect_operand && !is_call {
+                    problems.push(missing_operator());
+                }
+                open.push((span.clone(), is_call));
+                expect_operand = true;
+            }
+            Token::RightParen => {
+                if open.pop().is_none() {
+                    problems.push(
+                        Diagnostic::new("Unmatched ')'", span.clone())
+                            .with_suggestion("remove it or add a matching '('"),
+                    );
+                    continue;
+                }
+                if expect_operand {
+                    problems.push(missing_operand());
+                }
+                expect_operand = false;
+            }
+            Token::Comma => {
+                if !open.last().is_some_and(|(_, is_call)| *is_call) {
+                    problems.push(
+                        Diagnostic::new("Unexpected ',' outside of a function call", span.clone())
+                            .with_suggestion("remove it"),
+                    );
+                } else if expect_operand {
+                    problems.push(missing_operand());
+                }
+                expect_operand = true;
+            }
+            Token::Minus | Token::Negate => {
+                // A minus where an operand is expected is a unary minus
+                expect_operand = true;
+            }
+            Token::Plus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Modulo
+            | Token::Exponentiation
+            | Token::PlusMinus => {
+                if expect_operand {
+                    problems.push(missing_operand());
+                }
+                expect_operand = true;
+            }
+            // A unit on its own is most likely meant as a variable, so it stands in for
+            // an operand
+            Token::Unit(_) if expect_operand => {
+                problems.push(
+                    Diagnostic::new(
+                        format!("'{}' is reserved as a duration unit", text(span)),
+                        span.clone(),
+                    )
+                    .with_suggestion(format!(
+                        "write a number before it, such as 2 {}",
+                        text(span)
+                    )),
+                );
+                expect_operand = false;
+            }
+            Token::Factorial | Token::Unit(_) | Token::In(_) | Token::InCurrency(_) => {
+                if expect_operand {
+                    problems.push(missing_operand());
+                }
+            }
+            Token::Operator(operator) => match operator.fixity() {
+                Fixity::Infix => {
+                    if expect_operand {
+                        problems.push(missing_operand());
+                    }
+                    expect_operand = true;
+                }
+                Fixity::Prefix => {
+                    if !expect_operand {
+                        problems.push(missing_operator());
+                    }
+                    expect_operand = true;
+                }
+                Fixity::Postfix => {
+                    if expect_operand {
+                        problems.push(missing_operand());
+                    }
+                }
+            },
+        }
+        previous = Some(token);
+        previous_unreadable = false;
+    }
+
+    if let Some(call) = pending_call {
+        let name = text(&call);
+        problems.push(
+            Diagnostic::new(format!("Expected '(' after {}", name), call.end..call.end)
+                .with_suggestion(format!("write {}(...)", name)),
+        );
+    }
+    if let (Some((Lexeme::Token(token), span)), true) = (tokens.last(), expect_operand)
+        && awaits_operand(token)
+    {
+        problems.push(
+            Diagnostic::new(
+                format!("Expression ends with operator '{}'", text(span)),
+                span.clone(),
+            )
+            .with_suggestion(format!("add a value after '{}' or remove it", text(span))),
+        );
+    }
+    for (span, _) in open {
+        problems.push(Diagnostic::new("Unclosed '('", span).with_suggestion("add a matching ')'"));
+    }
+
+    problems.sort_by_key(|problem| problem.span.start);
+    problems
+}
+
+fn is_binary_operator(token: &Token) -> bool {
+    match token {
+        Token::Plus
+        | Token::Minus
+        | Token::Multiply
+        | Token::Divide
+        | Token::Modulo
+        | Token::Exponentiation
+        | Token::PlusMinus => true,
+        Token::Operator(operator) => operator.fixity() == Fixity::Infix,
+        _ => false,
+    }
+}
+
+/// Returns whether an operator still needs the operand that follows it.
+fn awaits_operand(token: &Token) -> bool {
+    match token {
+        Token::Operator(operator) => operator.fixity() != Fixity::Postfix,
+        Token::Negate => true,
+        _ => is_binary_operator(token),
+    }
+}
+
+/// Completes an unfinished expression so that a preview of its result can be shown.
+///
+/// Trailing operators, commas, open parentheses and function names that are still waiting
+/// for their operands are dropped, and any parentheses left open are closed, so that
+/// `12 * (3 +` becomes `12 * (3)`. Returns `None` if the input has no syntax problems to
+/// begin with, or if completing it does not resolve them all.
+pub fn complete(input: &str) -> Option<String> {
+    complete_with_functions(input, &Registry::new())
+}
+
+/// Completes an unfinished expression, resolving names against a function registry.
+pub fn complete_with_functions(input: &str, functions: &Registry) -> Option<String> {
+    if diagnose_with_functions(input, functions).is_empty() {
+        return None;
+    }
+    let (mut lexemes, _) = lex(input, functions);
+    while let Some((Lexeme::Token(token), _)) = lexemes.last() {
+        let unfinished = awaits_operand(token)
+            || matches!(
+                token,
+                Token::Comma
+                    | Token::LeftParen
+                    | Token::Sqrt
+                    | Token::Abs
+                    | Token::Function(_)
+                    | Token::Custom(_)
+            );
+        if !unfinished {
+            break;
+        }
+        lexemes.pop();
+    }
+    let (_, last) = lexemes.last()?;
+    let open = lexemes
+        .iter()
+        .map(|(lexeme, _)| match lexeme {
+            Lexeme::Token(Token::LeftParen) => 1,
+            Lexeme::Token(Token::RightParen) => -1,
+            _ => 0,
+        })
+        .sum::<i64>();
+
+    let mut completed = input[..last.end].to_string();
+    completed.push_str(&")".repeat(usize::try_from(open).ok()?));
+    diagnose_with_functions(&completed, functions)
+        .is_empty()
+        .then_some(completed)
+}
+
 /// Tokenizes the input string into a vector of tokens using nom parsers.
+///
+/// Fails with the first unreadable part of the input; use [`diagnose`] to find all of them.
 pub fn tokenize(input: &str) -> Result<Vec<Token>, Box<dyn Error>> {
-    let (remaining, tokens) = many0(parse_token)
-        .parse(input)
-        .map_err(|e| format!("Parse error: {}", e))?;
+    tokenize_with(input, &Limits::default())
+}
+
+/// Tokenizes an input string, first checking it against the maximum input length.
+pub fn tokenize_with(input: &str, limits: &Limits) -> Result<Vec<Token>, Box<dyn Error>> {
+    tokenize_with_functions(input, limits, &Registry::new())
+}
 
-    if !remaining.trim().is_empty() {
-        return Err(format!("Unable to parse remaining input: {}", remaining).into());
+/// Tokenizes an input string, resolving names against a function registry.
+pub fn tokenize_with_functions(
+    input: &str,
+    limits: &Limits,
+    functions: &Registry,
+) -> Result<Vec<Token>, Box<dyn Error>> {
+    limits.check_input(input)?;
+    let (tokens, problems) = lex(input, functions);
+    if let Some(problem) = problems.into_iter().next() {
+        return Err(problem.into());
     }
+    let tokens = tokens.into_iter().filter_map(|(lexeme, _)| match lexeme {
+        Lexeme::Token(token) => Some(token),
+        _ => None,
+    });
 
     // Post-process tokens to handle unary minus and factorial
     let mut processed_tokens = Vec::new();
-    let mut iter = tokens.into_iter().peekable();
+    let mut iter = tokens.peekable();
 
     while let Some(token) = iter.next() {
         match token {
             Token::Minus
-                if processed_tokens.is_empty()
-                    || matches!(
-                        processed_tokens.last().unwrap(),
-                        Token::Plus
-                            | Token::Minus
-                            | Token::Multiply
-                            | Token::Divide
-                            | Token::Modulo
-                            | Token::Exponentiation
-                            | Token::LeftParen
-                    ) =>
+                if processed_tokens.last().is_none_or(|last| {
+                    awaits_operand(last) || matches!(last, Token::LeftParen | Token::Comma)
+                }) =>
             {
                 // This is a unary minus
-                match iter.next() {
-                    Some(Token::Number(n)) => processed_tokens.push(Token::Number(-n)),
-                    Some(Token::LeftParen) => {
-                        processed_tokens.push(Token::LeftParen);
-                        processed_tokens.push(Token::Number(Decimal::from(-1)));
-                        processed_tokens.push(Token::Multiply);
+                match iter.peek() {
+                    Some(Token::Number(n)) => {
+                        let n = *n;
+                        iter.next();
+                        processed_tokens.push(Token::Number(-n));
                     }
-                    _ => return Err("Invalid unary minus".into()),
+                    // Negate anything else, such as a parenthesised expression or a duration
+                    Some(_) => processed_tokens.push(Token::Negate),
+                    None => return Err("Invalid unary minus".into()),
+                }
+            }
+            Token::Time(seconds) if matches!(processed_tokens.last(), Some(Token::Date(_))) => {
+                // A date directly followed by a time, as in `2026-10-16 14:30`
+                if let Some(Token::Date(days)) = processed_tokens.pop() {
+                    processed_tokens.push(Token::DateTime(days * SECONDS_PER_DAY + seconds));
+                }
+            }
+            Token::Duration(seconds)
+                if matches!(processed_tokens.last(), Some(Token::Duration(_))) =>
+            {
+                // Adjacent durations add up, as in `3d 4h`
+                if let Some(Token::Duration(previous)) = processed_tokens.pop() {
+                    let total = previous.checked_add(seconds).ok_or("Duration too large")?;
+                    processed_tokens.push(Token::Duration(total));
                 }
             }
             Token::Sqrt | Token::Abs => {
@@ -147,16 +886,6 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>, Box<dyn Error>> {
                     return Err("Invalid factorial operation".into());
                 }
             }
-            Token::Minus => {
-                // This is a binary minus
-                if let Some(Token::Number(_)) = iter.peek() {
-                    processed_tokens.push(token);
-                } else {
-                    processed_tokens.push(Token::Plus);
-                    processed_tokens.push(Token::Number(-Decimal::ONE));
-                    processed_tokens.push(Token::Multiply);
-                }
-            }
             _ => processed_tokens.push(token),
         }
     }
@@ -164,86 +893,364 @@ pub fn tokenize(input: &str) -> Result<Vec<Token>, Box<dyn Error>> {
     Ok(processed_tokens)
 }
 
+/// Precedence of function calls, which bind tighter than the built-in operators.
+const FUNCTION: u8 = 6;
+
 /// Returns the precedence of the given operator token.
 ///
 /// Lower numbers indicate lower precedence. Returns 0 for non-operator tokens.
 fn precedence(token: &Token) -> u8 {
     match token {
-        Token::Plus | Token::Minus => 1,
-        Token::Multiply | Token::Divide | Token::Modulo => 2,
-        Token::Exponentiation => 3,
-        Token::Factorial => 4,
-        Token::Sqrt | Token::Abs => 5,
+        Token::Plus | Token::Minus => operators::ADDITIVE,
+        Token::Multiply | Token::Divide | Token::Modulo => operators::MULTIPLICATIVE,
+        Token::PlusMinus => operators::UNCERTAINTY,
+        Token::Exponentiation => operators::POWER,
+        Token::Factorial => operators::FACTORIAL,
+        Token::Sqrt | Token::Abs | Token::Function(_) | Token::Custom(_) => FUNCTION,
+        Token::Operator(operator) => operator.precedence(),
+        Token::Negate => u8::MAX,
         _ => 0,
     }
 }
 
-/// Evaluates a slice of tokens and returns the result as a Decimal.
-pub fn evaluate(tokens: &[Token]) -> Result<Decimal, Box<dyn Error>> {
+/// Returns whether a chain of the given infix operator groups from the right.
+fn is_right_associative(token: &Token) -> bool {
+    match token {
+        Token::Exponentiation => true,
+        Token::Operator(operator) => operator.associativity() == Associativity::Right,
+        _ => false,
+    }
+}
+
+/// Returns the value of a literal, variable or clock token, or `None` for any other token.
+fn operand(token: &Token, now: i64) -> Option<Value> {
+    let value = match *token {
+        Token::Number(n) => Value::Number(n),
+        Token::Date(days) => Value::Date(days),
+        Token::Time(seconds) => Value::Time(seconds),
+        Token::DateTime(seconds) => Value::DateTime(seconds),
+        Token::Duration(seconds) => Value::duration(seconds),
+        Token::Money(amount, currency) => Value::Money { amount, currency },
+        Token::Today => Value::Date(now.div_euclid(SECONDS_PER_DAY)),
+        Token::Now => Value::DateTime(now),
+        Token::Variable(computed) => computed.value,
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Evaluates a slice of tokens and returns the resulting value.
+///
+/// Inexact intermediate results are rounded with the default half-even strategy, and
+/// `today` and `now` are read from the system clock.
+pub fn evaluate(tokens: &[Token]) -> Result<Value, Box<dyn Error>> {
+    evaluate_with(tokens, &EvalOptions::default())
+}
+
+/// Evaluates a slice of tokens with the given options.
+pub fn evaluate_with(tokens: &[Token], options: &EvalOptions) -> Result<Value, Box<dyn Error>> {
+    compute(tokens, options).map(|computed| computed.value)
+}
+
+/// State shared by the steps of one evaluation.
+struct Evaluation<'a> {
+    options: &'a EvalOptions<'a>,
+    meter: Meter,
+    /// Cleared once any step rounds or approximates its result.
+    exact: Cell<bool>,
+}
+
+impl Evaluation<'_> {
+    /// Records whether a step's result was exact.
+    fn track(&self, exact: bool) {
+        if !exact {
+            self.exact.set(false);
+        }
+    }
+
+    /// Rounds an amount to its currency's scale, recording whether that changed it.
+    fn round_money(&self, amount: Decimal, currency: Currency) -> Result<Decimal, Box<dyn Error>> {
+        let rounded = money::round(amount, currency)?;
+        self.track(rounded == amount);
+        Ok(rounded)
+    }
+
+    /// Divides `a` by a non-zero `b`, recording whether the quotient was rounded.
+    fn divide(&self, a: Decimal, b: Decimal) -> Result<Decimal, Box<dyn Error>> {
+        let quotient = checked(self.options.rounding.divide(a, b))?;
+        self.track(quotient.checked_mul(b) == Some(a));
+        Ok(quotient)
+    }
+}
+
+/// Evaluates a slice of tokens, also reporting whether the result is exact.
+///
+/// The result is approximate if any step rounded its result, such as the division in
+/// `1/3`, or approximated it, such as `2^0.5` or the propagation of an uncertainty.
+pub fn compute(tokens: &[Token], options: &EvalOptions) -> Result<Computed, Box<dyn Error>> {
     if tokens.is_empty() {
-        return Err("Empty expression".into());
+        return Err("Invalid expression: empty expression".into());
     }
+    let now = options.now.unwrap_or_else(datetime::system_now);
+    let eval = Evaluation {
+        options,
+        meter: Meter::start(options.limits),
+        exact: Cell::new(true),
+    };
+    let meter = &eval.meter;
 
-    let mut numbers: Vec<Decimal> = Vec::new();
+    let mut numbers: Vec<Value> = Vec::new();
     let mut operators: Vec<Token> = Vec::new();
-    let mut paren_count = 0;
+    // Argument counts for each open function call, innermost last
+    let mut arg_counts: Vec<usize> = Vec::new();
+    let mut paren_count: usize = 0;
     let mut expect_paren = false;
     let mut last_was_number = false;
     let mut last_was_operator = false;
 
     let mut i = 0;
     while i < tokens.len() {
-        match &tokens[i] {
-            Token::Number(n) => {
-                if expect_paren {
-                    return Err("Expected '(' after function".into());
-                }
-                if last_was_number {
-                    return Err("Invalid expression: consecutive numbers".into());
-                }
-                numbers.push(*n);
-                last_was_number = true;
-                last_was_operator = false;
+        meter.step()?;
+        if let Some(value) = operand(&tokens[i], now) {
+            if let Token::Variable(computed) = &tokens[i] {
+                eval.track(computed.exact);
+            }
+            if expect_paren {
+                return Err("Expected '(' after function".into());
             }
+            if last_was_number {
+                return Err("Invalid expression: consecutive numbers".into());
+            }
+            meter.check(&value)?;
+            numbers.push(value);
+            last_was_number = true;
+            last_was_operator = false;
+            i += 1;
+            continue;
+        }
+        match &tokens[i] {
             Token::LeftParen => {
                 paren_count += 1;
+                options.limits.check_depth(paren_count)?;
+                if let Some(Token::Function(_) | Token::Custom(_)) = operators.last() {
+                    arg_counts.push(1);
+                }
                 expect_paren = false;
                 operators.push(tokens[i].clone());
                 last_was_number = false;
                 last_was_operator = false;
             }
             Token::RightParen => {
-                paren_count -= 1;
-                if paren_count < 0 {
+                if paren_count == 0 {
                     return Err("Mismatched parentheses".into());
                 }
+                paren_count -= 1;
+                if !last_was_number {
+                    return Err("Invalid expression: missing operand before ')'".into());
+                }
                 while let Some(op) = operators.last() {
                     if let Token::LeftParen = op {
                         break;
                     }
-                    apply_operator(&mut numbers, operators.pop().unwrap())?;
+                    apply_operator(&mut numbers, operators.pop().unwrap(), &eval)?;
                 }
                 operators.pop(); // Remove LeftParen
 
                 // Apply any pending function
-                if let Some(Token::Sqrt | Token::Abs) = operators.last() {
-                    apply_operator(&mut numbers, operators.pop().unwrap())?;
+                match operators.last() {
+                    Some(Token::Sqrt | Token::Abs) => {
+                        apply_operator(&mut numbers, operators.pop().unwrap(), &eval)?;
+                    }
+                    Some(Token::Function(name)) => {
+                        let name = *name;
+                        operators.pop();
+                        let count = arg_counts.pop().unwrap_or(1);
+                        if numbers.len() < count {
+                            return Err(format!("Not enough arguments for {}", name).into());
+                        }
+                        let args = numbers.split_off(numbers.len() - count);
+                        meter.step()?;
+                        let result = call_function(name, &args, &eval)?;
+                        meter.check(&result)?;
+                        numbers.push(result);
+                    }
+                    Some(Token::Custom(function)) => {
+                        let function = function.clone();
+                        operators.pop();
+                        let count = arg_counts.pop().unwrap_or(1);
+                        if numbers.len() < count {
+                            let name = function.name();
+                            return Err(format!("Not enough arguments for {}", name).into());
+                        }
+                        let args = numbers.split_off(numbers.len() - count);
+                        meter.step()?;
+                        let result = call_custom(function.as_ref(), &args, &eval)?;
+                        meter.check(&result)?;
+                        numbers.push(result);
+                    }
+                    _ => {}
                 }
-                last_was_number = false;
+                last_was_number = true;
                 last_was_operator = false;
             }
-            Token::Sqrt | Token::Abs => {
+            Token::Factorial => {
+                if !last_was_number {
+                    return Err("Invalid factorial operation".into());
+                }
+                // Postfix with the highest binding, so apply immediately
+                apply_operator(&mut numbers, Token::Factorial, &eval)?;
+            }
+            Token::Unit(unit) => {
+                if !last_was_number {
+                    return Err(format!("Missing number before unit {}", unit).into());
+                }
+                // Binds like factorial, turning the preceding number into a duration
+                match numbers.pop() {
+                    Some(Value::Number(n)) => {
+                        let seconds = n.checked_mul(unit.seconds()).ok_or("Duration too large")?;
+                        let duration = Value::duration(seconds);
+                        meter.check(&duration)?;
+                        numbers.push(duration);
+                    }
+                    Some(other) => {
+                        return Err(format!("Cannot apply a unit to {}", other.kind()).into());
+                    }
+                    None => return Err(format!("Missing number before unit {}", unit).into()),
+                }
+            }
+            Token::In(unit) => {
+                if !last_was_number {
+                    return Err("Invalid expression: missing value before 'in'".into());
+                }
+                // 'in' binds loosest, so finish everything back to the enclosing parenthesis
+                while let Some(op) = operators.last() {
+                    if let Token::LeftParen = op {
+                        break;
+                    }
+                    apply_operator(&mut numbers, operators.pop().unwrap(), &eval)?;
+                }
+                match numbers.pop() {
+                    Some(Value::Duration { seconds, .. }) => numbers.push(Value::Duration {
+                        seconds,
+                        unit: Some(*unit),
+                    }),
+                    Some(other) => {
+                        return Err(format!("Cannot convert {} to {}", other.kind(), unit).into());
+                    }
+                    None => return Err("Invalid expression: missing value before 'in'".into()),
+                }
+            }
+            Token::InCurrency(target) => {
+                if !last_was_number {
+                    return Err("Invalid expression: missing value before 'in'".into());
+                }
+                while let Some(op) = operators.last() {
+                    if let Token::LeftParen = op {
+                        break;
+                    }
+                    apply_operator(&mut numbers, operators.pop().unwrap(), &eval)?;
+                }
+                let (amount, currency) = match (numbers.pop(), options.money) {
+                    (Some(Value::Money { amount, currency }), _) => (amount, currency),
+                    (Some(Value::Number(amount)), Some(currency)) => (amount, currency),
+                    (Some(other), _) => {
+                        return Err(format!("Cannot convert {} to {}", other.kind(), target).into());
+                    }
+                    (None, _) => {
+                        return Err("Invalid expression: missing value before 'in'".into());
+                    }
+                };
+                let rates = options.rates.ok_or("No exchange rate table loaded")?;
+                let converted = rates.convert(amount, currency, *target)?;
+                let unrounded = rates
+                    .rate(currency, *target)
+                    .and_then(|rate| amount.checked_mul(rate));
+                eval.track(unrounded == Some(converted));
+                let converted = Value::Money {
+                    amount: converted,
+                    currency: *target,
+                };
+                meter.check(&converted)?;
+                numbers.push(converted);
+            }
+            Token::Sqrt | Token::Abs | Token::Function(_) | Token::Custom(_) => {
+                if last_was_number {
+                    return Err("Invalid expression: missing operator before function".into());
+                }
                 expect_paren = true;
                 operators.push(tokens[i].clone());
                 last_was_number = false;
                 last_was_operator = false;
             }
+            Token::Comma => {
+                if !last_was_number {
+                    return Err("Invalid expression: missing function argument".into());
+                }
+                while let Some(op) = operators.last() {
+                    if let Token::LeftParen = op {
+                        break;
+                    }
+                    apply_operator(&mut numbers, operators.pop().unwrap(), &eval)?;
+                }
+                // Only the parentheses of a function call may contain commas
+                let in_call = operators.len() >= 2
+                    && matches!(
+                        operators[operators.len() - 2],
+                        Token::Function(_) | Token::Custom(_)
+                    );
+                match arg_counts.last_mut() {
+                    Some(count) if in_call => *count += 1,
+                    _ => return Err("Unexpected ',' outside of a function call".into()),
+                }
+                last_was_number = false;
+                last_was_operator = false;
+            }
+            Token::Negate => {
+                if expect_paren {
+                    return Err("Expected '(' after function".into());
+                }
+                // Applied once its operand is complete, before any operator that follows
+                operators.push(Token::Negate);
+                last_was_operator = false;
+            }
+            Token::Operator(operator) if operator.fixity() == Fixity::Prefix => {
+                if expect_paren {
+                    return Err("Expected '(' after function".into());
+                }
+                if last_was_number {
+                    return Err(format!(
+                        "Invalid expression: missing operator before {}",
+                        operator.symbol()
+                    )
+                    .into());
+                }
+                // Applied once an operator of lower or equal precedence follows its operand
+                operators.push(tokens[i].clone());
+                last_was_operator = false;
+            }
+            Token::Operator(operator) if operator.fixity() == Fixity::Postfix => {
+                if !last_was_number {
+                    return Err(format!("Missing operand before {}", operator.symbol()).into());
+                }
+                // Finish whatever binds at least as tightly, then apply to the result
+                while let Some(top_op) = operators.last() {
+                    if matches!(top_op, Token::LeftParen)
+                        || precedence(top_op) < operator.precedence()
+                    {
+                        break;
+                    }
+                    apply_operator(&mut numbers, operators.pop().unwrap(), &eval)?;
+                }
+                apply_operator(&mut numbers, tokens[i].clone(), &eval)?;
+            }
             op @ (Token::Plus
             | Token::Minus
             | Token::Multiply
             | Token::Divide
             | Token::Modulo
-            | Token::Exponentiation) => {
+            | Token::Exponentiation
+            | Token::PlusMinus
+            | Token::Operator(_)) => {
                 if expect_paren {
                     return Err("Expected '(' after function".into());
                 }
@@ -254,7 +1261,7 @@ pub fn evaluate(tokens: &[Token]) -> Result<Decimal, Box<dyn Error>> {
                     return Err("Invalid expression: operator without operand".into());
                 }
 
-                let is_right_associative = matches!(op, Token::Exponentiation);
+                let is_right_associative = is_right_associative(op);
                 while let Some(top_op) = operators.last() {
                     if let Token::LeftParen = top_op {
                         break;
@@ -262,7 +1269,7 @@ pub fn evaluate(tokens: &[Token]) -> Result<Decimal, Box<dyn Error>> {
                     if (is_right_associative && precedence(top_op) > precedence(op))
                         || (!is_right_associative && precedence(top_op) >= precedence(op))
                     {
-                        apply_operator(&mut numbers, operators.pop().unwrap())?;
+                        apply_operator(&mut numbers, operators.pop().unwrap(), &eval)?;
                     } else {
                         break;
                     }
@@ -271,6 +1278,7 @@ pub fn evaluate(tokens: &[Token]) -> Result<Decimal, Box<dyn Error>> {
                 last_was_number = false;
                 last_was_operator = true;
             }
+            _ => return Err("Invalid expression".into()),
         }
         i += 1;
     }
@@ -286,189 +1294,830 @@ pub fn evaluate(tokens: &[Token]) -> Result<Decimal, Box<dyn Error>> {
     }
 
     while let Some(op) = operators.pop() {
-        apply_operator(&mut numbers, op)?;
+        apply_operator(&mut numbers, op, &eval)?;
     }
 
     if numbers.len() != 1 {
         return Err("Invalid expression".into());
     }
 
-    Ok(numbers.pop().unwrap())
+    let value = match (numbers.pop().unwrap(), options.money) {
+        (Value::Number(amount), Some(currency)) => Value::Money {
+            amount: eval.round_money(amount, currency)?,
+            currency,
+        },
+        (result, _) => result,
+    };
+    Ok(Computed {
+        value,
+        exact: eval.exact.get(),
+    })
+}
+
+/// Applies an operator to the operands on top of the stack, counting it as a step and
+/// checking the result against the magnitude limit.
+fn apply_operator(
+    numbers: &mut Vec<Value>,
+    op: Token,
+    eval: &Evaluation,
+) -> Result<(), Box<dyn Error>> {
+    eval.meter.step()?;
+    let result = operator_result(numbers, op, eval)?;
+    eval.meter.check(&result)?;
+    numbers.push(result);
+    Ok(())
+}
+
+fn operator_result(
+    numbers: &mut Vec<Value>,
+    op: Token,
+    eval: &Evaluation,
+) -> Result<Value, Box<dyn Error>> {
+    if let Token::Operator(operator) = &op {
+        let arity = operator.fixity().arity();
+        if numbers.len() < arity {
+            return Err(format!("Not enough operands for {}", operator.symbol()).into());
+        }
+        let args = numbers.split_off(numbers.len() - arity);
+        eval.track(!args.iter().any(|a| matches!(a, Value::Uncertain { .. })));
+        return operator.apply(&args);
+    }
+    if let Token::Negate = op {
+        // Negating is multiplying by -1, which also applies to amounts and durations
+        let operand = numbers.pop().ok_or("Not enough operands for negation")?;
+        numbers.extend([Value::Number(Decimal::NEGATIVE_ONE), operand]);
+        return operator_result(numbers, Token::Multiply, eval);
+    }
+    let (arity, name) = match op {
+        Token::Plus => (2, "addition"),
+        Token::Minus => (2, "subtraction"),
+        Token::Multiply => (2, "multiplication"),
+        Token::Divide => (2, "division"),
+        Token::Modulo => (2, "modulo"),
+        Token::Exponentiation => (2, "exponentiation"),
+        Token::PlusMinus => (2, "uncertainty"),
+        Token::Factorial => (1, "factorial"),
+        Token::Sqrt => (1, "square root"),
+        Token::Abs => (1, "absolute value"),
+        _ => return Err("Invalid operator".into()),
+    };
+    if numbers.len() < arity {
+        return Err(format!("Not enough operands for {}", name).into());
+    }
+    let args = numbers.split_off(numbers.len() - arity);
+    if args
+        .iter()
+        .any(|a| matches!(a, Value::Money { .. } | Value::Split { .. }))
+    {
+        return money_operator(&op, name, &args, eval);
+    }
+    if args.iter().any(Value::is_temporal) {
+        return temporal_operator(&op, name, &args, eval);
+    }
+
+    let result = match (op, eval.options.money, &args[..]) {
+        // In money mode a plain number divided by a whole number is allocated into shares
+        (Token::Divide, Some(currency), &[Value::Number(amount), Value::Number(parts)])
+            if parts > Decimal::ZERO && parts.fract().is_zero() =>
+        {
+            divide_money(amount, currency, parts, eval)?
+        }
+        (op, ..) => apply_numeric_operator(op, &args, eval)?,
+    };
+    Ok(result)
 }
 
-fn apply_operator(numbers: &mut Vec<Decimal>, op: Token) -> Result<(), Box<dyn Error>> {
+/// Applies an operator to numbers, intervals and uncertain values.
+///
+/// Propagating an uncertainty is a first-order approximation, so it makes the result
+/// approximate.
+fn apply_numeric_operator(
+    op: Token,
+    args: &[Value],
+    eval: &Evaluation,
+) -> Result<Value, Box<dyn Error>> {
+    let ctx = &eval.options.rounding;
+    if !matches!(op, Token::PlusMinus) {
+        eval.track(!args.iter().any(|a| matches!(a, Value::Uncertain { .. })));
+    }
+    let result = match op {
+        Token::PlusMinus => match (args[0], args[1]) {
+            (Value::Number(mean), Value::Number(sigma)) => Value::uncertain(mean, sigma)?,
+            _ => return Err("± requires exact operands".into()),
+        },
+        Token::Modulo => remainder(args[0], args[1])?,
+        Token::Factorial => match args[0] {
+            Value::Number(n) => Value::Number(factorial(&n)?),
+            _ => return Err("Factorial requires an exact integer".into()),
+        },
+        _ => Value::propagate(args, |x| {
+            let y = apply_decimal_operator(&op, x, ctx)?;
+            eval.track(exact_operation(&op, x, y));
+            Ok(y)
+        })?,
+    };
+    Ok(result)
+}
+
+/// Returns whether `y` is the exact result of applying `op` to `x`.
+///
+/// Each check uses the inverse operation, which is exact whenever the result was.
+fn exact_operation(op: &Token, x: &[Decimal], y: Decimal) -> bool {
     match op {
-        Token::Plus => {
-            if numbers.len() < 2 {
-                return Err("Not enough operands for addition".into());
+        Token::Plus => y.checked_sub(x[1]) == Some(x[0]),
+        Token::Minus => y.checked_add(x[1]) == Some(x[0]),
+        Token::Multiply => x[1].is_zero() || y.checked_div(x[1]) == Some(x[0]),
+        Token::Divide => y.checked_mul(x[1]) == Some(x[0]),
+        Token::Exponentiation => exact_power(x[0], x[1], y),
+        Token::Sqrt => decimal_math::is_root(y, x[0], 2),
+        _ => true,
+    }
+}
+
+/// Returns whether `y` is exactly `a` raised to the power `b`.
+fn exact_power(a: Decimal, b: Decimal, y: Decimal) -> bool {
+    if a.is_zero() || a == Decimal::ONE {
+        return true;
+    }
+    if b.fract().is_zero() {
+        let base = if b.is_sign_negative() {
+            let inverse = Decimal::ONE / a;
+            if inverse * a != Decimal::ONE {
+                return false;
             }
-            let b = numbers.pop().unwrap();
-            let a = numbers.pop().unwrap();
-            numbers.push(a + b);
+            inverse
+        } else {
+            a
+        };
+        // An exact power of a number with d decimal places has d * |b| places, and its
+        // digits must not have been rounded away
+        let places = Decimal::from(base.normalize().scale()) * b.abs();
+        return Decimal::from(y.normalize().scale()) == places
+            && b.abs()
+                .to_u32()
+                .and_then(|n| decimal_math::powi(base, n))
+                .is_some_and(|power| power == y);
+    }
+    // A fractional power p/q is exact when y is the qth root of a^p
+    let b = b.normalize();
+    let q = 10u32.pow(b.scale().min(9));
+    let p = b * Decimal::from(q);
+    match (p.to_u32(), q) {
+        (Some(p), q) if p > 0 => {
+            let divisor = number_theory::gcd(p as u128, q as u128) as u32;
+            let (p, q) = (p / divisor, q / divisor);
+            decimal_math::powi(a, p).is_some_and(|power| decimal_math::is_root(y, power, q))
         }
-        Token::Minus => {
-            if numbers.len() < 2 {
-                return Err("Not enough operands for subtraction".into());
+        _ => false,
+    }
+}
+
+/// Returns whether `y` is the exact result of the named function applied to `x`.
+///
+/// Transcendental functions are only exact at the points where they are rational.
+fn exact_function(name: &str, x: &[Decimal], y: Decimal) -> bool {
+    match name {
+        "sinh" | "tanh" | "asinh" | "atanh" | "cosh" | "sech" => x[0].is_zero(),
+        "acosh" => x[0] == Decimal::ONE,
+        "csch" | "coth" => false,
+        "cbrt" => decimal_math::is_root(y, x[0], 3),
+        "root" => x[1]
+            .to_u32()
+            .is_some_and(|degree| decimal_math::is_root(y, x[0], degree)),
+        _ => true,
+    }
+}
+
+/// Describes an operator applied to operands it does not support.
+fn unsupported(name: &str, args: &[Value]) -> Box<dyn Error> {
+    match args {
+        [a] => format!("Cannot apply {} to {}", name, a.kind()).into(),
+        [a, b] => format!("Cannot apply {} to {} and {}", name, a.kind(), b.kind()).into(),
+        _ => format!("Not enough operands for {}", name).into(),
+    }
+}
+
+/// Applies an operator to operands of which at least one is money or an allocation.
+///
+/// Amounts are rounded to their currency's scale after every operation. In money mode a
+/// plain number added to or subtracted from an amount in the mode's currency is taken as
+/// an amount in that currency too.
+fn money_operator(
+    op: &Token,
+    name: &str,
+    args: &[Value],
+    eval: &Evaluation,
+) -> Result<Value, Box<dyn Error>> {
+    use Value::{Money, Number};
+
+    let options = eval.options;
+    let promote = |value: Value, currency: Currency| match value {
+        Number(amount) if options.money == Some(currency) => Money { amount, currency },
+        other => other,
+    };
+    let too_large = || "Amount too large";
+    let result = match (op, args) {
+        (Token::Abs, &[Money { amount, currency }]) => Money {
+            amount: amount.abs(),
+            currency,
+        },
+        (Token::Plus | Token::Minus, &[a, b]) => {
+            let (a, b) = match (a, b) {
+                (Money { currency, .. }, _) => (a, promote(b, currency)),
+                (_, Money { currency, .. }) => (promote(a, currency), b),
+                _ => (a, b),
+            };
+            match (a, b) {
+                (
+                    Money {
+                        amount: x,
+                        currency,
+                    },
+                    Money {
+                        amount: y,
+                        currency: other,
+                    },
+                ) if currency == other => {
+                    let amount = if matches!(op, Token::Plus) {
+                        x.checked_add(y)
+                    } else {
+                        x.checked_sub(y)
+                    };
+                    Money {
+                        amount: eval.round_money(amount.ok_or_else(too_large)?, currency)?,
+                        currency,
+                    }
+                }
+                (
+                    Money { currency, .. },
+                    Money {
+                        currency: other, ..
+                    },
+                ) => {
+                    return Err(format!(
+                        "Cannot apply {} to {} and {}; convert one with 'in'",
+                        name, currency, other
+                    )
+                    .into());
+                }
+                _ => return Err(unsupported(name, args)),
             }
-            let b = numbers.pop().unwrap();
-            let a = numbers.pop().unwrap();
-            numbers.push(a - b);
         }
-        Token::Multiply => {
-            if numbers.len() < 2 {
-                return Err("Not enough operands for multiplication".into());
-            }
-            let b = numbers.pop().unwrap();
-            let a = numbers.pop().unwrap();
-            match a.checked_mul(b) {
-                Some(result) => numbers.push(result),
-                None => return Err("Result too large".into()),
-            }
+        (Token::Multiply, &[Money { amount, currency }, Number(factor)])
+        | (Token::Multiply, &[Number(factor), Money { amount, currency }]) => Money {
+            amount: eval
+                .round_money(amount.checked_mul(factor).ok_or_else(too_large)?, currency)?,
+            currency,
+        },
+        (Token::Divide, &[Money { amount, currency }, Number(divisor)]) => {
+            divide_money(amount, currency, divisor, eval)?
         }
-        Token::Divide => {
-            if numbers.len() < 2 {
-                return Err("Not enough operands for division".into());
-            }
-            let b = numbers.pop().unwrap();
-            let a = numbers.pop().unwrap();
-            if b.is_zero() {
+        (
+            Token::Divide,
+            &[
+                Money { amount, currency },
+                Money {
+                    amount: divisor,
+                    currency: other,
+                },
+            ],
+        ) if currency == other => {
+            if divisor.is_zero() {
                 return Err("division by zero".into());
             }
-            numbers.push(a / b);
+            Number(eval.divide(amount, divisor)?)
         }
-        Token::Modulo => {
-            if numbers.len() < 2 {
-                return Err("Not enough operands for modulo".into());
+        _ => return Err(unsupported(name, args)),
+    };
+    Ok(result)
+}
+
+/// Divides an amount of money, allocating it into shares when the divisor is a whole number.
+fn divide_money(
+    amount: Decimal,
+    currency: Currency,
+    divisor: Decimal,
+    eval: &Evaluation,
+) -> Result<Value, Box<dyn Error>> {
+    if divisor.is_zero() {
+        return Err("division by zero".into());
+    }
+    let amount = eval.round_money(amount, currency)?;
+    if !divisor.fract().is_zero() {
+        return Ok(Value::Money {
+            amount: eval.round_money(eval.divide(amount, divisor)?, currency)?,
+            currency,
+        });
+    }
+    // Dividing by a negative number splits the negated amount
+    let parts = divisor
+        .abs()
+        .to_u32()
+        .ok_or_else(|| format!("Cannot split an amount into more than {} parts", u32::MAX))?;
+    let amount = if divisor.is_sign_negative() {
+        -amount
+    } else {
+        amount
+    };
+    match money::allocate(amount, parts, currency)?[..] {
+        // Divides evenly, so every share is the same amount
+        [(share, _)] => Ok(Value::Money {
+            amount: share,
+            currency,
+        }),
+        _ => Ok(Value::Split {
+            total: amount,
+            currency,
+            parts,
+        }),
+    }
+}
+
+/// Applies an operator to operands of which at least one is a date, time or duration.
+fn temporal_operator(
+    op: &Token,
+    name: &str,
+    args: &[Value],
+    eval: &Evaluation,
+) -> Result<Value, Box<dyn Error>> {
+    use Value::{Date, DateTime, Duration, Number, Time};
+
+    let too_large = || "Duration too large";
+    let result = match (op, args) {
+        (Token::Abs, &[Duration { seconds, unit }]) => Duration {
+            seconds: seconds.abs(),
+            unit,
+        },
+        (Token::Plus, &[Duration { seconds: a, .. }, Duration { seconds: b, .. }]) => {
+            Value::duration(a.checked_add(b).ok_or_else(too_large)?)
+        }
+        (Token::Minus, &[Duration { seconds: a, .. }, Duration { seconds: b, .. }]) => {
+            Value::duration(a.checked_sub(b).ok_or_else(too_large)?)
+        }
+        (
+            Token::Plus,
+            &[
+                point @ (Date(_) | Time(_) | DateTime(_)),
+                Duration { seconds, .. },
+            ],
+        )
+        | (
+            Token::Plus,
+            &[
+                Duration { seconds, .. },
+                point @ (Date(_) | Time(_) | DateTime(_)),
+            ],
+        ) => shift(point, seconds, eval)?,
+        (
+            Token::Minus,
+            &[
+                point @ (Date(_) | Time(_) | DateTime(_)),
+                Duration { seconds, .. },
+            ],
+        ) => shift(point, -seconds, eval)?,
+        (Token::Plus, &[Date(days), Time(seconds)])
+        | (Token::Plus, &[Time(seconds), Date(days)]) => DateTime(days * SECONDS_PER_DAY + seconds),
+        // The difference between two points in time of compatible kinds
+        (Token::Minus, &[a @ (Date(_) | DateTime(_)), b @ (Date(_) | DateTime(_))])
+        | (Token::Minus, &[a @ Time(_), b @ Time(_)]) => Value::duration(a.center() - b.center()),
+        (Token::Multiply, &[Duration { seconds, unit }, Number(n)])
+        | (Token::Multiply, &[Number(n), Duration { seconds, unit }]) => Duration {
+            seconds: seconds.checked_mul(n).ok_or_else(too_large)?,
+            unit,
+        },
+        (Token::Divide, &[Duration { seconds, unit }, Number(n)]) => {
+            if n.is_zero() {
+                return Err("division by zero".into());
             }
-            let b = numbers.pop().unwrap();
-            let a = numbers.pop().unwrap();
-            if b.is_zero() {
-                return Err("modulo by zero".into());
+            Duration {
+                seconds: eval.divide(seconds, n)?,
+                unit,
             }
-            numbers.push(a % b);
         }
-        Token::Exponentiation => {
-            if numbers.len() < 2 {
-                return Err("Not enough operands for exponentiation".into());
+        (Token::Divide, &[Duration { seconds: a, .. }, Duration { seconds: b, .. }]) => {
+            if b.is_zero() {
+                return Err("division by zero".into());
             }
-            let b = numbers.pop().unwrap();
-            let a = numbers.pop().unwrap();
+            Number(eval.divide(a, b)?)
+        }
+        _ => return Err(unsupported(name, args)),
+    };
+    Ok(result)
+}
 
-            // Check if exponent is an integer
-            if b.fract().is_zero() {
-                // Handle integer exponentiation
-                let exp = b.to_i128().ok_or("Exponent too large")?;
+/// Moves a date, time, or date and time by a number of seconds.
+///
+/// A date stays a date when moved by whole days and otherwise gains a time of day. Times
+/// of day wrap around midnight. Fractions of a second are rounded with the active strategy.
+fn shift(point: Value, seconds: Decimal, eval: &Evaluation) -> Result<Value, Box<dyn Error>> {
+    let ctx = &eval.options.rounding;
+    eval.track(seconds.fract().is_zero());
+    let day = Decimal::from(SECONDS_PER_DAY);
+    let whole = |s: Decimal| {
+        round_places(s, 0, ctx.rounding)
+            .ok_or("Date out of range")
+            .map_err(Into::into)
+            .and_then(datetime::whole_seconds)
+    };
+    match point {
+        Value::Date(days) if (seconds % day).is_zero() => {
+            let offset = datetime::whole_seconds(seconds / day)?;
+            let days = days.checked_add(offset).ok_or("Date out of range")?;
+            Ok(Value::Date(datetime::check_date(days)?))
+        }
+        Value::Date(days) => shift(Value::DateTime(days * SECONDS_PER_DAY), seconds, eval),
+        Value::DateTime(start) => {
+            let moment = start
+                .checked_add(whole(seconds)?)
+                .ok_or("Date out of range")?;
+            datetime::check_date(moment.div_euclid(SECONDS_PER_DAY))?;
+            Ok(Value::DateTime(moment))
+        }
+        Value::Time(start) => {
+            let offset = whole(seconds % day)?;
+            Ok(Value::Time((start + offset).rem_euclid(SECONDS_PER_DAY)))
+        }
+        other => Err(format!("Cannot add a duration to {}", other.kind()).into()),
+    }
+}
 
-                // For very large exponents, we need to check if the result would be too large
-                if exp > 0 {
-                    // Estimate result size by counting digits in base and multiplying by exponent
-                    let base_digits = a.abs().to_string().trim_end_matches('0').len();
-                    if base_digits as i128 * exp > 28 {
-                        // Decimal can handle up to 28-29 digits
-                        return Err("Result would be too large".into());
-                    }
-                }
+/// Applies an operator to exact operands.
+fn apply_decimal_operator(
+    op: &Token,
+    x: &[Decimal],
+    ctx: &RoundingContext,
+) -> Result<Decimal, Box<dyn Error>> {
+    match op {
+        Token::Plus => checked(x[0].checked_add(x[1])),
+        Token::Minus => checked(x[0].checked_sub(x[1])),
+        Token::Multiply => checked(x[0].checked_mul(x[1])),
+        Token::Divide => {
+            if x[1].is_zero() {
+                return Err("division by zero".into());
+            }
+            checked(ctx.divide(x[0], x[1]))
+        }
+        Token::Exponentiation => power(x[0], x[1], ctx),
+        Token::Sqrt => Ok(ctx.round_intermediate(decimal_math::sqrt(x[0])?)),
+        Token::Abs => Ok(x[0].abs()),
+        _ => Err("Invalid operator".into()),
+    }
+}
 
-                let mut result = Decimal::ONE;
-                let mut base = if exp < 0 {
-                    if a.is_zero() {
-                        return Err("Division by zero in negative exponent".into());
-                    }
-                    Decimal::ONE / a
+/// Turns the result of a checked `Decimal` operation into an overflow error.
+fn checked(result: Option<Decimal>) -> Result<Decimal, Box<dyn Error>> {
+    result.ok_or_else(|| "Result too large".into())
+}
+
+/// Computes the truncated remainder `a % b`.
+///
+/// An interval that spans a wrap-around of the remainder yields the whole period, since
+/// the remainder is not monotonic across it.
+fn remainder(a: Value, b: Value) -> Result<Value, Box<dyn Error>> {
+    if let Value::Number(m) = b
+        && m.is_zero()
+    {
+        return Err("modulo by zero".into());
+    }
+    match (a, b) {
+        (_, Value::Interval { .. }) => Err("Modulo by an interval is not supported".into()),
+        (Value::Interval { lo, hi }, Value::Number(m))
+            if checked(lo.checked_div(m))?.trunc() != checked(hi.checked_div(m))?.trunc()
+                || (lo < Decimal::ZERO && hi > Decimal::ZERO) =>
+        {
+            let m = m.abs();
+            Ok(Value::Interval {
+                lo: if lo < Decimal::ZERO {
+                    -m
                 } else {
-                    a
-                };
-                let mut exp_abs = exp.abs();
-
-                while exp_abs > 0 {
-                    if exp_abs & 1 == 1 {
-                        // Check for potential overflow before multiplying
-                        if let Some(new_result) = result.checked_mul(base) {
-                            result = new_result;
-                        } else {
-                            return Err("Result too large".into());
-                        }
-                    }
-                    // Check for potential overflow before squaring base
-                    if exp_abs > 1 {
-                        if let Some(new_base) = base.checked_mul(base) {
-                            base = new_base;
-                        } else {
-                            return Err("Intermediate result too large".into());
-                        }
-                    }
-                    exp_abs >>= 1;
-                }
-                numbers.push(result);
-            } else {
-                // For non-integer exponents, use f64 (with potential loss of precision)
-                let base = a.to_f64().ok_or("Cannot convert base to f64")?;
-                let exp = b.to_f64().ok_or("Cannot convert exponent to f64")?;
-                let result = base.powf(exp);
+                    Decimal::ZERO
+                },
+                hi: if hi > Decimal::ZERO { m } else { Decimal::ZERO },
+            })
+        }
+        _ => Value::propagate(&[a, b], |x| {
+            if x[1].is_zero() {
+                return Err("modulo by zero".into());
+            }
+            checked(x[0].checked_rem(x[1]))
+        }),
+    }
+}
 
-                if result.is_nan() || result.is_infinite() {
-                    return Err("Invalid exponentiation result".into());
-                }
+/// Raises `a` to the power `b`.
+fn power(a: Decimal, b: Decimal, ctx: &RoundingContext) -> Result<Decimal, Box<dyn Error>> {
+    // Check if exponent is an integer
+    if b.fract().is_zero() {
+        // Handle integer exponentiation
+        let exp = b.to_i128().ok_or("Exponent too large")?;
 
-                numbers.push(Decimal::from_f64(result).ok_or("Result too large for decimal")?);
+        let mut result = Decimal::ONE;
+        let mut base = if exp < 0 {
+            if a.is_zero() {
+                return Err("Division by zero in negative exponent".into());
             }
-        }
-        Token::Factorial => {
-            if numbers.is_empty() {
-                return Err("Not enough operands for factorial".into());
-            }
-            let n = numbers.pop().unwrap();
-            numbers.push(factorial(&n)?);
-        }
-        Token::Sqrt => {
-            if numbers.is_empty() {
-                return Err("Not enough operands for square root".into());
+            checked(ctx.divide(Decimal::ONE, a))?
+        } else {
+            a
+        };
+        let mut exp_abs = exp.abs();
+
+        while exp_abs > 0 {
+            if exp_abs & 1 == 1 {
+                // Check for potential overflow before multiplying
+                if let Some(new_result) = result.checked_mul(base) {
+                    result = new_result;
+                } else {
+                    return Err("Result too large".into());
+                }
             }
-            let n = numbers.pop().unwrap();
-            if n < Decimal::ZERO {
-                return Err("Cannot compute square root of negative number".into());
+            // Check for potential overflow before squaring base
+            if exp_abs > 1 {
+                if let Some(new_base) = base.checked_mul(base) {
+                    base = new_base;
+                } else {
+                    return Err("Intermediate result too large".into());
+                }
             }
-            let f = n.to_f64().ok_or("Cannot convert to f64")?;
-            let result = f.sqrt();
-            numbers.push(Decimal::from_f64(result).ok_or("Cannot convert result to Decimal")?);
+            exp_abs >>= 1;
         }
-        Token::Abs => {
-            if numbers.is_empty() {
-                return Err("Not enough operands for absolute value".into());
-            }
-            let n = numbers.pop().unwrap();
-            numbers.push(n.abs());
+        Ok(result)
+    } else {
+        // For non-integer exponents, use f64 (with potential loss of precision)
+        let base = a.to_f64().ok_or("Cannot convert base to f64")?;
+        let exp = b.to_f64().ok_or("Cannot convert exponent to f64")?;
+        let result = base.powf(exp);
+
+        if result.is_nan() || result.is_infinite() {
+            return Err("Invalid exponentiation result".into());
         }
-        _ => return Err("Invalid operator".into()),
+
+        let result = Decimal::from_f64(result).ok_or("Result too large for decimal")?;
+        Ok(ctx.round_intermediate(result))
     }
-    Ok(())
 }
 
 fn factorial(n: &Decimal) -> Result<Decimal, Box<dyn Error>> {
     if *n < Decimal::ZERO {
         return Err("Cannot compute factorial of negative number".into());
     }
+    if !n.fract().is_zero() {
+        return Err("Factorial requires an integer".into());
+    }
 
     let n_int = n.to_i128().ok_or("Number too large for factorial")?;
-    if n_int > 20 {
-        return Err("Factorial result too large".into());
-    }
 
+    // Overflows after at most 28 multiplications, so large arguments fail quickly
     let mut result = Decimal::ONE;
     for i in 1..=n_int {
-        result *= Decimal::from(i);
+        result = result
+            .checked_mul(Decimal::from(i))
+            .ok_or("Factorial result too large")?;
     }
     Ok(result)
 }
 
-#[allow(dead_code)]
-fn gcd(mut a: i128, mut b: i128) -> i128 {
-    a = a.abs();
-    b = b.abs();
-    while b != 0 {
-        let t = b;
-        b = a % b;
-        a = t;
+/// Converts a function argument to an exact integer.
+fn to_integer(name: &str, value: Decimal) -> Result<i128, Box<dyn Error>> {
+    if !value.fract().is_zero() {
+        return Err(format!("{} requires integer arguments", name).into());
+    }
+    value
+        .to_i128()
+        .ok_or_else(|| format!("Argument to {} is too large", name).into())
+}
+
+/// Converts a function argument to an exact non-negative integer.
+fn to_natural(name: &str, value: Decimal) -> Result<u128, Box<dyn Error>> {
+    let n = to_integer(name, value)?;
+    u128::try_from(n).map_err(|_| format!("{} requires non-negative arguments", name).into())
+}
+
+/// Converts an exact integer result back to a Decimal.
+fn from_natural(n: u128) -> Result<Decimal, Box<dyn Error>> {
+    Decimal::from_u128(n).ok_or_else(|| "Result too large".into())
+}
+
+fn from_bool(b: bool) -> Decimal {
+    if b { Decimal::ONE } else { Decimal::ZERO }
+}
+
+/// Checks that a function received exactly `expected` arguments.
+fn expect_arity<T>(name: &str, args: &[T], expected: usize) -> Result<(), Box<dyn Error>> {
+    if args.len() != expected {
+        return Err(format!(
+            "{} expects {} argument{}, got {}",
+            name,
+            expected,
+            if expected == 1 { "" } else { "s" },
+            args.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Calls a function added by the host application.
+///
+/// Its result is taken to be exact unless an argument is an uncertain value.
+fn call_custom(
+    function: &dyn Function,
+    args: &[Value],
+    eval: &Evaluation,
+) -> Result<Value, Box<dyn Error>> {
+    expect_arity(function.name(), args, function.arity())?;
+    eval.track(!args.iter().any(|a| matches!(a, Value::Uncertain { .. })));
+    function.call(args)
+}
+
+/// Calls the named built-in function with the given arguments.
+fn call_function(name: &str, args: &[Value], eval: &Evaluation) -> Result<Value, Box<dyn Error>> {
+    let ctx = &eval.options.rounding;
+    eval.track(!args.iter().any(|a| matches!(a, Value::Uncertain { .. })));
+    match name {
+        "interval" => {
+            expect_arity(name, args, 2)?;
+            match (args[0], args[1]) {
+                (Value::Number(a), Value::Number(b)) => Ok(Value::interval(a, b)),
+                _ => Err("interval requires exact bounds".into()),
+            }
+        }
+        "frac" => {
+            expect_arity(name, args, 1)?;
+            remainder(args[0], Value::Number(Decimal::ONE))
+        }
+        "weekday" => {
+            expect_arity(name, args, 1)?;
+            let days = match args[0] {
+                Value::Date(days) => days,
+                Value::DateTime(seconds) => seconds.div_euclid(SECONDS_PER_DAY),
+                other => {
+                    return Err(format!("weekday expects a date, got {}", other.kind()).into());
+                }
+            };
+            Ok(Value::Number(Decimal::from(datetime::weekday(days))))
+        }
+        _ if INTEGER_FUNCTIONS.contains(&name) => {
+            let numbers = args
+                .iter()
+                .map(Value::as_number)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("{} requires exact arguments", name))?;
+            Ok(Value::Number(call_decimal_function(
+                name,
+                &numbers,
+                ctx,
+                &eval.meter.deadline(),
+            )?))
+        }
+        _ => Value::propagate(args, |x| {
+            let y = call_decimal_function(name, x, ctx, &eval.meter.deadline())?;
+            eval.track(exact_function(name, x, y));
+            Ok(y)
+        }),
+    }
+}
+
+/// Calls the named built-in function with exact arguments, giving up on a long factorization
+/// or prime search once the deadline passes.
+fn call_decimal_function(
+    name: &str,
+    args: &[Decimal],
+    ctx: &RoundingContext,
+    deadline: &Deadline,
+) -> Result<Decimal, Box<dyn Error>> {
+    match name {
+        "gcd" | "lcm" => {
+            expect_arity(name, args, 2)?;
+            let a = to_integer(name, args[0])?.unsigned_abs();
+            let b = to_integer(name, args[1])?.unsigned_abs();
+            if name == "gcd" {
+                from_natural(number_theory::gcd(a, b))
+            } else {
+                from_natural(number_theory::lcm(a, b).ok_or("Result too large")?)
+            }
+        }
+        "is_prime" => {
+            expect_arity(name, args, 1)?;
+            let n = to_integer(name, args[0])?;
+            Ok(from_bool(n > 0 && number_theory::is_prime(n as u128)))
+        }
+        "next_prime" => {
+            expect_arity(name, args, 1)?;
+            let n = to_integer(name, args[0])?.max(0) as u128;
+            from_natural(number_theory::next_prime_within(n, deadline)?.ok_or("Result too large")?)
+        }
+        "totient" => {
+            expect_arity(name, args, 1)?;
+            from_natural(number_theory::totient_within(
+                to_natural(name, args[0])?,
+                deadline,
+            )?)
+        }
+        "powmod" => {
+            expect_arity(name, args, 3)?;
+            let m = to_natural(name, args[2])?;
+            if m == 0 {
+                return Err("powmod modulus must be positive".into());
+            }
+            let base = to_integer(name, args[0])?.rem_euclid(m as i128) as u128;
+            let exp = to_integer(name, args[1])?;
+            // A negative exponent raises the modular inverse instead
+            let base = if exp < 0 {
+                number_theory::modinv(base, m)
+                    .ok_or_else(|| format!("{} has no inverse modulo {}", args[0], m))?
+            } else {
+                base
+            };
+            from_natural(number_theory::powmod(base, exp.unsigned_abs(), m))
+        }
+        "modinv" => {
+            expect_arity(name, args, 2)?;
+            let m = to_natural(name, args[1])?;
+            if m == 0 {
+                return Err("modinv modulus must be positive".into());
+            }
+            let a = to_integer(name, args[0])?.rem_euclid(m as i128) as u128;
+            let inv = number_theory::modinv(a, m)
+                .ok_or_else(|| format!("{} has no inverse modulo {}", args[0], m))?;
+            from_natural(inv)
+        }
+        "crt" => {
+            if args.is_empty() || !args.len().is_multiple_of(2) {
+                return Err("crt expects residue, modulus pairs".into());
+            }
+            let mut congruences = Vec::with_capacity(args.len() / 2);
+            for pair in args.chunks(2) {
+                let m = to_natural(name, pair[1])?;
+                if m == 0 {
+                    return Err("crt moduli must be positive".into());
+                }
+                let r = to_integer(name, pair[0])?.rem_euclid(m as i128) as u128;
+                congruences.push((r, m));
+            }
+            let (x, _) = number_theory::crt(&congruences)
+                .ok_or("No solution to the system of congruences")?;
+            from_natural(x)
+        }
+        "fermat" => {
+            if args.len() != 1 && args.len() != 2 {
+                return Err(format!("fermat expects 1 or 2 arguments, got {}", args.len()).into());
+            }
+            let n = to_integer(name, args[0])?;
+            let base = match args.get(1) {
+                Some(b) => to_natural(name, *b)?,
+                None => 2,
+            };
+            Ok(from_bool(
+                n > 0 && number_theory::fermat_test(n as u128, base),
+            ))
+        }
+        "sinh" | "cosh" | "tanh" | "asinh" | "acosh" | "atanh" | "sech" | "csch" | "coth" => {
+            expect_arity(name, args, 1)?;
+            let f = match name {
+                "sinh" => decimal_math::sinh,
+                "cosh" => decimal_math::cosh,
+                "tanh" => decimal_math::tanh,
+                "asinh" => decimal_math::asinh,
+                "acosh" => decimal_math::acosh,
+                "atanh" => decimal_math::atanh,
+                "sech" => decimal_math::sech,
+                "csch" => decimal_math::csch,
+                _ => decimal_math::coth,
+            };
+            Ok(ctx.round_intermediate(f(args[0])?))
+        }
+        "cbrt" => {
+            expect_arity(name, args, 1)?;
+            Ok(ctx.round_intermediate(decimal_math::cbrt(args[0])?))
+        }
+        "root" => {
+            expect_arity(name, args, 2)?;
+            let degree = u32::try_from(to_integer(name, args[1])?)
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or("root requires a positive degree")?;
+            Ok(ctx.round_intermediate(decimal_math::root(args[0], degree)?))
+        }
+        "round" | "floor" | "ceil" | "trunc" => {
+            if args.len() != 1 && args.len() != 2 {
+                return Err(
+                    format!("{} expects 1 or 2 arguments, got {}", name, args.len()).into(),
+                );
+            }
+            let places = match args.get(1) {
+                Some(p) => to_integer(name, *p)?.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+                None => 0,
+            };
+            // round() follows the active strategy, the others have a fixed direction
+            let rounding = match name {
+                "round" => ctx.rounding,
+                "floor" => Rounding::Floor,
+                "ceil" => Rounding::Ceiling,
+                _ => Rounding::TowardZero,
+            };
+            round_places(args[0], places, rounding).ok_or_else(|| "Result too large".into())
+        }
+        _ => Err(format!("Unknown function: {}", name).into()),
     }
-    a
 }
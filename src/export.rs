@@ -0,0 +1,437 @@
+//! Export module for the calculator application.
+//!
+//! This module renders an [`Expr`] as LaTeX or as presentation MathML, for pasting into
+//! documents. Division is drawn as a fraction, powers as superscripts and roots with a
+//! radical sign, and parentheses are added exactly where the canonical printer adds them,
+//! so the rendering is grouped the way the calculator read the input. An evaluation result
+//! can be appended after `=` or `≈`.
+
+use crate::expr::{ATOM, BinaryOp, Expr};
+use crate::operators::{Associativity, Fixity, MULTIPLICATIVE, POWER};
+use crate::rounding::RoundingContext;
+use crate::value::{Computed, Value};
+
+/// Renders an expression as LaTeX math, without surrounding `$` delimiters.
+///
+/// ```
+/// use fermat::expr::parse;
+/// use fermat::export::latex;
+///
+/// let expr = parse("sqrt(x^2 + 1) / 2").unwrap();
+/// assert_eq!(latex(&expr), r"\frac{\sqrt{x^{2} + 1}}{2}");
+/// ```
+pub fn latex(expr: &Expr) -> String {
+    render(&Latex, expr)
+}
+
+/// Renders an expression as a presentation MathML `<math>` element.
+pub fn mathml(expr: &Expr) -> String {
+    Mathml.math(render(&Mathml, expr))
+}
+
+/// Renders an expression followed by its result as LaTeX, as in `\frac{1}{3} \approx 0.33`.
+pub fn latex_equation(expr: &Expr, computed: &Computed, ctx: &RoundingContext) -> String {
+    equation(&Latex, expr, computed, ctx)
+}
+
+/// Renders an expression followed by its result as a presentation MathML `<math>` element.
+pub fn mathml_equation(expr: &Expr, computed: &Computed, ctx: &RoundingContext) -> String {
+    Mathml.math(equation(&Mathml, expr, computed, ctx))
+}
+
+/// The building blocks of a rendering, one implementation per output language.
+trait Notation {
+    /// A number as formatted, which may start with a minus sign.
+    fn number(&self, text: &str) -> String;
+    fn variable(&self, name: &str) -> String;
+    /// Text shown upright, such as a date or a unit.
+    fn text(&self, text: &str) -> String;
+    /// An operator or relation, given by its plain symbol such as `*` or `≈`.
+    fn operator(&self, symbol: &str) -> String;
+    /// Parts laid out one after the other as a single group.
+    fn row(&self, parts: &[String]) -> String;
+    /// The narrow space between a quantity and its unit.
+    fn thin_space(&self) -> String;
+    /// A group between a pair of stretching delimiters such as `(` and `)`.
+    fn fenced(&self, open: &str, inner: &str, close: &str) -> String;
+    fn fraction(&self, numerator: &str, denominator: &str) -> String;
+    fn power(&self, base: &str, exponent: &str) -> String;
+    fn root(&self, radicand: &str, index: Option<&str>) -> String;
+    /// A function name, before its parenthesized arguments.
+    fn function(&self, name: &str) -> String;
+}
+
+/// Renders the expression followed by the relation and the result.
+fn equation(
+    notation: &impl Notation,
+    expr: &Expr,
+    computed: &Computed,
+    ctx: &RoundingContext,
+) -> String {
+    notation.row(&[
+        render(notation, expr),
+        notation.operator(computed.relation(ctx)),
+        result(notation, &computed.value, ctx),
+    ])
+}
+
+/// Renders a result as displayed, with numbers set as numbers and anything else as text.
+fn result(notation: &impl Notation, value: &Value, ctx: &RoundingContext) -> String {
+    let shown = value.format(ctx);
+    match value {
+        Value::Number(_) => notation.number(&shown),
+        Value::Uncertain { .. } => match shown.split_once(" ± ") {
+            Some((mean, sigma)) => notation.row(&[
+                notation.number(mean),
+                notation.operator("±"),
+                notation.number(sigma),
+            ]),
+            None => notation.text(&shown),
+        },
+        Value::Interval { .. } => {
+            match shown
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .and_then(|s| s.split_once(", "))
+            {
+                Some((lo, hi)) => {
+                    let bounds = notation.row(&[
+                        notation.number(lo),
+                        notation.operator(","),
+                        notation.number(hi),
+                    ]);
+                    notation.fenced("[", &bounds, "]")
+                }
+                None => notation.text(&shown),
+            }
+        }
+        _ => notation.text(&shown),
+    }
+}
+
+/// Returns how tightly an expression binds once rendered.
+///
+/// A fraction is set apart by its bar, so it needs no parentheses next to other operators.
+fn binding(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary(BinaryOp::Divide, ..) => ATOM,
+        _ => expr.precedence(),
+    }
+}
+
+/// Renders an operand, in parentheses if `wrap` is set.
+fn operand(notation: &impl Notation, expr: &Expr, wrap: bool) -> String {
+    let inner = render(notation, expr);
+    if wrap {
+        notation.fenced("(", &inner, ")")
+    } else {
+        inner
+    }
+}
+
+fn render(notation: &impl Notation, expr: &Expr) -> String {
+    match expr {
+        Expr::Number(n) => notation.number(&n.to_string()),
+        Expr::Variable(name) => notation.variable(name),
+        Expr::Literal(text) => notation.text(text),
+        Expr::Negate(e) => {
+            // Wrapped under the same conditions as in the canonical form
+            let text = e.to_string();
+            let wrap = (binding(e) < ATOM && !matches!(**e, Expr::Factorial(_) | Expr::Unit(..)))
+                || text.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '-');
+            notation.row(&[notation.operator("-"), operand(notation, e, wrap)])
+        }
+        Expr::Factorial(e) => {
+            let wrap = e.precedence() < ATOM || e.is_negative_number();
+            notation.row(&[operand(notation, e, wrap), notation.operator("!")])
+        }
+        Expr::Unit(e, unit) => {
+            let wrap = e.precedence() < ATOM || matches!(**e, Expr::Number(_));
+            notation.row(&[
+                operand(notation, e, wrap),
+                notation.thin_space(),
+                notation.text(unit),
+            ])
+        }
+        Expr::Convert(e, target) => notation.row(&[
+            render(notation, e),
+            notation.operator("in"),
+            notation.text(target),
+        ]),
+        Expr::Call(name, args) => call(notation, name, args),
+        Expr::Operator(operator, args) => {
+            let (symbol, p) = (operator.symbol.as_str(), operator.precedence);
+            match (operator.fixity, &args[..]) {
+                (Fixity::Prefix, [e]) => notation.row(&[
+                    notation.operator(symbol),
+                    operand(notation, e, binding(e) <= p || e.is_negative_number()),
+                ]),
+                (Fixity::Postfix, [e]) => notation.row(&[
+                    operand(notation, e, binding(e) < p || e.is_negative_number()),
+                    notation.operator(symbol),
+                ]),
+                (_, [left, right]) => {
+                    let right_associative = operator.associativity == Associativity::Right;
+                    notation.row(&[
+                        operand(
+                            notation,
+                            left,
+                            binding(left) < p || (right_associative && binding(left) == p),
+                        ),
+                        notation.operator(symbol),
+                        operand(
+                            notation,
+                            right,
+                            binding(right) < p || (!right_associative && binding(right) == p),
+                        ),
+                    ])
+                }
+                _ => notation.text(&expr.to_string()),
+            }
+        }
+        Expr::Binary(BinaryOp::Divide, left, right) => {
+            notation.fraction(&render(notation, left), &render(notation, right))
+        }
+        Expr::Binary(BinaryOp::Power, base, exponent) => {
+            let wrap = base.precedence() <= POWER || base.is_negative_number();
+            notation.power(&operand(notation, base, wrap), &render(notation, exponent))
+        }
+        Expr::Binary(op, left, right) => {
+            let p = op.precedence();
+            let wrap_left = binding(left) < p;
+            let wrap_right = match **right {
+                Expr::Negate(_) => p > MULTIPLICATIVE,
+                _ => binding(right) <= p,
+            };
+            notation.row(&[
+                operand(notation, left, wrap_left),
+                notation.operator(op.symbol()),
+                operand(notation, right, wrap_right),
+            ])
+        }
+    }
+}
+
+/// Renders a function call, using the usual notation for roots, absolute values, floor
+/// and ceiling.
+fn call(notation: &impl Notation, name: &str, args: &[Expr]) -> String {
+    let arg = |i: usize| render(notation, &args[i]);
+    match (name, args.len()) {
+        ("sqrt", 1) => notation.root(&arg(0), None),
+        ("cbrt", 1) => notation.root(&arg(0), Some(&notation.number("3"))),
+        ("root", 2) => notation.root(&arg(0), Some(&arg(1))),
+        ("abs", 1) => notation.fenced("|", &arg(0), "|"),
+        ("floor", 1) => notation.fenced("⌊", &arg(0), "⌋"),
+        ("ceil", 1) => notation.fenced("⌈", &arg(0), "⌉"),
+        _ => {
+            let mut parts = Vec::new();
+            for (i, a) in args.iter().enumerate() {
+                if i > 0 {
+                    parts.push(notation.operator(","));
+                }
+                parts.push(render(notation, a));
+            }
+            let arguments = notation.fenced("(", &notation.row(&parts), ")");
+            // A single part, so that the arguments follow the name without a space
+            notation.row(&[notation.function(name) + &arguments])
+        }
+    }
+}
+
+/// LaTeX math mode.
+struct Latex;
+
+/// Functions LaTeX has a command for.
+const LATEX_FUNCTIONS: &[&str] = &["sinh", "cosh", "tanh", "coth", "gcd"];
+
+/// Escapes the characters LaTeX treats specially in text.
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\textbackslash{}"),
+            '~' => escaped.push_str(r"\textasciitilde{}"),
+            '^' => escaped.push_str(r"\textasciicircum{}"),
+            '$' | '%' | '&' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Notation for Latex {
+    fn number(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn variable(&self, name: &str) -> String {
+        if name.chars().count() == 1 {
+            name.to_string()
+        } else {
+            format!(r"\mathit{{{}}}", escape_latex(name))
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        format!(r"\text{{{}}}", escape_latex(text))
+    }
+
+    fn operator(&self, symbol: &str) -> String {
+        match symbol {
+            "*" => r"\cdot".to_string(),
+            "%" => r"\bmod".to_string(),
+            "±" => r"\pm".to_string(),
+            "≈" => r"\approx".to_string(),
+            "in" => r"\text{ in }".to_string(),
+            // A host-defined operator written as a word, such as `mod`
+            _ if symbol.chars().all(char::is_alphabetic) => {
+                format!(r"\operatorname{{{}}}", symbol)
+            }
+            _ => escape_latex(symbol),
+        }
+    }
+
+    fn row(&self, parts: &[String]) -> String {
+        let mut row = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            // Space between operands and operators, but not after a leading minus sign or
+            // before a factorial or comma
+            let sign = i == 1 && parts[0] == "-";
+            if i > 0 && !sign && part != "!" && part != "," {
+                row.push(' ');
+            }
+            row.push_str(part);
+        }
+        row
+    }
+
+    fn thin_space(&self) -> String {
+        r"\,".to_string()
+    }
+
+    fn fenced(&self, open: &str, inner: &str, close: &str) -> String {
+        let delimiter = |d: &str| match d {
+            "|" => r"\lvert".to_string(),
+            "⌊" => r"\lfloor".to_string(),
+            "⌋" => r"\rfloor".to_string(),
+            "⌈" => r"\lceil".to_string(),
+            "⌉" => r"\rceil".to_string(),
+            _ => d.to_string(),
+        };
+        let close = match close {
+            "|" => r"\rvert".to_string(),
+            _ => delimiter(close),
+        };
+        format!(r"\left{} {} \right{}", delimiter(open), inner, close)
+    }
+
+    fn fraction(&self, numerator: &str, denominator: &str) -> String {
+        format!(r"\frac{{{}}}{{{}}}", numerator, denominator)
+    }
+
+    fn power(&self, base: &str, exponent: &str) -> String {
+        format!("{}^{{{}}}", base, exponent)
+    }
+
+    fn root(&self, radicand: &str, index: Option<&str>) -> String {
+        match index {
+            Some(index) => format!(r"\sqrt[{}]{{{}}}", index, radicand),
+            None => format!(r"\sqrt{{{}}}", radicand),
+        }
+    }
+
+    fn function(&self, name: &str) -> String {
+        if LATEX_FUNCTIONS.contains(&name) {
+            format!(r"\{}", name)
+        } else {
+            format!(r"\operatorname{{{}}}", escape_latex(name))
+        }
+    }
+}
+
+/// Presentation MathML.
+struct Mathml;
+
+/// Escapes the characters XML treats specially.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Mathml {
+    fn math(&self, content: String) -> String {
+        format!(
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML">{}</math>"#,
+            content
+        )
+    }
+}
+
+impl Notation for Mathml {
+    fn number(&self, text: &str) -> String {
+        match text.strip_prefix('-') {
+            Some(digits) => format!("<mrow><mo>−</mo><mn>{}</mn></mrow>", escape_xml(digits)),
+            None => format!("<mn>{}</mn>", escape_xml(text)),
+        }
+    }
+
+    fn variable(&self, name: &str) -> String {
+        format!("<mi>{}</mi>", escape_xml(name))
+    }
+
+    fn text(&self, text: &str) -> String {
+        format!("<mtext>{}</mtext>", escape_xml(text))
+    }
+
+    fn operator(&self, symbol: &str) -> String {
+        let symbol = match symbol {
+            "-" => "−",
+            "*" => "⋅",
+            "%" => "mod",
+            _ => symbol,
+        };
+        format!("<mo>{}</mo>", escape_xml(symbol))
+    }
+
+    fn row(&self, parts: &[String]) -> String {
+        format!("<mrow>{}</mrow>", parts.concat())
+    }
+
+    fn thin_space(&self) -> String {
+        r#"<mspace width="0.167em"/>"#.to_string()
+    }
+
+    fn fenced(&self, open: &str, inner: &str, close: &str) -> String {
+        format!(
+            "<mrow><mo>{}</mo>{}<mo>{}</mo></mrow>",
+            escape_xml(open),
+            inner,
+            escape_xml(close)
+        )
+    }
+
+    fn fraction(&self, numerator: &str, denominator: &str) -> String {
+        format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+    }
+
+    fn power(&self, base: &str, exponent: &str) -> String {
+        format!("<msup>{}{}</msup>", base, exponent)
+    }
+
+    fn root(&self, radicand: &str, index: Option<&str>) -> String {
+        match index {
+            Some(index) => format!("<mroot>{}{}</mroot>", radicand, index),
+            None => format!("<msqrt>{}</msqrt>", radicand),
+        }
+    }
+
+    fn function(&self, name: &str) -> String {
+        // Followed by an invisible function application operator
+        format!("<mi>{}</mi><mo>\u{2061}</mo>", escape_xml(name))
+    }
+}
@@ -0,0 +1,1005 @@
+//! Expression module for the calculator application.
+//!
+//! This module reads an expression into a tree, grouped exactly the way the evaluator
+//! groups it, so that printing the tree back shows how an input was understood: `-2^2`
+//! prints as `(-2)^2`, because a minus sign directly before a number is part of the
+//! number. Names that are not functions are read as variables, which lets [`simplify`]
+//! work on expressions such as `2*x + 3*x` as well as on plain numbers. Reading with
+//! [`parse_with_functions`] also knows the functions, operators and variables a host has
+//! registered.
+
+use crate::decimal_math;
+use crate::evaluator::{self, EvalOptions, Lexeme, Token};
+use crate::functions::Registry;
+use crate::operators::{
+    ADDITIVE, Associativity, FACTORIAL, Fixity, MULTIPLICATIVE, Operator, POWER, UNCERTAINTY,
+};
+use crate::value::{Computed, Value};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+/// Binding of literals, variables and function calls, tighter than any operator.
+pub(crate) const ATOM: u8 = u8::MAX;
+
+/// Binding of `in` conversions, looser than any operator.
+const CONVERSION: u8 = 0;
+
+/// An expression tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A number, including a negative one written with a leading minus sign.
+    Number(Decimal),
+    /// A name that is not a function.
+    Variable(String),
+    /// A date, time, duration or money literal, or `today` or `now`, as written.
+    Literal(String),
+    /// A minus sign before anything other than a number.
+    Negate(Box<Expr>),
+    /// The factorial `n!`.
+    Factorial(Box<Expr>),
+    /// An infix operator applied to two operands.
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A call of a built-in or host-defined function, including `sqrt` and `abs`.
+    Call(String, Vec<Expr>),
+    /// A time unit after an operand, as in `(1 + 2) days`.
+    Unit(Box<Expr>, String),
+    /// An `in` conversion to a unit or currency, as in `x in weeks`.
+    Convert(Box<Expr>, String),
+    /// A host-defined operator applied to its operands, one or two as its fixity says.
+    Operator(HostOperator, Vec<Expr>),
+}
+
+/// What printing needs to know of a host-defined operator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostOperator {
+    pub symbol: String,
+    pub fixity: Fixity,
+    pub precedence: u8,
+    pub associativity: Associativity,
+}
+
+impl HostOperator {
+    fn new(operator: &dyn Operator) -> Self {
+        HostOperator {
+            symbol: operator.symbol().to_string(),
+            fixity: operator.fixity(),
+            precedence: operator.precedence(),
+            associativity: operator.associativity(),
+        }
+    }
+}
+
+/// An infix operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    PlusMinus,
+}
+
+impl BinaryOp {
+    /// Returns the operator a token stands for, if it is an infix operator.
+    fn from_token(token: &Token) -> Option<Self> {
+        let op = match token {
+            Token::Plus => BinaryOp::Add,
+            Token::Minus => BinaryOp::Subtract,
+            Token::Multiply => BinaryOp::Multiply,
+            Token::Divide => BinaryOp::Divide,
+            Token::Modulo => BinaryOp::Modulo,
+            Token::Exponentiation => BinaryOp::Power,
+            Token::PlusMinus => BinaryOp::PlusMinus,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Returns how tightly the operator binds, on the evaluator's scale.
+    pub(crate) fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Subtract => ADDITIVE,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => MULTIPLICATIVE,
+            BinaryOp::PlusMinus => UNCERTAINTY,
+            BinaryOp::Power => POWER,
+        }
+    }
+
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Power => "^",
+            BinaryOp::PlusMinus => "±",
+        }
+    }
+}
+
+impl Expr {
+    fn binary(op: BinaryOp, left: Expr, right: Expr) -> Self {
+        Expr::Binary(op, Box::new(left), Box::new(right))
+    }
+
+    /// Returns how tightly the expression binds when printed.
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary(op, ..) => op.precedence(),
+            Expr::Operator(operator, _) => operator.precedence,
+            // A minus sign binds to its operand, so that `-x^2` is `(-x)^2` as `-2^2` is
+            Expr::Negate(_) => POWER,
+            Expr::Factorial(_) | Expr::Unit(..) => FACTORIAL,
+            Expr::Convert(..) => CONVERSION,
+            _ => ATOM,
+        }
+    }
+
+    /// Returns whether the expression mentions a variable.
+    pub fn has_variables(&self) -> bool {
+        match self {
+            Expr::Variable(_) => true,
+            Expr::Number(_) | Expr::Literal(_) => false,
+            Expr::Negate(e) | Expr::Factorial(e) | Expr::Unit(e, _) | Expr::Convert(e, _) => {
+                e.has_variables()
+            }
+            Expr::Binary(_, a, b) => a.has_variables() || b.has_variables(),
+            Expr::Call(_, args) | Expr::Operator(_, args) => args.iter().any(Expr::has_variables),
+        }
+    }
+
+    pub(crate) fn is_negative_number(&self) -> bool {
+        matches!(self, Expr::Number(n) if n.is_sign_negative())
+    }
+}
+
+/// Prints `expr`, in parentheses if `wrap` is set.
+fn write_operand(f: &mut fmt::Formatter<'_>, expr: &Expr, wrap: bool) -> fmt::Result {
+    if wrap {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+/// Prints an infix operator and its operands, which are wrapped in parentheses where they
+/// bind less tightly than the operator.
+fn write_infix(
+    f: &mut fmt::Formatter<'_>,
+    symbol: &str,
+    precedence: u8,
+    associativity: Associativity,
+    left: &Expr,
+    right: &Expr,
+) -> fmt::Result {
+    let p = precedence;
+    let right_associative = associativity == Associativity::Right;
+    let wrap_left = left.precedence() < p
+        || (right_associative && left.precedence() == p)
+        || (right_associative && left.is_negative_number());
+    // A minus sign on the right of an operator binding tighter than `*` is set apart
+    let wrap_right = match right {
+        Expr::Negate(_) => p > MULTIPLICATIVE,
+        _ => right.precedence() < p || (right.precedence() == p && !right_associative),
+    };
+    write_operand(f, left, wrap_left)?;
+    if symbol == "^" {
+        f.write_str(symbol)?;
+    } else {
+        write!(f, " {} ", symbol)?;
+    }
+    write_operand(f, right, wrap_right)
+}
+
+impl fmt::Display for Expr {
+    /// Prints the expression with single spaces around infix operators except `^`, and only
+    /// the parentheses needed to read it back the same way.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Variable(name) | Expr::Literal(name) => f.write_str(name),
+            Expr::Negate(e) => {
+                // A minus sign directly before a digit would become part of the number
+                let text = e.to_string();
+                let wrap = (e.precedence() < ATOM
+                    && !matches!(**e, Expr::Factorial(_) | Expr::Unit(..)))
+                    || text.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '-');
+                f.write_str("-")?;
+                write_operand(f, e, wrap)
+            }
+            Expr::Factorial(e) => {
+                write_operand(f, e, e.precedence() < ATOM || e.is_negative_number())?;
+                f.write_str("!")
+            }
+            Expr::Unit(e, unit) => {
+                // A number followed by a unit would be read as a duration literal
+                let wrap = e.precedence() < ATOM || matches!(**e, Expr::Number(_));
+                write_operand(f, e, wrap)?;
+                write!(f, " {}", unit)
+            }
+            Expr::Convert(e, target) => write!(f, "{} in {}", e, target),
+            Expr::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                f.write_str(")")
+            }
+            Expr::Binary(op, left, right) => {
+                let associativity = if *op == BinaryOp::Power {
+                    Associativity::Right
+                } else {
+                    Associativity::Left
+                };
+                write_infix(f, op.symbol(), op.precedence(), associativity, left, right)
+            }
+            Expr::Operator(operator, args) => {
+                let (symbol, p) = (operator.symbol.as_str(), operator.precedence);
+                match (operator.fixity, &args[..]) {
+                    (Fixity::Infix, [left, right]) => {
+                        write_infix(f, symbol, p, operator.associativity, left, right)
+                    }
+                    (Fixity::Prefix, [e]) => {
+                        f.write_str(symbol)?;
+                        // A symbol ending in a letter would run into a name after it
+                        if symbol.ends_with(|c: char| c.is_alphanumeric()) {
+                            f.write_str(" ")?;
+                        }
+                        write_operand(f, e, e.precedence() <= p || e.is_negative_number())
+                    }
+                    (Fixity::Postfix, [e]) => {
+                        write_operand(f, e, e.precedence() < p || e.is_negative_number())?;
+                        if symbol.starts_with(|c: char| c.is_alphanumeric()) {
+                            f.write_str(" ")?;
+                        }
+                        f.write_str(symbol)
+                    }
+                    _ => Err(fmt::Error),
+                }
+            }
+        }
+    }
+}
+
+/// A piece of the input the parser works on.
+enum Item {
+    Token(Token),
+    Literal(String),
+    Variable(String),
+}
+
+/// Reads an expression into a tree.
+///
+/// Names that are neither functions, units nor currencies are read as variables. Fails on
+/// the first syntax problem.
+pub fn parse(input: &str) -> Result<Expr, Box<dyn Error>> {
+    parse_with_functions(input, &Registry::new())
+}
+
+/// Reads an expression into a tree, resolving names and operators against a function
+/// registry. The registry's variables are read as variables, by their names.
+pub fn parse_with_functions(input: &str, functions: &Registry) -> Result<Expr, Box<dyn Error>> {
+    let (lexemes, problems) = evaluator::lex(input, functions);
+    let problem_at = |span: &Range<usize>| -> Box<dyn Error> {
+        match problems.iter().find(|p| span.contains(&p.span.start)) {
+            Some(problem) => problem.clone().into(),
+            None => format!("Cannot read '{}'", &input[span.clone()]).into(),
+        }
+    };
+    let text = |span: &Range<usize>| input[span.clone()].split_whitespace().collect::<Vec<_>>();
+
+    let mut items: Vec<(Item, Range<usize>)> = Vec::new();
+    let mut previous: Option<Token> = None;
+    for (lexeme, span) in lexemes {
+        let item = match lexeme {
+            Lexeme::Token(
+                token @ (Token::Date(_)
+                | Token::Time(_)
+                | Token::DateTime(_)
+                | Token::Duration(_)
+                | Token::Money(..)
+                | Token::Today
+                | Token::Now),
+            ) => {
+                // A date and a time, or adjacent durations, form a single literal
+                let joined = matches!(
+                    (&previous, &token),
+                    (Some(Token::Date(_)), Token::Time(_))
+                        | (Some(Token::Duration(_)), Token::Duration(_))
+                );
+                previous = Some(token);
+                if joined && let Some((_, before)) = items.pop() {
+                    let span = before.start..span.end;
+                    items.push((Item::Literal(text(&span).join(" ")), span));
+                    continue;
+                }
+                Item::Literal(text(&span).join(" "))
+            }
+            Lexeme::Token(Token::Variable(_)) => Item::Variable(input[span.clone()].to_string()),
+            Lexeme::Token(token) => Item::Token(token),
+            Lexeme::BadOperand if is_identifier(&input[span.clone()]) => {
+                Item::Variable(input[span.clone()].to_string())
+            }
+            Lexeme::BadOperand | Lexeme::BadSymbol => return Err(problem_at(&span)),
+        };
+        if !matches!(item, Item::Literal(_)) {
+            previous = None;
+        }
+        items.push((item, span));
+    }
+
+    let mut parser = Parser {
+        input,
+        items,
+        position: 0,
+    };
+    let expr = parser.expression(CONVERSION)?;
+    match parser.items.get(parser.position) {
+        Some((_, span)) => Err(format!("Unexpected '{}'", &input[span.clone()]).into()),
+        None => Ok(expr),
+    }
+}
+
+/// Returns whether a name can be read as a single identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A precedence-climbing parser over the items of one input.
+struct Parser<'a> {
+    input: &'a str,
+    items: Vec<(Item, Range<usize>)>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Item> {
+        self.items.get(self.position).map(|(item, _)| item)
+    }
+
+    fn peek_token(&self) -> Option<&Token> {
+        match self.peek() {
+            Some(Item::Token(token)) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Describes the item at the current position, for error messages.
+    fn current(&self) -> String {
+        match self.items.get(self.position) {
+            Some((_, span)) => format!("'{}'", &self.input[span.clone()]),
+            None => "the end".to_string(),
+        }
+    }
+
+    /// Reads operands joined by operators that bind at least as tightly as `min`.
+    fn expression(&mut self, min: u8) -> Result<Expr, Box<dyn Error>> {
+        let mut left = self.operand()?;
+        while let Some(token) = self.peek_token() {
+            if let Token::Operator(operator) = token {
+                let operator = HostOperator::new(operator.as_ref());
+                let p = operator.precedence;
+                if operator.fixity == Fixity::Prefix {
+                    return Err(format!("Missing operator before {}", self.current()).into());
+                }
+                if p < min {
+                    break;
+                }
+                self.position += 1;
+                left = if operator.fixity == Fixity::Postfix {
+                    Expr::Operator(operator, vec![left])
+                } else {
+                    let next = match operator.associativity {
+                        Associativity::Right => p,
+                        Associativity::Left => p.saturating_add(1),
+                    };
+                    let right = self.expression(next)?;
+                    Expr::Operator(operator, vec![left, right])
+                };
+                continue;
+            }
+            if let Some(op) = BinaryOp::from_token(token) {
+                let p = op.precedence();
+                if p < min {
+                    break;
+                }
+                self.position += 1;
+                let next = if op == BinaryOp::Power { p } else { p + 1 };
+                left = Expr::binary(op, left, self.expression(next)?);
+                continue;
+            }
+            let target = match token {
+                Token::In(unit) => unit.to_string(),
+                Token::InCurrency(currency) => currency.to_string(),
+                Token::RightParen | Token::Comma => break,
+                _ => return Err(format!("Missing operator before {}", self.current()).into()),
+            };
+            // A conversion applies to everything back to the enclosing parenthesis
+            if min > CONVERSION {
+                break;
+            }
+            self.position += 1;
+            left = Expr::Convert(Box::new(left), target);
+        }
+        if let Some(Item::Literal(_) | Item::Variable(_)) = self.peek() {
+            return Err(format!("Missing operator before {}", self.current()).into());
+        }
+        Ok(left)
+    }
+
+    /// Reads one operand with any factorials and units after it.
+    fn operand(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let Some((item, _)) = self.items.get(self.position) else {
+            return Err("Missing operand at the end".into());
+        };
+        let mut expr = match item {
+            Item::Literal(text) => Expr::Literal(text.clone()),
+            Item::Variable(name) => {
+                let name = name.clone();
+                self.position += 1;
+                return match self.peek_token() {
+                    Some(Token::LeftParen) => self.call(name),
+                    _ => self.postfix(Expr::Variable(name)),
+                };
+            }
+            Item::Token(token) => match token {
+                Token::Number(n) => Expr::Number(*n),
+                Token::Minus => {
+                    self.position += 1;
+                    if let Some(&Token::Number(n)) = self.peek_token() {
+                        self.position += 1;
+                        return self.postfix(Expr::Number(-n));
+                    }
+                    // Anything else is negated as a whole, with its factorials and units
+                    return Ok(Expr::Negate(Box::new(self.operand()?)));
+                }
+                Token::LeftParen => {
+                    self.position += 1;
+                    let inner = self.expression(CONVERSION)?;
+                    self.expect_right_paren()?;
+                    return self.postfix(inner);
+                }
+                Token::Sqrt => return self.call_at("sqrt"),
+                Token::Abs => return self.call_at("abs"),
+                Token::Function(name) => {
+                    let name = *name;
+                    return self.call_at(name);
+                }
+                Token::Custom(function) => {
+                    let name = function.name().to_string();
+                    return self.call_at(&name);
+                }
+                Token::Operator(operator) if operator.fixity() == Fixity::Prefix => {
+                    let operator = HostOperator::new(operator.as_ref());
+                    self.position += 1;
+                    let operand = self.prefix_operand(operator.precedence)?;
+                    return Ok(Expr::Operator(operator, vec![operand]));
+                }
+                _ => return Err(format!("Missing operand before {}", self.current()).into()),
+            },
+        };
+        self.position += 1;
+        expr = self.postfix(expr)?;
+        Ok(expr)
+    }
+
+    /// Reads the operand of a prefix operator binding at `precedence`.
+    ///
+    /// As in the evaluator, the operator is applied once an operator binding no tighter
+    /// follows its operand, except a right-associative one binding as tightly, which takes
+    /// the operand first: `√4^2` is `√(4^2)` when `√` binds like `^`.
+    fn prefix_operand(&mut self, precedence: u8) -> Result<Expr, Box<dyn Error>> {
+        let operand = self.expression(precedence.saturating_add(1))?;
+        match self.peek_token() {
+            Some(Token::Exponentiation) if precedence == POWER => {
+                self.position += 1;
+                let right = self.expression(POWER)?;
+                Ok(Expr::binary(BinaryOp::Power, operand, right))
+            }
+            Some(Token::Operator(operator)) => {
+                let operator = HostOperator::new(operator.as_ref());
+                if operator.fixity != Fixity::Infix
+                    || operator.associativity != Associativity::Right
+                    || operator.precedence != precedence
+                {
+                    return Ok(operand);
+                }
+                self.position += 1;
+                let right = self.expression(precedence)?;
+                Ok(Expr::Operator(operator, vec![operand, right]))
+            }
+            _ => Ok(operand),
+        }
+    }
+
+    /// Applies any factorials and units that follow an operand.
+    fn postfix(&mut self, mut expr: Expr) -> Result<Expr, Box<dyn Error>> {
+        loop {
+            match self.peek_token() {
+                Some(Token::Factorial) => expr = Expr::Factorial(Box::new(expr)),
+                Some(Token::Unit(unit)) => expr = Expr::Unit(Box::new(expr), unit.to_string()),
+                _ => return Ok(expr),
+            }
+            self.position += 1;
+        }
+    }
+
+    /// Reads a call of the built-in function named at the current position.
+    fn call_at(&mut self, name: &str) -> Result<Expr, Box<dyn Error>> {
+        self.position += 1;
+        self.call(name.to_string())
+    }
+
+    /// Reads the parenthesised arguments of a call.
+    fn call(&mut self, name: String) -> Result<Expr, Box<dyn Error>> {
+        if !matches!(self.peek_token(), Some(Token::LeftParen)) {
+            return Err(format!("Expected '(' after {}", name).into());
+        }
+        self.position += 1;
+        let mut args = Vec::new();
+        if !matches!(self.peek_token(), Some(Token::RightParen)) {
+            loop {
+                args.push(self.expression(CONVERSION)?);
+                if !matches!(self.peek_token(), Some(Token::Comma)) {
+                    break;
+                }
+                self.position += 1;
+            }
+        }
+        self.expect_right_paren()?;
+        self.postfix(Expr::Call(name, args))
+    }
+
+    fn expect_right_paren(&mut self) -> Result<(), Box<dyn Error>> {
+        if !matches!(self.peek_token(), Some(Token::RightParen)) {
+            return Err(format!("Expected ')' before {}", self.current()).into());
+        }
+        self.position += 1;
+        Ok(())
+    }
+}
+
+/// Simplifies an expression.
+///
+/// Parts without variables are folded into a number when they evaluate exactly, so `1/4`
+/// becomes `0.25` but `1/3` is kept. Sums and products are brought into a canonical form
+/// that collects like terms (`2*x + 3*x` is `5 * x`), drops identities such as `x*1` and
+/// `x + 0`, and combines powers of the same base (`x^2 * x` is `x^3`, `(x^2)^3` is
+/// `x^6`).
+///
+/// A rewrite that would make the expression defined where it was not is not made: `x/x`,
+/// `0/x` and `x^0.5 * x^0.5` are kept, as they fail for some `x` while `1`, `0` and `x`
+/// would not. Powers of a variable are therefore only combined when their exponents are
+/// integers of the same sign, while powers of a positive number always are.
+pub fn simplify(expr: &Expr) -> Expr {
+    let expr = match expr {
+        Expr::Number(_) | Expr::Variable(_) | Expr::Literal(_) => return expr.clone(),
+        Expr::Negate(e) => Expr::Negate(Box::new(simplify(e))),
+        Expr::Factorial(e) => Expr::Factorial(Box::new(simplify(e))),
+        Expr::Unit(e, unit) => Expr::Unit(Box::new(simplify(e)), unit.clone()),
+        Expr::Convert(e, target) => Expr::Convert(Box::new(simplify(e)), target.clone()),
+        Expr::Binary(op, a, b) => Expr::binary(*op, simplify(a), simplify(b)),
+        Expr::Call(name, args) => Expr::Call(name.clone(), args.iter().map(simplify).collect()),
+        Expr::Operator(operator, args) => {
+            Expr::Operator(operator.clone(), args.iter().map(simplify).collect())
+        }
+    };
+    if !expr.has_variables()
+        && let Some(n) = fold(&expr)
+    {
+        return Expr::Number(n);
+    }
+    match expr {
+        Expr::Negate(_)
+        | Expr::Binary(
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Power,
+            ..,
+        ) => Sum::from_expr(&expr).to_expr(),
+        _ => expr,
+    }
+}
+
+/// Evaluates an expression without variables, if its result is an exact number.
+fn fold(expr: &Expr) -> Option<Decimal> {
+    let tokens = evaluator::tokenize(&expr.to_string()).ok()?;
+    match evaluator::compute(&tokens, &EvalOptions::default()).ok()? {
+        Computed {
+            value: Value::Number(n),
+            exact: true,
+        } => Some(n),
+        _ => None,
+    }
+}
+
+/// Returns `a + b` if it is exact.
+fn exact_add(a: Decimal, b: Decimal) -> Option<Decimal> {
+    let sum = a.checked_add(b)?;
+    (sum.checked_sub(b) == Some(a)).then_some(sum)
+}
+
+/// Returns `a * b` if it is exact.
+fn exact_mul(a: Decimal, b: Decimal) -> Option<Decimal> {
+    let product = a.checked_mul(b)?;
+    (b.is_zero() || product.checked_div(b) == Some(a)).then_some(product)
+}
+
+/// Returns `1 / a` if it is exact.
+fn exact_inverse(a: Decimal) -> Option<Decimal> {
+    let inverse = Decimal::ONE.checked_div(a)?;
+    (inverse.checked_mul(a) == Some(Decimal::ONE)).then_some(inverse)
+}
+
+/// Returns `a ^ n` if it is exact.
+fn exact_power(a: Decimal, n: Decimal) -> Option<Decimal> {
+    if a == Decimal::ONE {
+        return Some(a);
+    }
+    if !n.fract().is_zero() {
+        return None;
+    }
+    let base = if n.is_sign_negative() {
+        exact_inverse(a)?
+    } else {
+        a
+    };
+    let exponent = n.abs().to_u32()?;
+    let power = decimal_math::powi(base, exponent)?;
+    decimal_math::is_root(base, power, exponent).then_some(power)
+}
+
+/// Returns whether an expression is defined for every value of its variables, as a
+/// polynomial is.
+fn is_total(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::Variable(_) => true,
+        Expr::Negate(e) => is_total(e),
+        Expr::Binary(BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply, a, b) => {
+            is_total(a) && is_total(b)
+        }
+        Expr::Binary(BinaryOp::Power, a, b) => {
+            matches!(**b, Expr::Number(n) if is_natural(n)) && is_total(a)
+        }
+        _ => false,
+    }
+}
+
+/// Returns whether a number is a positive integer.
+fn is_natural(n: Decimal) -> bool {
+    n.is_sign_positive() && !n.is_zero() && n.fract().is_zero()
+}
+
+/// An expression that is not simplified further, raised to a power.
+#[derive(Debug, Clone)]
+struct Factor {
+    /// The printed base, which identifies like factors.
+    key: String,
+    base: Expr,
+    exponent: Decimal,
+}
+
+impl Factor {
+    /// Returns whether the base is a positive number, whose powers are always defined.
+    fn is_positive_number(&self) -> bool {
+        matches!(self.base, Expr::Number(n) if n.is_sign_positive() && !n.is_zero())
+    }
+
+    /// Returns whether multiplying by the base raised to `exponent` may add the exponents
+    /// without changing where the product is defined.
+    fn combines_with(&self, exponent: Decimal) -> bool {
+        let integers = self.exponent.fract().is_zero() && exponent.fract().is_zero();
+        let same_sign = self.exponent.is_sign_negative() == exponent.is_sign_negative();
+        self.is_positive_number() || (integers && same_sign)
+    }
+
+    /// Returns whether raising the factor to `n` may multiply the exponents without
+    /// changing where the power is defined.
+    fn raises_to(&self, n: Decimal) -> bool {
+        let integers = self.exponent.fract().is_zero() && n.fract().is_zero();
+        self.is_positive_number()
+            || self.exponent == Decimal::ONE
+            || (integers && (n.is_sign_positive() || self.exponent.is_sign_positive()))
+    }
+
+    fn is_total(&self) -> bool {
+        is_natural(self.exponent) && is_total(&self.base)
+    }
+}
+
+/// A coefficient times a product of factors.
+#[derive(Debug, Clone)]
+struct Term {
+    coefficient: Decimal,
+    factors: Vec<Factor>,
+}
+
+impl Term {
+    /// Returns what identifies like terms: the factors regardless of their order.
+    fn key(&self) -> Vec<(String, Decimal)> {
+        let mut key: Vec<_> = self
+            .factors
+            .iter()
+            .map(|f| (f.key.clone(), f.exponent.normalize()))
+            .collect();
+        key.sort();
+        key
+    }
+
+    fn multiply(&self, other: &Term) -> Option<Term> {
+        let mut product = Term {
+            coefficient: exact_mul(self.coefficient, other.coefficient)?,
+            factors: self.factors.clone(),
+        };
+        for factor in &other.factors {
+            let like = |f: &&mut Factor| f.key == factor.key && f.combines_with(factor.exponent);
+            match product.factors.iter_mut().find(like) {
+                Some(existing) => {
+                    existing.exponent = exact_add(existing.exponent, factor.exponent)?
+                }
+                None => product.factors.push(factor.clone()),
+            }
+        }
+        product.factors.retain(|f| !f.exponent.is_zero());
+        Some(product)
+    }
+
+    fn power(&self, n: Decimal) -> Option<Term> {
+        // A fractional power of a product is the product of the powers only for positive
+        // factors, as in (-1 * -1)^0.5
+        let variables = self.factors.iter().filter(|f| !f.is_positive_number());
+        if !n.fract().is_zero() && (self.coefficient.is_sign_negative() || variables.count() > 1) {
+            return None;
+        }
+        let mut factors = Vec::new();
+        let coefficient = match exact_power(self.coefficient, n) {
+            Some(coefficient) => coefficient,
+            None => {
+                // Keep a coefficient such as the 2 in 2^0.5 as a factor instead
+                factors.push(Factor {
+                    key: self.coefficient.to_string(),
+                    base: Expr::Number(self.coefficient),
+                    exponent: n,
+                });
+                Decimal::ONE
+            }
+        };
+        for factor in &self.factors {
+            if !factor.raises_to(n) {
+                return None;
+            }
+            let exponent = exact_mul(factor.exponent, n)?;
+            if !exponent.is_zero() {
+                factors.push(Factor {
+                    exponent,
+                    ..factor.clone()
+                });
+            }
+        }
+        Some(Term {
+            coefficient,
+            factors,
+        })
+    }
+
+    fn is_total(&self) -> bool {
+        self.factors.iter().all(Factor::is_total)
+    }
+
+    /// Builds the term with the absolute value of its coefficient, or with the sign kept.
+    fn to_expr(&self, keep_sign: bool) -> Expr {
+        let coefficient = if keep_sign {
+            self.coefficient
+        } else {
+            self.coefficient.abs()
+        };
+        let power = |factor: &Factor, exponent: Decimal| {
+            if exponent == Decimal::ONE {
+                factor.base.clone()
+            } else {
+                Expr::binary(BinaryOp::Power, factor.base.clone(), Expr::Number(exponent))
+            }
+        };
+        let mut numerator = self
+            .factors
+            .iter()
+            .filter(|f| f.exponent.is_sign_positive())
+            .map(|f| power(f, f.exponent.normalize()));
+        let mut expr = match numerator.next() {
+            None => Expr::Number(coefficient.normalize()),
+            Some(n) if coefficient == Decimal::ONE => n,
+            Some(n) if coefficient == Decimal::NEGATIVE_ONE => Expr::Negate(Box::new(n)),
+            Some(n) => Expr::binary(BinaryOp::Multiply, Expr::Number(coefficient.normalize()), n),
+        };
+        // Multiply in the remaining factors from the left, so that no parentheses are needed
+        for n in numerator {
+            expr = match expr {
+                Expr::Negate(e) => Expr::Negate(Box::new(Expr::binary(BinaryOp::Multiply, *e, n))),
+                e => Expr::binary(BinaryOp::Multiply, e, n),
+            };
+        }
+        for factor in self
+            .factors
+            .iter()
+            .filter(|f| f.exponent.is_sign_negative())
+        {
+            let denominator = power(factor, -factor.exponent.normalize());
+            expr = Expr::binary(BinaryOp::Divide, expr, denominator);
+        }
+        expr
+    }
+}
+
+/// A sum of terms in which no two terms are alike.
+#[derive(Debug, Clone)]
+struct Sum {
+    terms: Vec<Term>,
+}
+
+impl Sum {
+    fn constant(n: Decimal) -> Self {
+        let terms = if n.is_zero() {
+            Vec::new()
+        } else {
+            vec![Term {
+                coefficient: n,
+                factors: Vec::new(),
+            }]
+        };
+        Sum { terms }
+    }
+
+    /// A single expression that is not simplified further, raised to a power.
+    fn atom(base: &Expr, exponent: Decimal) -> Self {
+        Sum {
+            terms: vec![Term {
+                coefficient: Decimal::ONE,
+                factors: vec![Factor {
+                    key: base.to_string(),
+                    base: base.clone(),
+                    exponent,
+                }],
+            }],
+        }
+    }
+
+    /// Brings an expression into canonical form, keeping any part that cannot be as is.
+    fn from_expr(expr: &Expr) -> Self {
+        let sum = match expr {
+            Expr::Number(n) => Some(Sum::constant(*n)),
+            Expr::Negate(e) => Sum::from_expr(e).scale(Decimal::NEGATIVE_ONE),
+            Expr::Binary(BinaryOp::Add, a, b) => Sum::from_expr(a).add(&Sum::from_expr(b)),
+            Expr::Binary(BinaryOp::Subtract, a, b) => Sum::from_expr(b)
+                .scale(Decimal::NEGATIVE_ONE)
+                .and_then(|b| Sum::from_expr(a).add(&b)),
+            Expr::Binary(BinaryOp::Multiply, a, b) => {
+                Sum::from_expr(a).multiply(&Sum::from_expr(b))
+            }
+            Expr::Binary(BinaryOp::Divide, a, b) => {
+                let divisor = Sum::from_expr(b);
+                if divisor.terms.is_empty() {
+                    None
+                } else {
+                    let reciprocal = divisor
+                        .power(Decimal::NEGATIVE_ONE)
+                        .unwrap_or_else(|| Sum::atom(b, Decimal::NEGATIVE_ONE));
+                    Sum::from_expr(a).multiply(&reciprocal)
+                }
+            }
+            Expr::Binary(BinaryOp::Power, a, b) => match **b {
+                Expr::Number(n) => Sum::from_expr(a).power(n),
+                _ => None,
+            },
+            _ => None,
+        };
+        sum.unwrap_or_else(|| Sum::atom(expr, Decimal::ONE))
+    }
+
+    fn add(&self, other: &Sum) -> Option<Sum> {
+        let mut terms = self.terms.clone();
+        for term in &other.terms {
+            let key = term.key();
+            match terms.iter_mut().find(|t| t.key() == key) {
+                Some(existing) => {
+                    existing.coefficient = exact_add(existing.coefficient, term.coefficient)?;
+                    // Terms that may be undefined do not cancel, as in 1/x - 1/x
+                    if existing.coefficient.is_zero() && !existing.is_total() {
+                        return None;
+                    }
+                }
+                None => terms.push(term.clone()),
+            }
+        }
+        terms.retain(|t| !t.coefficient.is_zero());
+        Some(Sum { terms })
+    }
+
+    fn scale(&self, n: Decimal) -> Option<Sum> {
+        let terms = self
+            .terms
+            .iter()
+            .map(|t| {
+                Some(Term {
+                    coefficient: exact_mul(t.coefficient, n)?,
+                    factors: t.factors.clone(),
+                })
+            })
+            .collect::<Option<Vec<Term>>>()?;
+        let terms = terms
+            .into_iter()
+            .filter(|t| !t.coefficient.is_zero())
+            .collect();
+        Some(Sum { terms })
+    }
+
+    /// Multiplies two sums, distributing only a constant over a sum.
+    ///
+    /// Zero times a sum that may be undefined is not simplified, as in `0/x`.
+    fn multiply(&self, other: &Sum) -> Option<Sum> {
+        let product = match (&self.terms[..], &other.terms[..]) {
+            ([], _) | (_, []) => {
+                return (self.is_total() && other.is_total()).then_some(Sum { terms: Vec::new() });
+            }
+            ([a], [b]) => a.multiply(b).map(|t| Sum { terms: vec![t] }),
+            ([a], _) if a.factors.is_empty() => other.scale(a.coefficient),
+            (_, [b]) if b.factors.is_empty() => self.scale(b.coefficient),
+            _ => None,
+        };
+        Some(product.unwrap_or_else(|| {
+            // A product of sums is kept, with equal sums combined into a power
+            let a = Sum::atom(&self.to_expr(), Decimal::ONE);
+            let b = Sum::atom(&other.to_expr(), Decimal::ONE);
+            let product = a.terms[0].multiply(&b.terms[0]);
+            Sum {
+                terms: product.into_iter().collect(),
+            }
+        }))
+    }
+
+    fn power(&self, n: Decimal) -> Option<Sum> {
+        if n.is_zero() && !self.terms.is_empty() {
+            return self.is_total().then(|| Sum::constant(Decimal::ONE));
+        }
+        match &self.terms[..] {
+            [term] => term.power(n).map(|t| Sum { terms: vec![t] }),
+            [] if n.is_sign_positive() && !n.is_zero() => Some(self.clone()),
+            [] => None,
+            _ => Some(Sum::atom(&self.to_expr(), n)),
+        }
+    }
+
+    fn is_total(&self) -> bool {
+        self.terms.iter().all(Term::is_total)
+    }
+
+    /// Builds the sum, with any constant term last.
+    fn to_expr(&self) -> Expr {
+        let (constants, terms): (Vec<&Term>, Vec<&Term>) =
+            self.terms.iter().partition(|t| t.factors.is_empty());
+        let mut ordered = terms.into_iter().chain(constants);
+        let Some(first) = ordered.next() else {
+            return Expr::Number(Decimal::ZERO);
+        };
+        ordered.fold(first.to_expr(true), |sum, term| {
+            let op = if term.coefficient.is_sign_negative() {
+                BinaryOp::Subtract
+            } else {
+                BinaryOp::Add
+            };
+            Expr::binary(op, sum, term.to_expr(false))
+        })
+    }
+}
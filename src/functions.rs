@@ -0,0 +1,207 @@
+//! Functions module for the calculator application.
+//!
+//! This module lets a host application extend the calculator with its own functions. A
+//! [`Function`] describes one function, and a [`Registry`] holds the functions added by
+//! the host together with the built-in functions it removed, as well as any operators and
+//! variables the host added. The parser resolves every name against the registry first, so
+//! a registered function overrides a built-in one of the same name.
+
+use crate::evaluator;
+use crate::operators::{self, Operator};
+use crate::value::{Computed, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Names with a meaning of their own in an expression, which cannot name a function.
+const RESERVED: &[&str] = &["in"];
+
+/// A function that can be called from an expression.
+///
+/// ```
+/// use fermat::functions::Function;
+/// use fermat::value::Value;
+/// use rust_decimal_macros::dec;
+/// use std::error::Error;
+///
+/// struct Tax;
+///
+/// impl Function for Tax {
+///     fn name(&self) -> &str {
+///         "tax"
+///     }
+///     fn arity(&self) -> usize {
+///         1
+///     }
+///     fn doc(&self) -> &str {
+///         "Sales tax on an amount"
+///     }
+///     fn call(&self, args: &[Value]) -> Result<Value, Box<dyn Error>> {
+///         let amount = args[0].as_number().ok_or("tax expects a number")?;
+///         Ok(Value::Number(amount * dec!(0.2)))
+///     }
+/// }
+/// ```
+pub trait Function: Send + Sync {
+    /// The name the function is called by.
+    fn name(&self) -> &str;
+
+    /// The number of arguments the function takes.
+    fn arity(&self) -> usize;
+
+    /// A one-line description of what the function computes.
+    fn doc(&self) -> &str;
+
+    /// Calls the function with exactly [`Function::arity`] arguments.
+    ///
+    /// The result counts as exact unless an argument is an uncertain value.
+    fn call(&self, args: &[Value]) -> Result<Value, Box<dyn Error>>;
+}
+
+impl fmt::Debug for dyn Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.name(), self.arity())
+    }
+}
+
+/// The functions, operators and variables a host application added, and the built-in
+/// functions it removed.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    functions: BTreeMap<String, Arc<dyn Function>>,
+    removed: BTreeSet<String>,
+    operators: BTreeMap<String, Arc<dyn Operator>>,
+    variables: BTreeMap<String, Computed>,
+}
+
+impl Registry {
+    /// Creates a registry that leaves the built-in functions as they are.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a function, replacing any function of the same name, including a built-in one.
+    ///
+    /// Fails if the name is not an identifier or is reserved, such as `in`.
+    pub fn insert(&mut self, function: impl Function + 'static) -> Result<(), Box<dyn Error>> {
+        let name = function.name().to_string();
+        if !is_identifier(&name) || RESERVED.contains(&name.as_str()) {
+            return Err(format!("Invalid function name: {}", name).into());
+        }
+        self.removed.remove(&name);
+        self.functions.insert(name, Arc::new(function));
+        Ok(())
+    }
+
+    /// Removes a function, whether it was added or is built in.
+    pub fn remove(&mut self, name: &str) {
+        self.functions.remove(name);
+        self.removed.insert(name.to_string());
+    }
+
+    /// Returns the added function with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Function>> {
+        self.functions.get(name)
+    }
+
+    /// Returns whether a function was removed and not added again since.
+    pub fn is_removed(&self, name: &str) -> bool {
+        self.removed.contains(name)
+    }
+
+    /// Returns the added functions in order of name.
+    pub fn functions(&self) -> impl Iterator<Item = &Arc<dyn Function>> {
+        self.functions.values()
+    }
+
+    /// Adds an operator, replacing any added operator with the same symbol.
+    ///
+    /// Fails if the symbol is empty or contains whitespace, parentheses or commas, if it
+    /// starts with a digit, or if it is reserved, such as `in`.
+    pub fn insert_operator(
+        &mut self,
+        operator: impl Operator + 'static,
+    ) -> Result<(), Box<dyn Error>> {
+        let symbol = operator.symbol().to_string();
+        if !operators::is_valid_symbol(&symbol) {
+            return Err(format!("Invalid operator symbol: {}", symbol).into());
+        }
+        self.operators.insert(symbol, Arc::new(operator));
+        Ok(())
+    }
+
+    /// Removes an added operator.
+    pub fn remove_operator(&mut self, symbol: &str) {
+        self.operators.remove(symbol);
+    }
+
+    /// Returns the added operator the input starts with, preferring the longest symbol.
+    ///
+    /// A symbol that is an identifier, such as `mod`, only matches a whole identifier.
+    pub fn operator_at(&self, input: &str) -> Option<&Arc<dyn Operator>> {
+        self.operators
+            .iter()
+            .filter(|(symbol, _)| match input.strip_prefix(symbol.as_str()) {
+                Some(rest) => {
+                    !is_identifier(symbol)
+                        || !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+                }
+                None => false,
+            })
+            .max_by_key(|(symbol, _)| symbol.len())
+            .map(|(_, operator)| operator)
+    }
+
+    /// Returns the added operators in order of symbol.
+    pub fn operators(&self) -> impl Iterator<Item = &Arc<dyn Operator>> {
+        self.operators.values()
+    }
+
+    /// Defines a variable, replacing any earlier value. A variable whose value is not
+    /// exact makes every result that uses it approximate.
+    ///
+    /// Fails if the name is not an identifier, already names a function, or means
+    /// something else in an expression, such as `sqrt`, `today` or the unit `min`.
+    pub fn set_variable(&mut self, name: &str, value: Computed) -> Result<(), Box<dyn Error>> {
+        if evaluator::is_unit(name) {
+            return Err(format!(
+                "Invalid variable name: {} is reserved as a duration unit",
+                name
+            )
+            .into());
+        }
+        if !is_identifier(name) || evaluator::is_reserved(name) {
+            return Err(format!("Invalid variable name: {}", name).into());
+        }
+        if self.functions.contains_key(name) {
+            return Err(format!("Invalid variable name: {} is a function", name).into());
+        }
+        self.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Removes a variable.
+    pub fn remove_variable(&mut self, name: &str) {
+        self.variables.remove(name);
+    }
+
+    /// Returns the value of a variable, if it is defined.
+    pub fn variable(&self, name: &str) -> Option<&Computed> {
+        self.variables.get(name)
+    }
+
+    /// Returns the variables and their values in order of name.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Computed)> {
+        self.variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+}
+
+/// Returns whether a name can be read as a single identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
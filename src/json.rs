@@ -0,0 +1,560 @@
+//! JSON module for the calculator application.
+//!
+//! This module exchanges evaluation results with other programs as JSON. [`Json`] is a
+//! minimal JSON value with a parser and a compact serializer, and [`evaluation`] describes
+//! one evaluation in a documented, versioned schema:
+//!
+//! ```json
+//! {
+//!   "schema": 1,
+//!   "input": "1/3",
+//!   "normalized": "1 / 3",
+//!   "ok": true,
+//!   "exact": false,
+//!   "relation": "≈",
+//!   "value": {"type": "number", "exact": "0.333333333333333333333333333",
+//!             "formatted": "0.3333333333", "unit": null},
+//!   "error": null
+//! }
+//! ```
+//!
+//! `normalized` is the canonical form of the input, or `null` if it cannot be read. `value`
+//! is `null` on failure; otherwise `exact` holds every digit and `formatted` the displayed
+//! form, `unit` is the time unit or currency code if there is one, and values other than
+//! numbers add fields of their own: `lo` and `hi` for intervals, `mean` and `sigma` for
+//! uncertain values, `seconds` for durations, `amount` and `currency` for money, and
+//! `total`, `currency` and `parts` for allocations. Decimal numbers are written as strings
+//! so that no digit is lost. `error` is `null` on success; otherwise it has a `kind`
+//! (`syntax`, `limit` or `evaluation`), a `message`, and for syntax errors a list of
+//! `diagnostics`, each with a `message`, a `suggestion` or `null`, and a `span` with
+//! `start` and `end` character offsets into the input, counting from 0.
+//!
+//! Fields are only ever added within a schema version; removing or changing one raises
+//! [`SCHEMA_VERSION`].
+
+use crate::diagnostics::Diagnostic;
+use crate::engine::{EvalError, Evaluator};
+use crate::expr;
+use crate::value::{Computed, Value};
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// The version of the schema written by [`evaluation`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A JSON value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// A number, kept as written so that no precision is lost.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    /// An object, with its members in the order they were added.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a JSON text.
+    ///
+    /// ```
+    /// use fermat::json::Json;
+    ///
+    /// let json = Json::parse(r#"{"method": "eval", "id": 1}"#).unwrap();
+    /// assert_eq!(json.get("method").and_then(Json::as_str), Some("eval"));
+    /// ```
+    pub fn parse(text: &str) -> Result<Json, Box<dyn Error>> {
+        let mut parser = Parser {
+            text,
+            chars: text.char_indices().peekable(),
+            depth: 0,
+        };
+        let json = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            Some(&(at, c)) => parser.error(at, format!("unexpected '{}' after the value", c)),
+            None => Ok(json),
+        }
+    }
+
+    /// Creates an object from members.
+    pub fn object<'a>(members: impl IntoIterator<Item = (&'a str, Json)>) -> Self {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Returns a member of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns a member of an object for changing it.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Json> {
+        match self {
+            Json::Object(members) => members.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Sets a member of an object, replacing it if present or adding it at the end
+    /// otherwise. Does nothing if this is not an object.
+    pub fn insert(&mut self, key: &str, value: Json) {
+        if let Some(existing) = self.get_mut(key) {
+            *existing = value;
+        } else if let Json::Object(members) = self {
+            members.push((key.to_string(), value));
+        }
+    }
+
+    /// Returns the text of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a number that is a whole number in range.
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Deepest nesting of arrays and objects [`Json::parse`] accepts.
+const MAX_DEPTH: usize = 128;
+
+/// Reads JSON text, reporting errors at 0-based character offsets.
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error<T>(&self, at: usize, message: impl fmt::Display) -> Result<T, Box<dyn Error>> {
+        let offset = self.text[..at].chars().count();
+        Err(format!("{} at character {}", message, offset).into())
+    }
+
+    fn end(&self) -> usize {
+        self.text.len()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .next_if(|(_, c)| matches!(c, ' ' | '\t' | '\n' | '\r'))
+            .is_some()
+        {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Box<dyn Error>> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((at, c)) => self.error(at, format!("expected '{}', found '{}'", expected, c)),
+            None => self.error(self.end(), format!("expected '{}'", expected)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, Box<dyn Error>> {
+        self.skip_whitespace();
+        let Some(&(at, c)) = self.chars.peek() else {
+            return self.error(self.end(), "expected a value");
+        };
+        match c {
+            '{' | '[' => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    return self.error(at, "too deeply nested");
+                }
+                let json = if c == '{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                json
+            }
+            '"' => self.string().map(Json::String),
+            '-' | '0'..='9' => self.number(),
+            _ => {
+                for (word, json) in [
+                    ("true", Json::Bool(true)),
+                    ("false", Json::Bool(false)),
+                    ("null", Json::Null),
+                ] {
+                    if self.text[at..].starts_with(word) {
+                        for _ in 0..word.len() {
+                            self.chars.next();
+                        }
+                        return Ok(json);
+                    }
+                }
+                self.error(at, format!("unexpected '{}'", c))
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, Box<dyn Error>> {
+        self.chars.next();
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|&(_, c)| c == '}').is_some() {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some(&(_, '"')) => {}
+                Some(&(at, _)) => return self.error(at, "expected a member name"),
+                None => return self.error(self.end(), "expected a member name"),
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, '}')) => return Ok(Json::Object(members)),
+                Some((at, c)) => {
+                    return self.error(at, format!("expected ',' or '}}', found '{}'", c));
+                }
+                None => return self.error(self.end(), "unterminated object"),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, Box<dyn Error>> {
+        self.chars.next();
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|&(_, c)| c == ']').is_some() {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, ']')) => return Ok(Json::Array(items)),
+                Some((at, c)) => {
+                    return self.error(at, format!("expected ',' or ']', found '{}'", c));
+                }
+                None => return self.error(self.end(), "unterminated array"),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        let (start, _) = self.chars.next().expect("called at a quote");
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(s),
+                Some((at, '\\')) => {
+                    let c = match self.chars.next() {
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '/')) => '/',
+                        Some((_, 'b')) => '\u{8}',
+                        Some((_, 'f')) => '\u{c}',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'u')) => self.unicode_escape(at)?,
+                        _ => return self.error(at, "invalid escape"),
+                    };
+                    s.push(c);
+                }
+                Some((at, c)) if (c as u32) < 0x20 => {
+                    return self.error(at, "control character in string");
+                }
+                Some((_, c)) => s.push(c),
+                None => return self.error(start, "unterminated string"),
+            }
+        }
+    }
+
+    /// Reads the digits of a `\\u` escape, combining a surrogate pair into one character.
+    fn unicode_escape(&mut self, at: usize) -> Result<char, Box<dyn Error>> {
+        let Some(high) = self.hex() else {
+            return self.error(at, "invalid escape");
+        };
+        let code = if (0xD800..0xDC00).contains(&high) {
+            let low = match (self.chars.next(), self.chars.next()) {
+                (Some((_, '\\')), Some((_, 'u'))) => self.hex(),
+                _ => None,
+            };
+            match low {
+                Some(low) if (0xDC00..0xE000).contains(&low) => {
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                }
+                _ => return self.error(at, "unpaired surrogate in escape"),
+            }
+        } else {
+            high
+        };
+        match char::from_u32(code) {
+            Some(c) => Ok(c),
+            None => self.error(at, "unpaired surrogate in escape"),
+        }
+    }
+
+    /// Reads four hexadecimal digits.
+    fn hex(&mut self) -> Option<u32> {
+        let digits: String = (0..4)
+            .filter_map(|_| self.chars.next().map(|(_, c)| c))
+            .collect();
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        u32::from_str_radix(&digits, 16).ok()
+    }
+
+    /// Reads a number, keeping its text.
+    fn number(&mut self) -> Result<Json, Box<dyn Error>> {
+        let (start, _) = *self.chars.peek().expect("called at a digit or sign");
+        let mut text = String::new();
+        while let Some((_, c)) = self
+            .chars
+            .next_if(|(_, c)| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(c);
+        }
+        let integer = text.strip_prefix('-').unwrap_or(&text);
+        let (mantissa, exponent) = match integer.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+            None => (integer, None),
+        };
+        let (whole, fraction) = match mantissa.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (mantissa, None),
+        };
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let valid = digits(whole)
+            && (whole == "0" || !whole.starts_with('0'))
+            && fraction.is_none_or(digits)
+            && exponent.is_none_or(|e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)));
+        if !valid {
+            return self.error(start, format!("invalid number {}", text));
+        }
+        Ok(Json::Number(text))
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(n: u32) -> Self {
+        Json::Number(n.to_string())
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Number(n.to_string())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+/// Writes a string with quotes, escaping what JSON requires.
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Json {
+    /// Writes the value compactly, on a single line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => f.write_str(n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Describes the evaluation of `input` in the schema documented in this module.
+pub fn evaluation(
+    evaluator: &Evaluator,
+    input: &str,
+    result: &Result<Computed, EvalError>,
+) -> Json {
+    let normalized = expr::parse_with_functions(input, evaluator.functions())
+        .ok()
+        .map(|expr| expr.to_string());
+    let (value, exact, relation, error) = match result {
+        Ok(computed) => (
+            value(evaluator, &computed.value),
+            Json::Bool(computed.exact),
+            Json::from(computed.relation(&evaluator.rounding())),
+            Json::Null,
+        ),
+        Err(e) => (Json::Null, Json::Null, Json::Null, error(input, e)),
+    };
+    Json::object([
+        ("schema", Json::from(SCHEMA_VERSION)),
+        ("input", Json::from(input)),
+        ("normalized", Json::from(normalized)),
+        ("ok", Json::Bool(result.is_ok())),
+        ("exact", exact),
+        ("relation", relation),
+        ("value", value),
+        ("error", error),
+    ])
+}
+
+/// Describes a value, with its exact and formatted forms and its unit.
+fn value(evaluator: &Evaluator, value: &Value) -> Json {
+    let decimal = |n: &dyn ToString| Json::String(n.to_string());
+    let (kind, unit, fields): (&str, Option<String>, Vec<(&str, Json)>) = match value {
+        Value::Number(_) => ("number", None, vec![]),
+        Value::Interval { lo, hi } => (
+            "interval",
+            None,
+            vec![("lo", decimal(lo)), ("hi", decimal(hi))],
+        ),
+        Value::Uncertain { mean, sigma } => (
+            "uncertain",
+            None,
+            vec![("mean", decimal(mean)), ("sigma", decimal(sigma))],
+        ),
+        Value::Date(_) => ("date", None, vec![]),
+        Value::Time(_) => ("time", None, vec![]),
+        Value::DateTime(_) => ("datetime", None, vec![]),
+        Value::Duration { seconds, unit } => (
+            "duration",
+            unit.map(|unit| unit.to_string()),
+            vec![("seconds", decimal(seconds))],
+        ),
+        Value::Money { amount, currency } => (
+            "money",
+            Some(currency.to_string()),
+            vec![("amount", decimal(amount)), ("currency", decimal(currency))],
+        ),
+        Value::Split {
+            total,
+            currency,
+            parts,
+        } => (
+            "split",
+            Some(currency.to_string()),
+            vec![
+                ("total", decimal(total)),
+                ("currency", decimal(currency)),
+                ("parts", Json::from(*parts)),
+            ],
+        ),
+    };
+    let mut json = Json::object([
+        ("type", Json::from(kind)),
+        ("exact", Json::String(value.to_string())),
+        ("formatted", Json::String(evaluator.format(value))),
+        ("unit", Json::from(unit)),
+    ]);
+    for (key, field) in fields {
+        json.insert(key, field);
+    }
+    json
+}
+
+/// Describes an error, with the location of each syntax problem.
+fn error(input: &str, error: &EvalError) -> Json {
+    let problems = match error {
+        EvalError::Syntax(problems) => &problems[..],
+        _ => &[],
+    };
+    Json::object([
+        ("kind", Json::from(error.kind())),
+        ("message", Json::String(error.to_string())),
+        ("diagnostics", diagnostics(input, problems)),
+    ])
+}
+
+/// Describes syntax problems as in the `diagnostics` of an error.
+pub fn diagnostics(input: &str, problems: &[Diagnostic]) -> Json {
+    Json::Array(problems.iter().map(|p| diagnostic(input, p)).collect())
+}
+
+fn diagnostic(input: &str, problem: &Diagnostic) -> Json {
+    let offset = |bytes: usize| input.get(..bytes).map_or(0, |s| s.chars().count());
+    Json::object([
+        ("message", Json::from(problem.message.as_str())),
+        ("suggestion", Json::from(problem.suggestion.clone())),
+        (
+            "span",
+            Json::object([
+                ("start", Json::from(offset(problem.span.start))),
+                ("end", Json::from(offset(problem.span.end))),
+            ]),
+        ),
+    ])
+}
@@ -1,6 +1,52 @@
+pub mod annotate;
+pub mod config;
+pub mod console;
+pub mod datetime;
+pub mod decimal_math;
+pub mod diagnostics;
+pub mod engine;
 pub mod evaluator;
+pub mod export;
+pub mod expr;
+pub mod functions;
+pub mod json;
+pub mod limits;
+pub mod money;
+pub mod notation;
+pub mod number_theory;
+pub mod operators;
+pub mod rounding;
+pub mod server;
+pub mod session;
+pub mod value;
+pub mod worksheet;
+
+pub use engine::{EvalError, Evaluator, EvaluatorBuilder};
+pub use value::Value;
 
 #[cfg(test)]
 mod tests {
+    mod annotate_tests;
+    mod config_tests;
+    mod console_tests;
+    mod datetime_tests;
+    mod decimal_math_tests;
+    mod diagnostics_tests;
+    mod engine_tests;
     mod evaluator_tests;
+    mod export_tests;
+    mod expr_tests;
+    mod functions_tests;
+    mod json_tests;
+    mod limits_tests;
+    mod money_tests;
+    mod notation_tests;
+    mod number_theory_tests;
+    mod operators_tests;
+    mod rounding_tests;
+    #[cfg(unix)]
+    mod server_tests;
+    mod session_tests;
+    mod value_tests;
+    mod worksheet_tests;
 }
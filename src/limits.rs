@@ -0,0 +1,228 @@
+//! Limits module for the calculator application.
+//!
+//! This module defines the resources an evaluation may use: how long the input may be, how
+//! large any value may grow, how deeply parentheses may nest, how many operations may be
+//! performed and how long evaluation may take. Every limit that is exceeded is reported as
+//! its own [`LimitError`] variant, so callers can tell them apart by downcasting the error.
+
+use crate::value::Value;
+use rust_decimal::Decimal;
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Resource limits an expression is evaluated under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Longest accepted input, in characters.
+    pub max_input_length: usize,
+    /// Largest absolute value any number, intermediate result or final result may have.
+    ///
+    /// Durations are measured in seconds. Points in time are not measured, as their range
+    /// is fixed by the calendar.
+    pub max_magnitude: Decimal,
+    /// Deepest nesting of parentheses and function calls.
+    pub max_depth: usize,
+    /// Most evaluation steps: operands read, operators applied and functions called.
+    pub max_steps: usize,
+    /// Longest time an evaluation may take.
+    ///
+    /// Checked between steps, and within factorization and the search for the next prime,
+    /// which may take long on their own. Powers, roots and series take a bounded number of
+    /// iterations, so checking between steps suffices for them.
+    pub max_time: Duration,
+}
+
+impl Limits {
+    /// Returns limits that never trigger.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_input_length: usize::MAX,
+            max_magnitude: Decimal::MAX,
+            max_depth: usize::MAX,
+            max_steps: usize::MAX,
+            max_time: Duration::MAX,
+        }
+    }
+
+    /// Checks that an input is no longer than the maximum input length.
+    pub fn check_input(&self, input: &str) -> Result<(), LimitError> {
+        let length = input.chars().count();
+        if length > self.max_input_length {
+            return Err(LimitError::InputTooLong {
+                length,
+                limit: self.max_input_length,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that a value lies within the maximum magnitude.
+    pub fn check_magnitude(&self, value: &Value) -> Result<(), LimitError> {
+        if matches!(value, Value::Date(_) | Value::Time(_) | Value::DateTime(_)) {
+            return Ok(());
+        }
+        if value.max_abs() > self.max_magnitude {
+            return Err(LimitError::MagnitudeExceeded {
+                limit: self.max_magnitude,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that a nesting depth is within the maximum depth.
+    pub fn check_depth(&self, depth: usize) -> Result<(), LimitError> {
+        if depth > self.max_depth {
+            return Err(LimitError::TooDeep {
+                limit: self.max_depth,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for Limits {
+    /// Limits generous enough for any expression typed by hand.
+    fn default() -> Self {
+        Limits {
+            max_input_length: 1_000,
+            max_magnitude: Decimal::MAX,
+            max_depth: 64,
+            max_steps: 10_000,
+            max_time: Duration::from_secs(1),
+        }
+    }
+}
+
+/// A limit exceeded during evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    /// The input has more characters than allowed.
+    InputTooLong { length: usize, limit: usize },
+    /// A number or result is larger in magnitude than allowed.
+    MagnitudeExceeded { limit: Decimal },
+    /// Parentheses or function calls are nested too deeply.
+    TooDeep { limit: usize },
+    /// Evaluation needed more steps than allowed.
+    TooManySteps { limit: usize },
+    /// Evaluation took longer than allowed.
+    TimedOut { limit: Duration },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::InputTooLong { length, limit } => write!(
+                f,
+                "Input too long: {} characters, limit is {}",
+                length, limit
+            ),
+            LimitError::MagnitudeExceeded { limit } => {
+                write!(f, "Number exceeds the magnitude limit of {}", limit)
+            }
+            LimitError::TooDeep { limit } => {
+                write!(f, "Expression nested too deeply: limit is {} levels", limit)
+            }
+            LimitError::TooManySteps { limit } => {
+                write!(
+                    f,
+                    "Expression too long to evaluate: limit is {} steps",
+                    limit
+                )
+            }
+            LimitError::TimedOut { limit } => {
+                write!(f, "Evaluation timed out after {:?}", limit)
+            }
+        }
+    }
+}
+
+impl Error for LimitError {}
+
+/// A moment after which a long computation gives up, reporting the time limit it enforces.
+///
+/// ```
+/// use fermat::limits::Deadline;
+/// use std::time::Duration;
+///
+/// assert!(Deadline::never().check().is_ok());
+/// assert!(Deadline::after(Duration::ZERO).check().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    /// When the computation must end, or `None` if it may run forever.
+    at: Option<Instant>,
+    limit: Duration,
+}
+
+impl Deadline {
+    /// Returns a deadline `limit` from now.
+    pub fn after(limit: Duration) -> Self {
+        Deadline::from(Instant::now(), limit)
+    }
+
+    /// Returns a deadline that never passes.
+    pub fn never() -> Self {
+        Deadline {
+            at: None,
+            limit: Duration::MAX,
+        }
+    }
+
+    fn from(started: Instant, limit: Duration) -> Self {
+        Deadline {
+            at: started.checked_add(limit),
+            limit,
+        }
+    }
+
+    /// Fails once the deadline has passed.
+    pub fn check(&self) -> Result<(), LimitError> {
+        match self.at {
+            Some(at) if Instant::now() > at => Err(LimitError::TimedOut { limit: self.limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Counts the steps and time used by one evaluation.
+pub(crate) struct Meter {
+    limits: Limits,
+    steps: Cell<usize>,
+    started: Instant,
+}
+
+impl Meter {
+    /// Starts measuring an evaluation under the given limits.
+    pub(crate) fn start(limits: Limits) -> Self {
+        Meter {
+            limits,
+            steps: Cell::new(0),
+            started: Instant::now(),
+        }
+    }
+
+    /// Records one evaluation step, failing once the step or time limit is exceeded.
+    pub(crate) fn step(&self) -> Result<(), LimitError> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if steps > self.limits.max_steps {
+            return Err(LimitError::TooManySteps {
+                limit: self.limits.max_steps,
+            });
+        }
+        self.deadline().check()
+    }
+
+    /// Returns the moment the evaluation runs out of time, for computations that take
+    /// many iterations within a single step.
+    pub(crate) fn deadline(&self) -> Deadline {
+        Deadline::from(self.started, self.limits.max_time)
+    }
+
+    /// Checks that a value lies within the maximum magnitude.
+    pub(crate) fn check(&self, value: &Value) -> Result<(), LimitError> {
+        self.limits.check_magnitude(value)
+    }
+}
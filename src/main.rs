@@ -2,25 +2,56 @@
 //!
 //! This module initializes the terminal UI, handles user input in real-time, and updates
 //! the display with the current expression and its evaluated result. The evaluation is
-//! performed automatically as the user types.
+//! performed automatically as the user types. A worksheet of many lines, each with its
+//! own result, can be opened in place of the single input.
 
 mod button_grid;
-mod evaluator;
+mod cli;
+mod repl;
+mod settings;
+mod worksheet_view;
 
 use crossterm::{
     ExecutableCommand,
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     terminal::{
         Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
         enable_raw_mode,
     },
 };
+use fermat::Evaluator;
+use fermat::config::{self, Config};
+use fermat::diagnostics::{self, Diagnostic};
+use fermat::export;
+use fermat::expr;
+use fermat::limits::Limits;
+use fermat::money::{Currency, RateTable};
+use fermat::rounding::RoundingContext;
+use fermat::value::Computed;
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Paragraph},
 };
-use rust_decimal::prelude::*;
-use std::io; // Removed unused stdout
+use settings::{Keys, Settings, Theme};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use worksheet_view::WorksheetView;
+
+/// Environment variable naming the exchange rate file used for currency conversions.
+pub(crate) const RATES_VARIABLE: &str = "FERMAT_RATES";
+
+/// File in the working directory the `l` key writes the expression to as LaTeX.
+const LATEX_FILE: &str = "fermat.tex";
+
+/// File in the working directory the `m` key writes the expression to as MathML.
+const MATHML_FILE: &str = "fermat.mml";
+
+/// What the TUI shows when it starts.
+pub(crate) enum Start {
+    Calculator,
+    /// The worksheet, opened from a file if there is one.
+    Worksheet(Option<PathBuf>),
+}
 
 /// A simple calculator structure that holds the current input expression and its evaluated result.
 struct Calculator {
@@ -28,28 +59,116 @@ struct Calculator {
     input: String,
     /// The result of evaluating the input expression. None if the input is empty or invalid.
     result: Option<String>,
+    /// Syntax problems in the input, shown with carets instead of a result.
+    diagnostics: Vec<Diagnostic>,
+    /// Whether the result is a preview of an unfinished expression, shown greyed out.
+    preview: bool,
+    /// The input as the calculator reads it, shown when it differs from what was typed.
+    normalized: Option<String>,
+    /// The outcome of the last export, shown below the result until the next key press.
+    status: Option<String>,
     button_grid: button_grid::ButtonGrid,
-    max_input_length: usize, // Add maximum input length
+    /// Evaluates the input under the display precision, money mode, exchange rates and
+    /// resource limits.
+    evaluator: Evaluator,
+    theme: Theme,
+    keys: Keys,
+    /// The worksheet, kept while the calculator is shown so that it can be returned to.
+    worksheet: WorksheetView,
+    /// Whether the worksheet is shown instead of the calculator.
+    show_worksheet: bool,
 }
 
 impl Calculator {
-    /// Creates a new Calculator instance with empty input and no result.
-    fn new() -> Self {
+    /// Creates a new Calculator instance with empty input and no result, starting from the
+    /// settings of the configuration file.
+    fn new(config: &Config, settings: &Settings) -> Self {
         Self {
             input: String::new(),
             result: None,
-            button_grid: button_grid::ButtonGrid::new(),
-            max_input_length: 50, // Reasonable limit for input length
+            diagnostics: Vec::new(),
+            preview: false,
+            normalized: None,
+            status: None,
+            button_grid: button_grid::ButtonGrid::new(&settings.grid),
+            // Keep the input within the width of the input field unless configured otherwise
+            evaluator: config
+                .evaluator_builder(Limits {
+                    max_input_length: 50,
+                    ..Limits::default()
+                })
+                .build(),
+            theme: settings.theme,
+            keys: settings.keys,
+            worksheet: WorksheetView::new(config.evaluator_builder(Limits::default()).build()),
+            show_worksheet: false,
+        }
+    }
+
+    /// Loads the exchange rate table named by the `FERMAT_RATES` environment variable.
+    ///
+    /// A file that cannot be read or parsed is reported in the result field.
+    fn load_rates(&mut self) {
+        if let Some(path) = std::env::var_os(RATES_VARIABLE) {
+            match RateTable::load(Path::new(&path)) {
+                Ok(rates) => {
+                    let sheet = &mut self.worksheet.sheet;
+                    sheet.evaluator_mut().set_rates(Some(rates.clone()));
+                    sheet.recompute();
+                    self.evaluator.set_rates(Some(rates));
+                }
+                Err(e) => self.result = Some(format!("Error: {}", e)),
+            }
         }
     }
 
     /// Handles a key press event and automatically re-evaluates the expression.
     ///
     /// Accepts digits, operators, and special characters. Backspace removes the last character.
-    /// After processing the key, it updates the evaluated result automatically.
+    /// By default `<` and `>` change the display precision, `#` switches between decimal
+    /// places and significant digits, `~` cycles the rounding strategy, `$` toggles money
+    /// mode, `l` and `m` export the expression as LaTeX and MathML, and `w` opens the
+    /// worksheet; the configuration file may move these to other keys. After processing the
+    /// key, it updates the evaluated result automatically.
     fn handle_key(&mut self, key: KeyCode) {
+        let mut rounding = self.evaluator.rounding();
+        let precision = rounding.precision;
+        let keys = self.keys;
+        self.status = None;
         match key {
-            KeyCode::Char('q') if self.input.is_empty() => {
+            KeyCode::Char(c) if c == keys.quit && self.input.is_empty() => {
+                return;
+            }
+            KeyCode::Char(c) if c == keys.fewer_digits => {
+                rounding.precision = precision.with_digits(precision.digits().saturating_sub(1));
+                self.evaluator.set_rounding(rounding);
+            }
+            KeyCode::Char(c) if c == keys.more_digits => {
+                rounding.precision = precision.with_digits(precision.digits() + 1);
+                self.evaluator.set_rounding(rounding);
+            }
+            KeyCode::Char(c) if c == keys.toggle_precision => {
+                rounding.precision = precision.toggle_kind();
+                self.evaluator.set_rounding(rounding);
+            }
+            KeyCode::Char(c) if c == keys.cycle_rounding => {
+                rounding.rounding = rounding.rounding.next();
+                self.evaluator.set_rounding(rounding);
+            }
+            KeyCode::Char(c) if c == keys.toggle_money => {
+                self.evaluator.set_money(match self.evaluator.money() {
+                    Some(_) => None,
+                    None => Currency::new("USD"),
+                });
+            }
+            KeyCode::Char(c) if c == keys.export_latex => {
+                self.export(LATEX_FILE, export::latex, export::latex_equation)
+            }
+            KeyCode::Char(c) if c == keys.export_mathml => {
+                self.export(MATHML_FILE, export::mathml, export::mathml_equation)
+            }
+            KeyCode::Char(c) if c == keys.worksheet => {
+                self.open_worksheet();
                 return;
             }
             _ => {
@@ -72,21 +191,16 @@ impl Calculator {
                         }
                         _ => {
                             // Check if adding the text would exceed the maximum length
-                            if self.input.len() + text.len() <= self.max_input_length {
-                                self.input.push_str(&text);
-                            } else {
-                                self.result = Some("Error: Input too long".to_string());
+                            let extended = format!("{}{}", self.input, text);
+                            if let Err(e) = self.evaluator.limits().check_input(&extended) {
+                                self.result = Some(format!("Error: {}", e));
                                 return;
                             }
+                            self.input = extended;
                         }
                     }
-                } else {
-                    match key {
-                        KeyCode::Backspace => {
-                            self.input.pop();
-                        }
-                        _ => {}
-                    }
+                } else if key == KeyCode::Backspace {
+                    self.input.pop();
                 }
             }
         }
@@ -95,77 +209,180 @@ impl Calculator {
 
     /// Evaluates the current input expression and updates the result field.
     ///
-    /// If the input is empty, the result is set to None. Otherwise, the input is tokenized and evaluated.
-    /// Any error during evaluation will be captured and stored as the result string.
+    /// If the input is empty, the result is set to None. An unfinished expression, such as
+    /// one with a trailing operator or unclosed parentheses, is completed and its result
+    /// shown as a preview. Otherwise any syntax problems are all collected for display, and
+    /// if there are none the input is tokenized and evaluated. Any error during evaluation
+    /// will be captured and stored as the result string.
     fn evaluate(&mut self) {
+        self.diagnostics = self.evaluator.diagnose(&self.input);
+        self.preview = false;
+        self.normalized = None;
         if self.input.is_empty() {
             self.result = None;
             return;
         }
-
-        // Parse individual numbers and check if they're within reasonable bounds
-        let mut current_number = String::new();
-        for c in self.input.chars() {
-            if c.is_ascii_digit() || c == '.' {
-                current_number.push(c);
-            } else if !current_number.is_empty() {
-                if let Ok(num) = current_number.parse::<f64>() {
-                    if !num.is_finite() || num.abs() > 1e100 {
-                        self.result = Some("Error: Number too large".to_string());
-                        return;
-                    }
-                }
-                current_number.clear();
+        if !self.diagnostics.is_empty() {
+            self.result = None;
+            if let Some(completed) = self.evaluator.complete(&self.input) {
+                // Still being typed: show the best partial result, or nothing if it fails
+                self.diagnostics.clear();
+                self.preview = true;
+                self.result = self.compute(&completed).ok();
             }
+            return;
         }
-        // Check the last number if exists
-        if !current_number.is_empty() {
-            if let Ok(num) = current_number.parse::<f64>() {
-                if !num.is_finite() || num.abs() > 1e100 {
-                    self.result = Some("Error: Number too large".to_string());
-                    return;
-                }
+        self.normalized = expr::parse_with_functions(&self.input, self.evaluator.functions())
+            .ok()
+            .map(|expr| expr.to_string())
+            .filter(|normalized| !same_ignoring_spaces(normalized, &self.input));
+        self.result = Some(self.compute(&self.input).unwrap_or_else(|e| e));
+    }
+
+    /// Shows the worksheet, evaluated with the display precision, rounding and money mode
+    /// chosen in the calculator.
+    fn open_worksheet(&mut self) {
+        let sheet = &mut self.worksheet.sheet;
+        sheet
+            .evaluator_mut()
+            .set_rounding(self.evaluator.rounding());
+        sheet.evaluator_mut().set_money(self.evaluator.money());
+        sheet.recompute();
+        self.show_worksheet = true;
+    }
+
+    /// Writes the expression to a file, followed by its result if it can be evaluated.
+    ///
+    /// `render` renders the expression alone and `equation` the expression with its result.
+    fn export(
+        &mut self,
+        path: &str,
+        render: fn(&expr::Expr) -> String,
+        equation: fn(&expr::Expr, &Computed, &RoundingContext) -> String,
+    ) {
+        let expr = match expr::parse_with_functions(&self.input, self.evaluator.functions()) {
+            Ok(expr) => expr,
+            Err(e) => {
+                self.status = Some(format!("Nothing to export: {}", e));
+                return;
             }
-        }
+        };
+        let text = match self.evaluator.compute(&self.input) {
+            Ok(computed) => equation(&expr, &computed, &self.evaluator.rounding()),
+            Err(_) => render(&expr),
+        };
+        self.status = Some(match std::fs::write(path, text + "\n") {
+            Ok(()) => format!("Wrote {}", path),
+            Err(e) => format!("Error: cannot write {}: {}", path, e),
+        });
+    }
 
-        match evaluator::tokenize(&self.input) {
-            Ok(tokens) => {
-                match evaluator::evaluate(&tokens) {
-                    Ok(result) => {
-                        // Check if the result is too large
-                        if result > Decimal::from_str("1e50").unwrap_or(Decimal::MAX)
-                            || result < Decimal::from_str("-1e50").unwrap_or(Decimal::MIN)
-                        {
-                            self.result = Some("Error: Result too large".to_string());
-                            return;
-                        }
-                        // Format the result to prevent excessive decimal places
-                        let result_str = format!("{:.10}", result);
-                        // Remove trailing zeros after decimal point
-                        let result_str = if result_str.contains('.') {
-                            result_str
-                                .trim_end_matches('0')
-                                .trim_end_matches('.')
-                                .to_string()
-                        } else {
-                            result_str
-                        };
-                        self.result = Some(result_str);
-                    }
-                    Err(e) => {
-                        self.result = Some(format!("Error: {}", e));
-                    }
-                }
+    /// Evaluates an expression, returning the formatted result or an error message.
+    ///
+    /// The result follows `=` when it is shown exactly and `≈` when it is approximate.
+    fn compute(&self, input: &str) -> Result<String, String> {
+        let computed = self
+            .evaluator
+            .compute(input)
+            .map_err(|e| format!("Error: {}", e))?;
+        Ok(self.evaluator.format_computed(&computed))
+    }
+
+    /// Returns the lines shown in the result pane.
+    ///
+    /// Syntax problems are shown as the input with carets under each problem, followed by
+    /// one line per problem; otherwise the pane holds the result, preceded by the input as
+    /// it was read if that shows grouping the typed input did not, as in `-2^2`.
+    fn result_lines(&self) -> Vec<Line<'_>> {
+        if self.diagnostics.is_empty() {
+            let result = self.result.as_deref().unwrap_or("");
+            let grey = Style::default().fg(self.theme.muted);
+            if self.preview {
+                return vec![Line::styled(result, grey)];
             }
-            Err(e) => {
-                self.result = Some(format!("Error: {}", e));
+            let mut lines = Vec::new();
+            if let Some(normalized) = &self.normalized {
+                lines.push(Line::styled(format!("Read as {}", normalized), grey));
             }
+            lines.push(Line::from(result));
+            if let Some(status) = &self.status {
+                lines.push(Line::styled(status.as_str(), grey));
+            }
+            return lines;
         }
+        let error = Style::default().fg(self.theme.error);
+        let mut lines = vec![
+            Line::from(self.input.as_str()),
+            Line::styled(
+                diagnostics::caret_line(&self.input, &self.diagnostics),
+                error,
+            ),
+        ];
+        lines.extend(
+            self.diagnostics
+                .iter()
+                .map(|d| Line::styled(format!("error: {}", d), error)),
+        );
+        lines
     }
 }
 
-/// The main function that sets up the terminal, runs the TUI loop and cleans up on exit.
+/// Returns whether two expressions are written the same apart from whitespace.
+fn same_ignoring_spaces(a: &str, b: &str) -> bool {
+    a.chars()
+        .filter(|c| !c.is_whitespace())
+        .eq(b.chars().filter(|c| !c.is_whitespace()))
+}
+
+/// The main function: evaluates the arguments if there are any, reads stdin if it or
+/// stdout is not a terminal, or runs the TUI.
 fn main() -> io::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() && !(io::stdin().is_terminal() && io::stdout().is_terminal()) {
+        args.push("-".to_string());
+    }
+    let (config, settings) = match load_config() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args, &config, &settings));
+    }
+    run_tui(&config, &settings, Start::Calculator)
+}
+
+/// Reads the configuration file, checking every setting, including those only the TUI
+/// uses, so that mistakes are reported however the program is run.
+fn load_config() -> Result<(Config, Settings), Box<dyn std::error::Error>> {
+    let Some(path) = config::default_path() else {
+        return Ok((
+            Config::default(),
+            Settings::from_config(&Config::default())?,
+        ));
+    };
+    let config = Config::load(&path)?;
+    let settings =
+        Settings::from_config(&config).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok((config, settings))
+}
+
+/// Sets up the terminal, runs the TUI loop and cleans up on exit.
+///
+/// A worksheet file is read before the terminal is set up, so that an error reading it is
+/// printed as usual.
+pub(crate) fn run_tui(config: &Config, settings: &Settings, start: Start) -> io::Result<()> {
+    let mut calculator = Calculator::new(config, settings);
+    calculator.load_rates();
+    if let Start::Worksheet(path) = start {
+        if let Some(path) = path {
+            calculator.worksheet.open(&path)?;
+        }
+        calculator.open_worksheet();
+    }
+
     // Setup terminal in raw mode and switch to alternate screen
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -174,11 +391,15 @@ fn main() -> io::Result<()> {
     stdout.execute(Clear(ClearType::All))?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
 
-    let mut calculator = Calculator::new();
-
     loop {
         terminal.draw(|frame| {
             let area = frame.area();
+            if calculator.show_worksheet {
+                let area = area.inner(Margin::new(2, 2));
+                calculator.worksheet.render(frame, area, &calculator.theme);
+                return;
+            }
+            let result_lines = calculator.result_lines();
 
             // Create the layout with designated areas for input and result
             let chunks = Layout::default()
@@ -186,9 +407,9 @@ fn main() -> io::Result<()> {
                 .margin(2)
                 .constraints(
                     [
-                        Constraint::Length(3), // Input area
-                        Constraint::Length(3), // Result area
-                        Constraint::Min(20),   // Button grid area
+                        Constraint::Length(3),                             // Input area
+                        Constraint::Length(result_lines.len() as u16 + 2), // Result area
+                        Constraint::Min(calculator.button_grid.height()),  // Button grid area
                     ]
                     .as_ref(),
                 )
@@ -197,32 +418,48 @@ fn main() -> io::Result<()> {
             // Render input field with character count
             let input_block = Block::default().borders(Borders::ALL).title(format!(
                 "Input ({}/{})",
-                calculator.input.len(),
-                calculator.max_input_length
+                calculator.input.chars().count(),
+                calculator.evaluator.limits().max_input_length
             ));
             let input = Paragraph::new(calculator.input.as_str()).block(input_block);
             frame.render_widget(input, chunks[0]);
 
             // Render result field
-            let result_block = Block::default().borders(Borders::ALL).title("Result");
-            let result =
-                Paragraph::new(calculator.result.as_deref().unwrap_or("")).block(result_block);
+            let rounding = calculator.evaluator.rounding();
+            let mode = match calculator.evaluator.money() {
+                Some(currency) => format!(", {}", currency),
+                None => String::new(),
+            };
+            let result_block = Block::default().borders(Borders::ALL).title(format!(
+                "Result ({}, {}{})",
+                rounding.precision, rounding.rounding, mode
+            ));
+            let result = Paragraph::new(result_lines).block(result_block);
             frame.render_widget(result, chunks[1]);
 
             // Render button grid
-            calculator.button_grid.render(frame, chunks[2]);
+            calculator
+                .button_grid
+                .render(frame, chunks[2], &calculator.theme);
         })?;
 
         match event::read()? {
-            Event::Key(key) => {
-                if key.kind == event::KeyEventKind::Press {
-                    if key.code == KeyCode::Char('q') && calculator.input.is_empty() {
-                        break;
-                    }
-                    calculator.handle_key(key.code);
+            Event::Key(key)
+                if key.kind == event::KeyEventKind::Press && calculator.show_worksheet =>
+            {
+                if key.code == KeyCode::Esc {
+                    calculator.show_worksheet = false;
+                } else {
+                    calculator.worksheet.handle_key(key);
+                }
+            }
+            Event::Key(key) if key.kind == event::KeyEventKind::Press => {
+                if key.code == KeyCode::Char(calculator.keys.quit) && calculator.input.is_empty() {
+                    break;
                 }
+                calculator.handle_key(key.code);
             }
-            Event::Mouse(mouse) => {
+            Event::Mouse(mouse) if !calculator.show_worksheet => {
                 if let Some(text) = calculator
                     .button_grid
                     .handle_mouse_event(mouse, terminal.get_frame().area())
@@ -242,12 +479,12 @@ fn main() -> io::Result<()> {
                         }
                         _ => {
                             // Check if adding the text would exceed the maximum length
-                            if calculator.input.len() + text.len() <= calculator.max_input_length {
-                                calculator.input.push_str(&text);
-                            } else {
-                                calculator.result = Some("Error: Input too long".to_string());
+                            let extended = format!("{}{}", calculator.input, text);
+                            if let Err(e) = calculator.evaluator.limits().check_input(&extended) {
+                                calculator.result = Some(format!("Error: {}", e));
                                 continue;
                             }
+                            calculator.input = extended;
                         }
                     }
                     calculator.evaluate();
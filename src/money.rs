@@ -0,0 +1,251 @@
+//! Money module for the calculator application.
+//!
+//! This module defines currencies and the rules for money amounts: every amount is kept at
+//! its currency's fixed number of decimal places using banker's rounding, a division into
+//! equal shares distributes the remainder minor units instead of losing them, and amounts
+//! are printed with a currency symbol and thousands separators. Conversions use a rate
+//! table read from a local file; rates are never fetched over the network.
+
+use crate::rounding::{Rounding, round_places};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Symbols and decimal places of well-known currencies: `(code, symbol, scale)`.
+///
+/// Any other three-letter code is accepted with two decimal places and no symbol.
+const KNOWN_CURRENCIES: &[(&str, Option<&str>, u32)] = &[
+    ("USD", Some("$"), 2),
+    ("EUR", Some("€"), 2),
+    ("GBP", Some("£"), 2),
+    ("JPY", Some("¥"), 0),
+    ("INR", Some("₹"), 2),
+    ("KRW", Some("₩"), 0),
+    ("ISK", None, 0),
+    ("BHD", None, 3),
+    ("KWD", None, 3),
+    ("OMR", None, 3),
+];
+
+/// A currency, identified by its three-letter ISO 4217 code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    /// Creates a currency from a code of three uppercase ASCII letters.
+    pub fn new(code: &str) -> Option<Self> {
+        let bytes: [u8; 3] = code.as_bytes().try_into().ok()?;
+        if bytes.iter().all(u8::is_ascii_uppercase) {
+            Some(Currency(bytes))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the currency whose symbol is `symbol`, such as `$` or `€`.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        KNOWN_CURRENCIES
+            .iter()
+            .find(|(_, s, _)| *s == Some(symbol))
+            .and_then(|(code, _, _)| Currency::new(code))
+    }
+
+    /// Returns the three-letter code.
+    pub fn code(&self) -> &str {
+        // Only ever constructed from ASCII letters
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+
+    /// Returns the symbol printed before amounts, if the currency has one.
+    pub fn symbol(&self) -> Option<&'static str> {
+        self.known().and_then(|(_, symbol, _)| *symbol)
+    }
+
+    /// Returns the number of decimal places amounts are kept at.
+    pub fn scale(&self) -> u32 {
+        self.known().map_or(2, |(_, _, scale)| *scale)
+    }
+
+    fn known(&self) -> Option<&'static (&'static str, Option<&'static str>, u32)> {
+        KNOWN_CURRENCIES
+            .iter()
+            .find(|(code, _, _)| *code == self.code())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Rounds an amount to the currency's scale using banker's rounding.
+pub fn round(amount: Decimal, currency: Currency) -> Result<Decimal, Box<dyn Error>> {
+    round_places(amount, currency.scale() as i64, Rounding::HalfEven)
+        .ok_or_else(|| "Amount too large".into())
+}
+
+/// Splits an amount into `parts` shares that differ by at most one minor unit.
+///
+/// Returns the distinct share amounts with how many shares receive each, largest share
+/// (in absolute value) first. The shares always add up to the rounded amount.
+pub fn allocate(
+    amount: Decimal,
+    parts: u32,
+    currency: Currency,
+) -> Result<Vec<(Decimal, u32)>, Box<dyn Error>> {
+    if parts == 0 {
+        return Err("Cannot allocate into zero parts".into());
+    }
+    let unit = Decimal::new(1, currency.scale());
+    let units = round(amount, currency)?
+        .checked_div(unit)
+        .and_then(|units| units.to_i128())
+        .ok_or("Amount too large")?;
+    let parts_i = parts as i128;
+    let (base, extra) = (units / parts_i, units % parts_i);
+
+    let share = |units: i128| {
+        Decimal::from_i128(units)
+            .map(|u| u * unit)
+            .ok_or("Amount too large")
+    };
+    let mut groups = Vec::with_capacity(2);
+    if extra != 0 {
+        groups.push((share(base + extra.signum())?, extra.unsigned_abs() as u32));
+    }
+    if extra.unsigned_abs() < parts as u128 {
+        groups.push((share(base)?, parts - extra.unsigned_abs() as u32));
+    }
+    Ok(groups)
+}
+
+/// Formats an amount with its currency symbol and thousands separators, as in `$1,234.50`.
+///
+/// Currencies without a symbol are prefixed with their code, as in `CHF 1,234.50`.
+pub fn format(amount: Decimal, currency: Currency) -> String {
+    let scale = currency.scale();
+    let rounded = round(amount, currency).unwrap_or(amount);
+    let digits = format!("{:.*}", scale as usize, rounded.abs());
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits.as_str(), None),
+    };
+
+    let mut grouped = String::with_capacity(whole.len() + whole.len() / 3);
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+
+    let sign = if rounded < Decimal::ZERO { "-" } else { "" };
+    match currency.symbol() {
+        Some(symbol) => format!("{}{}{}", sign, symbol, grouped),
+        None => format!("{}{} {}", sign, currency, grouped),
+    }
+}
+
+/// Exchange rates between currencies, read from a local file.
+///
+/// Each line of the file holds one rate as `FROM TO RATE`, meaning one unit of `FROM` is
+/// worth `RATE` units of `TO`. Blank lines and lines starting with `#` are ignored.
+/// Rates are also used in reverse, and through one intermediate currency when no direct
+/// rate is listed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateTable {
+    rates: HashMap<(Currency, Currency), Decimal>,
+}
+
+impl RateTable {
+    /// Creates an empty rate table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a rate table from a file.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read rate table {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Parses a rate table from the contents of a rate file.
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut table = RateTable::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [from, to, rate] = fields[..] else {
+                return Err(format!("line {}: expected `FROM TO RATE`", index + 1).into());
+            };
+            let currency = |code: &str| {
+                Currency::new(code)
+                    .ok_or_else(|| format!("line {}: invalid currency code '{}'", index + 1, code))
+            };
+            let rate = Decimal::from_str(rate)
+                .ok()
+                .filter(|r| *r > Decimal::ZERO)
+                .ok_or_else(|| format!("line {}: invalid rate '{}'", index + 1, rate))?;
+            table.insert(currency(from)?, currency(to)?, rate);
+        }
+        Ok(table)
+    }
+
+    /// Sets the rate for converting one unit of `from` into `to`.
+    pub fn insert(&mut self, from: Currency, to: Currency, rate: Decimal) {
+        self.rates.insert((from, to), rate);
+    }
+
+    /// Returns the rate for converting `from` into `to`, if it can be derived.
+    pub fn rate(&self, from: Currency, to: Currency) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        if let Some(rate) = self.direct(from, to) {
+            return Some(rate);
+        }
+        // Go through an intermediate currency, such as EUR -> USD -> JPY, trying them
+        // in a fixed order so that the result does not depend on hashing
+        let mut via: Vec<Currency> = self.rates.keys().flat_map(|&(a, b)| [a, b]).collect();
+        via.sort_by(|a, b| a.code().cmp(b.code()));
+        via.dedup();
+        via.into_iter()
+            .find_map(|via| self.direct(from, via)?.checked_mul(self.direct(via, to)?))
+    }
+
+    fn direct(&self, from: Currency, to: Currency) -> Option<Decimal> {
+        match self.rates.get(&(from, to)) {
+            Some(rate) => Some(*rate),
+            None => self
+                .rates
+                .get(&(to, from))
+                .and_then(|r| Decimal::ONE.checked_div(*r)),
+        }
+    }
+
+    /// Converts an amount between currencies, rounding to the target's scale.
+    pub fn convert(
+        &self,
+        amount: Decimal,
+        from: Currency,
+        to: Currency,
+    ) -> Result<Decimal, Box<dyn Error>> {
+        let rate = self
+            .rate(from, to)
+            .ok_or_else(|| format!("No exchange rate from {} to {}", from, to))?;
+        round(amount.checked_mul(rate).ok_or("Amount too large")?, to)
+    }
+}
@@ -0,0 +1,143 @@
+//! Notation module for the calculator application.
+//!
+//! This module formats numbers in scientific or engineering notation and in bases other
+//! than ten, for output that is read by other programs or by people used to those forms.
+//! Rounding follows the same [`RoundingContext`] as ordinary display, applied to the
+//! mantissa.
+
+use crate::rounding::RoundingContext;
+use rust_decimal::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// How a number is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Notation {
+    /// Positional notation, as in `1025`.
+    #[default]
+    Plain,
+    /// A mantissa from 1 up to 10 and a power of ten, as in `1.025e3`.
+    Scientific,
+    /// A mantissa from 1 up to 1000 and a power of ten that is a multiple of three, as in
+    /// `12.5e3`.
+    Engineering,
+}
+
+impl fmt::Display for Notation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Notation::Plain => "plain",
+            Notation::Scientific => "scientific",
+            Notation::Engineering => "engineering",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Notation {
+    type Err = Box<dyn Error>;
+
+    /// Reads a notation by its name, as shown by [`Notation`]'s `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Notation::Plain),
+            "scientific" => Ok(Notation::Scientific),
+            "engineering" => Ok(Notation::Engineering),
+            _ => Err(format!(
+                "Unknown notation: {} (expected plain, scientific or engineering)",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+/// Formats a number in the given notation at the context's precision.
+///
+/// In scientific and engineering notation the precision applies to the mantissa.
+///
+/// ```
+/// use fermat::notation::{Notation, format_number};
+/// use fermat::rounding::RoundingContext;
+/// use rust_decimal_macros::dec;
+///
+/// let ctx = RoundingContext::default();
+/// assert_eq!(format_number(dec!(1025), Notation::Scientific, &ctx), "1.025e3");
+/// assert_eq!(format_number(dec!(0.0125), Notation::Engineering, &ctx), "12.5e-3");
+/// ```
+pub fn format_number(n: Decimal, notation: Notation, ctx: &RoundingContext) -> String {
+    let step = match notation {
+        Notation::Plain => return ctx.format(n),
+        Notation::Scientific => 1,
+        Notation::Engineering => 3,
+    };
+    if n.is_zero() {
+        return format!("{}e0", ctx.format(n));
+    }
+    let (mut mantissa, mut exponent) = split(n, step);
+    let mut shown = ctx.round(mantissa);
+    // Rounding can carry the mantissa up to the next power, as 9.99 does to 10.0
+    let limit = Decimal::from(10i64.pow(step));
+    if shown.abs() >= limit {
+        exponent += step as i64;
+        mantissa = shift(n, -exponent);
+        shown = ctx.round(mantissa);
+    }
+    format!("{}e{}", shown, exponent)
+}
+
+/// Splits a non-zero number into a mantissa and an exponent that is a multiple of `step`,
+/// with the mantissa from 1 up to `10^step`.
+fn split(n: Decimal, step: u32) -> (Decimal, i64) {
+    let n = n.normalize();
+    let digits = n.mantissa().unsigned_abs().ilog10() as i64 + 1;
+    // The power of ten of the leading digit
+    let magnitude = digits - 1 - n.scale() as i64;
+    let exponent = magnitude.div_euclid(step as i64) * step as i64;
+    (shift(n, -exponent), exponent)
+}
+
+/// Multiplies a number by `10^places` without rounding it.
+fn shift(n: Decimal, places: i64) -> Decimal {
+    let scale = n.scale() as i64 - places;
+    if scale >= 0 {
+        Decimal::try_from_i128_with_scale(n.mantissa(), scale as u32).unwrap_or(n)
+    } else {
+        // Only engineering notation scales a short mantissa up, by at most two places
+        n.normalize() * Decimal::from_i128_with_scale(10i128.pow((-scale) as u32), 0)
+    }
+}
+
+/// Formats a whole number in a base from 2 to 36, using lowercase letters for digits past 9.
+///
+/// ```
+/// use fermat::notation::format_radix;
+/// use rust_decimal_macros::dec;
+///
+/// assert_eq!(format_radix(dec!(255), 16).unwrap(), "ff");
+/// assert_eq!(format_radix(dec!(-5), 2).unwrap(), "-101");
+/// ```
+pub fn format_radix(n: Decimal, radix: u32) -> Result<String, Box<dyn Error>> {
+    if !(2..=36).contains(&radix) {
+        return Err(format!("Base must be from 2 to 36, not {}", radix).into());
+    }
+    if !n.fract().is_zero() {
+        return Err(format!("Only whole numbers can be shown in base {}: {}", radix, n).into());
+    }
+    let whole = n.trunc().to_i128().ok_or("Number too large")?;
+    let mut magnitude = whole.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        let digit = (magnitude % radix as u128) as u32;
+        digits.push(char::from_digit(digit, radix).unwrap_or('?'));
+        magnitude /= radix as u128;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if whole < 0 {
+        digits.push('-');
+    }
+    Ok(digits.into_iter().rev().collect())
+}
@@ -0,0 +1,598 @@
+//! Number theory module for the calculator application.
+//!
+//! This module provides exact integer routines: primality testing, prime factorization,
+//! Euler's totient, divisor enumeration, modular exponentiation and inversion, and the
+//! Chinese remainder theorem. All arithmetic is carried out on `u128`, with modular
+//! products computed without overflow so that every modulus up to `u128::MAX` is supported.
+//! Products modulo odd numbers, which primality testing and factorization use, are taken in
+//! Montgomery form, which avoids dividing wide products.
+//!
+//! Factorization and the search for the next prime can take long for large inputs, so their
+//! `_within` variants stop with [`LimitError::TimedOut`] once a [`Deadline`] passes.
+
+use crate::limits::{Deadline, LimitError};
+
+/// Bases for which Miller–Rabin is deterministic for every `n < 2^64`.
+const BASES_64: [u128; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Bases for which Miller–Rabin is deterministic for every `n < MR_DETERMINISTIC_LIMIT`.
+const BASES_128: [u128; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// Upper bound (exclusive) below which the first 13 prime bases are known to be sufficient.
+const MR_DETERMINISTIC_LIMIT: u128 = 3_317_044_064_679_887_385_961_981;
+
+/// Pollard's rho steps whose differences are multiplied together before taking a gcd.
+const RHO_BATCH: u64 = 128;
+
+/// Small primes used for trial division before falling back to Pollard's rho.
+const SMALL_PRIMES: [u128; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+/// Returns the greatest common divisor of `a` and `b`.
+pub fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let t = b;
+        b = a % b;
+        a = t;
+    }
+    a
+}
+
+/// Returns the least common multiple of `a` and `b`, or `None` if it overflows.
+pub fn lcm(a: u128, b: u128) -> Option<u128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Computes `(a + b) mod m` for `a, b < m` without overflowing.
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    if a >= m - b { a - (m - b) } else { a + b }
+}
+
+/// Computes `(a * b) mod m` without overflowing.
+fn mul_mod(a: u128, b: u128, m: u128) -> u128 {
+    let (a, b) = (a % m, b % m);
+    if m <= u64::MAX as u128 {
+        return a * b % m;
+    }
+
+    // Double-and-add so that no intermediate value exceeds `m`
+    let (mut a, mut b, mut result) = (a, b, 0);
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    result
+}
+
+/// Returns the full 256-bit product of `a` and `b` as its low and high halves.
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const LOW: u128 = u64::MAX as u128;
+    let (a1, a0) = (a >> 64, a & LOW);
+    let (b1, b0) = (b >> 64, b & LOW);
+    let (middle, middle_carry) = (a0 * b1).overflowing_add(a1 * b0);
+    let (low, low_carry) = (a0 * b0).overflowing_add(middle << 64);
+    let high = a1 * b1 + (middle >> 64) + ((middle_carry as u128) << 64) + low_carry as u128;
+    (low, high)
+}
+
+/// Multiplication modulo an odd `n`, on numbers in Montgomery form: `a` is held as
+/// `a * 2^128 mod n`.
+#[derive(Debug, Clone, Copy)]
+struct Montgomery {
+    n: u128,
+    /// `-n^-1 mod 2^128`.
+    n_neg_inv: u128,
+    /// `2^256 mod n`, which converts numbers into Montgomery form.
+    r2: u128,
+}
+
+impl Montgomery {
+    fn new(n: u128) -> Self {
+        debug_assert!(!n.is_multiple_of(2), "Montgomery form needs an odd modulus");
+        // Newton's iteration doubles the correct low bits of the inverse, starting from 3
+        let mut inv = n;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u128.wrapping_sub(n.wrapping_mul(inv)));
+        }
+        let mut r2 = (u128::MAX % n + 1) % n;
+        for _ in 0..128 {
+            r2 = add_mod(r2, r2, n);
+        }
+        Montgomery {
+            n,
+            n_neg_inv: inv.wrapping_neg(),
+            r2,
+        }
+    }
+
+    /// Divides a 256-bit number below `n * 2^128` by `2^128` modulo `n`.
+    fn reduce(&self, low: u128, high: u128) -> u128 {
+        let m = low.wrapping_mul(self.n_neg_inv);
+        let (m_low, m_high) = widening_mul(m, self.n);
+        // The low half of the sum is zero by the choice of `m`
+        let (_, carry) = low.overflowing_add(m_low);
+        let (t, overflow) = high.overflowing_add(m_high);
+        let (t, carry_overflow) = t.overflowing_add(carry as u128);
+        if overflow || carry_overflow || t >= self.n {
+            t.wrapping_sub(self.n)
+        } else {
+            t
+        }
+    }
+
+    fn mul(&self, a: u128, b: u128) -> u128 {
+        let (low, high) = widening_mul(a, b);
+        self.reduce(low, high)
+    }
+
+    fn to(&self, a: u128) -> u128 {
+        self.mul(a % self.n, self.r2)
+    }
+
+    fn from(&self, a: u128) -> u128 {
+        self.reduce(a, 0)
+    }
+
+    /// Raises a number in Montgomery form to a power.
+    fn pow(&self, mut base: u128, mut exp: u128) -> u128 {
+        let mut result = self.to(1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, base);
+            }
+            base = self.mul(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+/// Computes `base^exp mod m`.
+///
+/// # Panics
+///
+/// Panics if `m` is zero.
+pub fn powmod(base: u128, mut exp: u128, m: u128) -> u128 {
+    assert!(m != 0, "powmod with a zero modulus");
+    if m == 1 {
+        return 0;
+    }
+    if m > u64::MAX as u128 && !m.is_multiple_of(2) {
+        let field = Montgomery::new(m);
+        return field.from(field.pow(field.to(base), exp));
+    }
+    let mut base = base % m;
+    let mut result = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Returns the inverse of `a` modulo `m`, or `None` if `a` and `m` are not coprime.
+pub fn modinv(a: u128, m: u128) -> Option<u128> {
+    if m == 0 {
+        return None;
+    }
+    if m == 1 {
+        return Some(0);
+    }
+
+    // Extended Euclid, tracking the Bezout coefficient of `a` modulo `m`
+    let (mut old_r, mut r) = (a % m, m);
+    let (mut old_s, mut s) = (1u128, 0u128);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, sub_mod(old_s, mul_mod(q, s, m), m));
+    }
+    if old_r != 1 {
+        return None;
+    }
+    Some(old_s % m)
+}
+
+/// Computes `(a - b) mod m` for `a, b < m` without underflowing.
+fn sub_mod(a: u128, b: u128, m: u128) -> u128 {
+    if a >= b { a - b } else { m - (b - a) }
+}
+
+/// Solves a system of congruences `x ≡ r_i (mod m_i)` using the Chinese remainder theorem.
+///
+/// The moduli do not need to be pairwise coprime. Returns `(x, m)` where `m` is the least
+/// common multiple of all moduli and `0 <= x < m`, or `None` if the system is inconsistent,
+/// a modulus is zero, or the combined modulus overflows.
+pub fn crt(congruences: &[(u128, u128)]) -> Option<(u128, u128)> {
+    let mut x = 0u128;
+    let mut m = 1u128;
+    for &(residue, modulus) in congruences {
+        if modulus == 0 {
+            return None;
+        }
+
+        // Solve x + m*t ≡ residue (mod modulus) for t, which needs g | (residue - x)
+        let g = gcd(m, modulus);
+        let diff = sub_mod(residue % modulus, x % modulus, modulus);
+        if !diff.is_multiple_of(g) {
+            return None;
+        }
+        let step = modulus / g;
+        let combined = lcm(m, modulus)?;
+        let t = mul_mod(diff / g, modinv((m / g) % step, step)?, step);
+        x = add_mod(x, mul_mod(m, t, combined), combined);
+        m = combined;
+    }
+    Some((x, m))
+}
+
+/// Returns whether `n` is a Fermat probable prime to the given base.
+///
+/// Every prime passes this test; composites that pass are Fermat pseudoprimes.
+pub fn fermat_test(n: u128, base: u128) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if n < 4 {
+        return true;
+    }
+    let base = base % n;
+    if base == 0 {
+        return false;
+    }
+    powmod(base, n - 1, n) == 1
+}
+
+/// Runs a single Miller–Rabin round for odd `n > 2` with `n - 1 = d * 2^s`.
+fn miller_rabin_round(field: &Montgomery, d: u128, s: u32, base: u128) -> bool {
+    let n = field.n;
+    if base.is_multiple_of(n) {
+        return true;
+    }
+    let one = field.to(1);
+    let minus_one = n - one;
+    let mut x = field.pow(field.to(base), d);
+    if x == one || x == minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = field.mul(x, x);
+        if x == minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+/// Reduces a signed value modulo `n`.
+fn signed_mod(a: i128, n: u128) -> u128 {
+    let r = a.unsigned_abs() % n;
+    if a < 0 && r != 0 { n - r } else { r }
+}
+
+/// Returns the Jacobi symbol `(a / n)` for odd `n`.
+fn jacobi(a: i128, n: u128) -> i8 {
+    let mut a = signed_mod(a, n);
+    let mut n = n;
+    let mut result = 1;
+    while a != 0 {
+        while a.is_multiple_of(2) {
+            a /= 2;
+            if n % 8 == 3 || n % 8 == 5 {
+                result = -result;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        a %= n;
+    }
+    if n == 1 { result } else { 0 }
+}
+
+/// Halves `x` modulo odd `n`.
+fn half_mod(x: u128, n: u128) -> u128 {
+    if x.is_multiple_of(2) {
+        x / 2
+    } else {
+        // (x + n) / 2 without overflowing
+        x / 2 + n / 2 + 1
+    }
+}
+
+/// Strong Lucas probable prime test with Selfridge's parameters, for odd non-square `n`.
+fn strong_lucas(field: &Montgomery) -> bool {
+    let n = field.n;
+    let isqrt = integer_sqrt(n);
+    if isqrt * isqrt == n {
+        return false;
+    }
+
+    // Find the first D in 5, -7, 9, -11, ... with Jacobi(D / n) = -1
+    let mut d: i128 = 5;
+    loop {
+        match jacobi(d, n) {
+            -1 => break,
+            0 if d.unsigned_abs() != n => return false,
+            _ => {}
+        }
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+    }
+    // P is 1, so multiplying by it is left out
+    let q = (1 - d) / 4;
+    let d_mod = field.to(signed_mod(d, n));
+    let q_mod = field.to(signed_mod(q, n));
+    let two = field.to(2);
+
+    let mut k = n + 1;
+    let mut s = 0;
+    while k.is_multiple_of(2) {
+        k /= 2;
+        s += 1;
+    }
+
+    // Binary Lucas chain for U_k, V_k and Q^k
+    let (mut u, mut v, mut qk) = (0u128, two, field.to(1));
+    for bit in (0..128 - k.leading_zeros()).rev() {
+        u = field.mul(u, v);
+        v = sub_mod(field.mul(v, v), add_mod(qk, qk, n), n);
+        qk = field.mul(qk, qk);
+        if (k >> bit) & 1 == 1 {
+            let new_u = half_mod(add_mod(u, v, n), n);
+            let new_v = half_mod(add_mod(field.mul(d_mod, u), v, n), n);
+            u = new_u;
+            v = new_v;
+            qk = field.mul(qk, q_mod);
+        }
+    }
+
+    if u == 0 || v == 0 {
+        return true;
+    }
+    for _ in 1..s {
+        v = sub_mod(field.mul(v, v), add_mod(qk, qk, n), n);
+        qk = field.mul(qk, qk);
+        if v == 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns the integer square root of `n`, rounded down.
+pub fn integer_sqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = (n as f64).sqrt() as u128;
+    // Correct the floating-point estimate in both directions
+    while x.checked_mul(x).is_none_or(|sq| sq > n) {
+        x -= 1;
+    }
+    while (x + 1).checked_mul(x + 1).is_some_and(|sq| sq <= n) {
+        x += 1;
+    }
+    x
+}
+
+/// Returns whether `n` is prime.
+///
+/// Uses Miller–Rabin with fixed bases, which is deterministic for every `n` below
+/// 3.3 × 10²⁴ (and therefore for every `u64`). Larger inputs additionally run a strong
+/// Lucas test (Baillie–PSW), for which no counterexample is known.
+pub fn is_prime(n: u128) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in &SMALL_PRIMES {
+        if n == p {
+            return true;
+        }
+        if n.is_multiple_of(p) {
+            return false;
+        }
+    }
+
+    let mut d = n - 1;
+    let mut s = 0;
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+
+    let field = Montgomery::new(n);
+    if n <= u64::MAX as u128 {
+        return BASES_64
+            .iter()
+            .all(|&base| miller_rabin_round(&field, d, s, base));
+    }
+    if n < MR_DETERMINISTIC_LIMIT {
+        return BASES_128
+            .iter()
+            .all(|&base| miller_rabin_round(&field, d, s, base));
+    }
+    miller_rabin_round(&field, d, s, 2) && strong_lucas(&field)
+}
+
+/// Returns the smallest prime strictly greater than `n`, or `None` if it does not fit in `u128`.
+pub fn next_prime(n: u128) -> Option<u128> {
+    unbounded(next_prime_within(n, &Deadline::never()))
+}
+
+/// Returns the smallest prime strictly greater than `n`, or `None` if it does not fit in
+/// `u128`, giving up once the deadline passes.
+pub fn next_prime_within(n: u128, deadline: &Deadline) -> Result<Option<u128>, LimitError> {
+    if n < 2 {
+        return Ok(Some(2));
+    }
+    let mut candidate = if n.is_multiple_of(2) {
+        n + 1
+    } else {
+        n.wrapping_add(2)
+    };
+    loop {
+        if candidate < n {
+            // Wrapped around past `u128::MAX`
+            return Ok(None);
+        }
+        if is_prime(candidate) {
+            return Ok(Some(candidate));
+        }
+        deadline.check()?;
+        candidate = candidate.wrapping_add(2);
+    }
+}
+
+/// Returns the result of a computation run without a deadline, which cannot time out.
+fn unbounded<T>(result: Result<T, LimitError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => unreachable!("{} without a deadline", e),
+    }
+}
+
+/// Finds a non-trivial factor of the odd composite `n` using Pollard's rho (Brent's variant).
+///
+/// The differences of a batch of steps are multiplied together so that a single gcd is
+/// taken per batch; a batch whose product shares every factor with `n` is stepped through
+/// again one value at a time.
+fn pollard_rho(n: u128, deadline: &Deadline) -> Result<u128, LimitError> {
+    let field = Montgomery::new(n);
+    let mut seed = 1u128;
+    loop {
+        let c = field.to(seed);
+        let f = |x: u128| add_mod(field.mul(x, x), c, n);
+        let (mut x, mut y, mut saved) = (0, field.to(2), 0);
+        let (mut product, mut g) = (field.to(1), 1);
+        let mut length = 1u64;
+        while g == 1 {
+            x = y;
+            for i in 0..length {
+                if i % RHO_BATCH == 0 {
+                    deadline.check()?;
+                }
+                y = f(y);
+            }
+            let mut done = 0;
+            while done < length && g == 1 {
+                deadline.check()?;
+                saved = y;
+                for _ in 0..RHO_BATCH.min(length - done) {
+                    y = f(y);
+                    product = field.mul(product, x.abs_diff(y));
+                }
+                // Montgomery form multiplies by a power of two, which shares no factor with `n`
+                g = gcd(product, n);
+                done += RHO_BATCH;
+            }
+            length *= 2;
+        }
+        if g == n {
+            loop {
+                saved = f(saved);
+                g = gcd(x.abs_diff(saved), n);
+                if g != 1 {
+                    break;
+                }
+            }
+        }
+        if g != n {
+            return Ok(g);
+        }
+        seed += 1;
+    }
+}
+
+/// Pushes the prime factors of `n` (with repetition) onto `out`.
+fn factor_into(n: u128, out: &mut Vec<u128>, deadline: &Deadline) -> Result<(), LimitError> {
+    if n == 1 {
+        return Ok(());
+    }
+    if is_prime(n) {
+        out.push(n);
+        return Ok(());
+    }
+    let d = pollard_rho(n, deadline)?;
+    factor_into(d, out, deadline)?;
+    factor_into(n / d, out, deadline)
+}
+
+/// Returns the prime factorization of `n` as `(prime, exponent)` pairs in ascending order.
+///
+/// Small factors are removed by trial division and the remainder is split with Pollard's
+/// rho. `factor(0)` and `factor(1)` return an empty list.
+pub fn factor(n: u128) -> Vec<(u128, u32)> {
+    unbounded(factor_within(n, &Deadline::never()))
+}
+
+/// Returns the prime factorization of `n` as [`factor`] does, giving up once the deadline
+/// passes.
+pub fn factor_within(mut n: u128, deadline: &Deadline) -> Result<Vec<(u128, u32)>, LimitError> {
+    let mut primes = Vec::new();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    for &p in &SMALL_PRIMES {
+        while n.is_multiple_of(p) {
+            primes.push(p);
+            n /= p;
+        }
+    }
+    factor_into(n, &mut primes, deadline)?;
+    primes.sort_unstable();
+
+    let mut result: Vec<(u128, u32)> = Vec::new();
+    for p in primes {
+        match result.last_mut() {
+            Some((last, count)) if *last == p => *count += 1,
+            _ => result.push((p, 1)),
+        }
+    }
+    Ok(result)
+}
+
+/// Returns Euler's totient `φ(n)`, the count of integers in `1..=n` coprime to `n`.
+pub fn totient(n: u128) -> u128 {
+    unbounded(totient_within(n, &Deadline::never()))
+}
+
+/// Returns Euler's totient `φ(n)`, giving up once the deadline passes.
+pub fn totient_within(n: u128, deadline: &Deadline) -> Result<u128, LimitError> {
+    if n == 0 {
+        return Ok(0);
+    }
+    Ok(factor_within(n, deadline)?
+        .into_iter()
+        .fold(n, |acc, (p, _)| acc / p * (p - 1)))
+}
+
+/// Returns all positive divisors of `n` in ascending order. `divisors(0)` is empty.
+pub fn divisors(n: u128) -> Vec<u128> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut result = vec![1u128];
+    for (p, exp) in factor(n) {
+        let existing = result.len();
+        let mut power = 1u128;
+        for _ in 0..exp {
+            power *= p;
+            for i in 0..existing {
+                result.push(result[i] * power);
+            }
+        }
+    }
+    result.sort_unstable();
+    result
+}
@@ -0,0 +1,96 @@
+//! Operators module for the calculator application.
+//!
+//! This module lets a host application add its own operators, such as `a mod b` or
+//! `n choose k`. An [`Operator`] declares its symbol, whether it is written before, between
+//! or after its operands, and its precedence and associativity, which the parser honours
+//! in the same way as for the built-in operators. Operators are registered in a
+//! [`Registry`](crate::functions::Registry) alongside host-defined functions.
+
+use crate::value::Value;
+use std::error::Error;
+use std::fmt;
+
+/// Precedence of the built-in `+` and `-`.
+pub const ADDITIVE: u8 = 1;
+/// Precedence of the built-in `*`, `/` and `%`.
+pub const MULTIPLICATIVE: u8 = 2;
+/// Precedence of the built-in `±`.
+pub const UNCERTAINTY: u8 = 3;
+/// Precedence of the built-in `^`.
+pub const POWER: u8 = 4;
+/// Precedence of the built-in `!`.
+pub const FACTORIAL: u8 = 5;
+
+/// Where an operator is written relative to its operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixity {
+    /// Between two operands, as in `a ⊕ b`.
+    Infix,
+    /// Before one operand, as in `√x`.
+    Prefix,
+    /// After one operand, as in `n!`.
+    Postfix,
+}
+
+impl Fixity {
+    /// Returns the number of operands an operator of this fixity takes.
+    pub fn arity(self) -> usize {
+        match self {
+            Fixity::Infix => 2,
+            Fixity::Prefix | Fixity::Postfix => 1,
+        }
+    }
+}
+
+/// How a chain of infix operators of equal precedence is grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a ⊕ b ⊕ c` is `(a ⊕ b) ⊕ c`, as for `-`.
+    Left,
+    /// `a ⊕ b ⊕ c` is `a ⊕ (b ⊕ c)`, as for `^`.
+    Right,
+}
+
+/// An operator that can be used in an expression.
+///
+/// Precedence is on the same scale as the built-in operators, from [`ADDITIVE`] for `+`
+/// to [`FACTORIAL`] for `!`; higher binds tighter. A word such as `mod` is only read as
+/// the operator when it stands on its own, while a symbol such as `//` is read wherever
+/// it appears, taking priority over built-in operators that it starts with.
+pub trait Operator: Send + Sync {
+    /// The symbol or word the operator is written as.
+    fn symbol(&self) -> &str;
+
+    /// Where the operator is written relative to its operands.
+    fn fixity(&self) -> Fixity;
+
+    /// How tightly the operator binds.
+    fn precedence(&self) -> u8;
+
+    /// How a chain of the operator is grouped; only meaningful for infix operators.
+    fn associativity(&self) -> Associativity {
+        Associativity::Left
+    }
+
+    /// Applies the operator to its operands, in the order they are written.
+    ///
+    /// The result counts as exact unless an operand is an uncertain value.
+    fn apply(&self, args: &[Value]) -> Result<Value, Box<dyn Error>>;
+}
+
+impl fmt::Debug for dyn Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Returns whether a symbol can name an operator.
+///
+/// It must not be empty, contain whitespace, parentheses or commas, start with a digit or
+/// a decimal point, or be the reserved word `in`.
+pub(crate) fn is_valid_symbol(symbol: &str) -> bool {
+    !symbol.is_empty()
+        && symbol != "in"
+        && !symbol.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && !symbol.contains(|c: char| c.is_whitespace() || "(),".contains(c))
+}
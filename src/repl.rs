@@ -0,0 +1,157 @@
+//! REPL module for the Calculator application.
+//!
+//! This module runs a plain read-eval-print loop for terminals where the TUI does not work
+//! well, such as SSH sessions, dumb terminals and screen readers. It prints a prompt, reads
+//! a line, and prints the result below it, without the alternate screen or the button
+//! grid. Lines share their variables as in a script, and commands starting with `:` change
+//! the session's settings. The commands and the line editing are in the library's
+//! `console` module; this module reads the keys and draws the line.
+
+use crate::cli;
+use crossterm::{
+    cursor, event,
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    queue,
+    terminal::{self, ClearType},
+};
+use fermat::console::{self, LineEditor, Reply};
+use fermat::session::Session;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+const PROMPT: &str = "> ";
+
+/// Runs the loop until the input ends or `:quit` is entered, and returns the exit status.
+pub fn run(mut session: Session) -> i32 {
+    let mut input = Input::new();
+    println!("fermat {}, :help for commands", env!("CARGO_PKG_VERSION"));
+    loop {
+        let line = match input.read_line(PROMPT) {
+            Ok(Some(line)) => line,
+            Ok(None) => return 0,
+            Err(e) => {
+                eprintln!("error: cannot read input: {}", e);
+                return 1;
+            }
+        };
+        let line = line.trim();
+        if let Some(command) = line.strip_prefix(':') {
+            match console::command(&mut session, command) {
+                Ok(Reply::Show(text)) => println!("{}", text),
+                Ok(Reply::Quit) => return 0,
+                Err(e) => eprintln!("error: {}", e),
+            }
+            continue;
+        }
+        match session.run(line) {
+            Ok(None) => {}
+            Ok(Some(entry)) => println!("{}", console::show(&session, &entry)),
+            Err(e) => cli::report(line, &e, None),
+        }
+    }
+}
+
+/// Reads lines with editing and history when the input is an interactive terminal, or
+/// plainly otherwise.
+///
+/// The editor understands the arrow keys, Home, End, Backspace and Delete, the history
+/// with Up and Down, and the Emacs keys Ctrl-A, Ctrl-E, Ctrl-U, Ctrl-K and Ctrl-W. Ctrl-C
+/// discards the line and Ctrl-D on an empty line ends the input.
+struct Input {
+    editor: LineEditor,
+    /// Whether to edit in raw mode; off for piped input and dumb terminals.
+    interactive: bool,
+}
+
+impl Input {
+    fn new() -> Self {
+        let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        Input {
+            editor: LineEditor::new(),
+            interactive: io::stdin().is_terminal() && io::stdout().is_terminal() && !dumb,
+        }
+    }
+
+    /// Reads a line after showing the prompt, returning `None` at the end of the input.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        if self.interactive {
+            terminal::enable_raw_mode()?;
+            let line = self.edit(prompt);
+            terminal::disable_raw_mode()?;
+            println!();
+            return line;
+        }
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            // End the prompt's line
+            println!();
+            return Ok(None);
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+        self.editor.remember(line);
+        Ok(Some(line.to_string()))
+    }
+
+    /// Edits a line in raw mode until Enter, Ctrl-C or Ctrl-D.
+    fn edit(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let editor = &mut self.editor;
+        loop {
+            redraw(prompt, editor)?;
+            let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: event::KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+            let control = modifiers.contains(KeyModifiers::CONTROL);
+            match code {
+                KeyCode::Enter => return Ok(Some(editor.finish())),
+                KeyCode::Char('c') if control => {
+                    print!("^C");
+                    editor.cancel();
+                    return Ok(Some(String::new()));
+                }
+                KeyCode::Char('d') if control => {
+                    if editor.is_empty() {
+                        return Ok(None);
+                    }
+                    editor.delete();
+                }
+                KeyCode::Char('a') if control => editor.home(),
+                KeyCode::Char('e') if control => editor.end(),
+                KeyCode::Char('u') if control => editor.delete_to_start(),
+                KeyCode::Char('k') if control => editor.delete_to_end(),
+                KeyCode::Char('w') if control => editor.delete_word(),
+                KeyCode::Char(c) if !control => editor.insert(c),
+                KeyCode::Backspace => editor.backspace(),
+                KeyCode::Delete => editor.delete(),
+                KeyCode::Left => editor.left(),
+                KeyCode::Right => editor.right(),
+                KeyCode::Home => editor.home(),
+                KeyCode::End => editor.end(),
+                KeyCode::Up => editor.older(),
+                KeyCode::Down => editor.newer(),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Redraws the prompt and the line, placing the cursor where the editor's cursor is.
+fn redraw(prompt: &str, editor: &LineEditor) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    let text = editor.line();
+    queue!(
+        stdout,
+        cursor::MoveToColumn(0),
+        terminal::Clear(ClearType::UntilNewLine)
+    )?;
+    write!(stdout, "{}{}", prompt, text)?;
+    let column = prompt.chars().count() + editor.cursor();
+    queue!(stdout, cursor::MoveToColumn(column as u16))?;
+    stdout.flush()
+}
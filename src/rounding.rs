@@ -0,0 +1,360 @@
+//! Rounding module for the calculator application.
+//!
+//! This module defines the user-selectable display precision and rounding strategy. The
+//! strategy is applied wherever the evaluator has to round an inexact intermediate result
+//! (division, roots, transcendental functions), and the precision and strategy together
+//! determine how the final value is displayed.
+
+use rust_decimal::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Significant digits kept when rounding inexact intermediate results.
+///
+/// One less than the 28 digits a `Decimal` always holds, so that the selected strategy,
+/// rather than the built-in half-even rounding, decides the last kept digit of a result
+/// computed in `Decimal`, such as a root. Quotients are rounded from the exact quotient.
+const WORKING_DIGITS: u32 = 27;
+
+/// Largest number of decimal places or significant digits that can be requested.
+pub const MAX_DIGITS: u32 = 28;
+
+/// Strategy used to round a value that cannot be represented exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Round to nearest, ties to even ("banker's rounding").
+    #[default]
+    HalfEven,
+    /// Round to nearest, ties away from zero.
+    HalfUp,
+    /// Always round toward zero (truncate).
+    TowardZero,
+    /// Always round toward negative infinity.
+    Floor,
+    /// Always round toward positive infinity.
+    Ceiling,
+}
+
+impl Rounding {
+    /// Returns the next strategy, wrapping around after `Ceiling`.
+    pub fn next(self) -> Self {
+        match self {
+            Rounding::HalfEven => Rounding::HalfUp,
+            Rounding::HalfUp => Rounding::TowardZero,
+            Rounding::TowardZero => Rounding::Floor,
+            Rounding::Floor => Rounding::Ceiling,
+            Rounding::Ceiling => Rounding::HalfEven,
+        }
+    }
+
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Rounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::TowardZero => RoundingStrategy::ToZero,
+            Rounding::Floor => RoundingStrategy::ToNegativeInfinity,
+            Rounding::Ceiling => RoundingStrategy::ToPositiveInfinity,
+        }
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rounding::HalfEven => "half-even",
+            Rounding::HalfUp => "half-up",
+            Rounding::TowardZero => "toward zero",
+            Rounding::Floor => "floor",
+            Rounding::Ceiling => "ceiling",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = Box<dyn Error>;
+
+    /// Reads a strategy by the name it is shown with, writing `toward-zero` for
+    /// `toward zero` where a single word is needed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "half-even" => Ok(Rounding::HalfEven),
+            "half-up" => Ok(Rounding::HalfUp),
+            "toward zero" | "toward-zero" => Ok(Rounding::TowardZero),
+            "floor" => Ok(Rounding::Floor),
+            "ceiling" => Ok(Rounding::Ceiling),
+            _ => Err(format!(
+                "Unknown rounding: {} (expected half-even, half-up, toward-zero, floor or ceiling)",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+/// How many digits of a result are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// A fixed number of digits after the decimal point.
+    DecimalPlaces(u32),
+    /// A fixed number of significant digits.
+    SignificantDigits(u32),
+}
+
+impl Precision {
+    /// Returns the number of digits, regardless of the kind of precision.
+    pub fn digits(self) -> u32 {
+        match self {
+            Precision::DecimalPlaces(n) | Precision::SignificantDigits(n) => n,
+        }
+    }
+
+    /// Returns the same kind of precision with a different number of digits.
+    ///
+    /// The count is clamped to `0..=MAX_DIGITS`, and significant digits to at least one.
+    pub fn with_digits(self, digits: u32) -> Self {
+        match self {
+            Precision::DecimalPlaces(_) => Precision::DecimalPlaces(digits.min(MAX_DIGITS)),
+            Precision::SignificantDigits(_) => {
+                Precision::SignificantDigits(digits.clamp(1, MAX_DIGITS))
+            }
+        }
+    }
+
+    /// Switches between decimal places and significant digits, keeping the digit count.
+    pub fn toggle_kind(self) -> Self {
+        match self {
+            Precision::DecimalPlaces(n) => Precision::SignificantDigits(n.max(1)),
+            Precision::SignificantDigits(n) => Precision::DecimalPlaces(n),
+        }
+    }
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Precision::DecimalPlaces(10)
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Precision::DecimalPlaces(n) => write!(f, "{} dp", n),
+            Precision::SignificantDigits(n) => write!(f, "{} sig", n),
+        }
+    }
+}
+
+/// The precision and rounding strategy used for evaluation and display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RoundingContext {
+    pub precision: Precision,
+    pub rounding: Rounding,
+}
+
+impl RoundingContext {
+    /// Rounds an inexact intermediate result using the active strategy.
+    ///
+    /// The value should be the most precise one a `Decimal` holds, such as a root or a
+    /// series; a quotient should be computed with [`RoundingContext::divide`] instead, as
+    /// `Decimal` division has already rounded it half-even.
+    pub fn round_intermediate(&self, value: Decimal) -> Decimal {
+        round_significant(value, WORKING_DIGITS, self.rounding)
+    }
+
+    /// Divides `a` by `b`, rounding the exact quotient once using the active strategy.
+    ///
+    /// Returns `None` if `b` is zero or the quotient is too large.
+    ///
+    /// ```
+    /// use fermat::rounding::{Rounding, RoundingContext};
+    /// use rust_decimal_macros::dec;
+    ///
+    /// let ctx = RoundingContext {
+    ///     rounding: Rounding::Ceiling,
+    ///     ..RoundingContext::default()
+    /// };
+    /// assert_eq!(ctx.divide(dec!(2), dec!(3)), Some(dec!(0.666666666666666666666666667)));
+    /// assert_eq!(ctx.divide(dec!(10), dec!(4)), Some(dec!(2.5)));
+    /// ```
+    pub fn divide(&self, a: Decimal, b: Decimal) -> Option<Decimal> {
+        divide(a, b, WORKING_DIGITS, self.rounding)
+    }
+
+    /// Rounds a value to the configured precision using the active strategy.
+    pub fn round(&self, value: Decimal) -> Decimal {
+        let rounded = match self.precision {
+            Precision::DecimalPlaces(dp) => {
+                value.round_dp_with_strategy(dp.min(MAX_DIGITS), self.rounding.strategy())
+            }
+            Precision::SignificantDigits(sf) => round_significant(value, sf.max(1), self.rounding),
+        };
+        if rounded.is_zero() {
+            Decimal::ZERO
+        } else {
+            rounded.normalize()
+        }
+    }
+
+    /// Rounds a value to `places` decimal places using the active strategy.
+    ///
+    /// A negative `places` rounds to tens, hundreds and so on.
+    pub fn round_places(&self, value: Decimal, places: i64) -> Option<Decimal> {
+        round_places(value, places, self.rounding)
+    }
+
+    /// Formats a value for display at the configured precision.
+    ///
+    /// Trailing zeros after the decimal point are removed.
+    pub fn format(&self, value: Decimal) -> String {
+        self.round(value).to_string()
+    }
+}
+
+/// Rounds a value to `digits` significant digits with the given strategy.
+fn round_significant(value: Decimal, digits: u32, rounding: Rounding) -> Decimal {
+    let value = value.normalize();
+    // Asking `Decimal` for more digits than a small value has can produce a scale past 28
+    let mantissa = value.mantissa().unsigned_abs();
+    if mantissa == 0 || mantissa.ilog10() < digits {
+        return value;
+    }
+    value
+        .round_sf_with_strategy(digits, rounding.strategy())
+        .unwrap_or(value)
+}
+
+/// Divides `a` by `b`, rounding the exact quotient once with the given strategy to
+/// `digits` significant digits, or to the 28 decimal places a `Decimal` holds if that is
+/// fewer.
+///
+/// The digits of the quotient are found by long division of the mantissas, so the digits
+/// after the last kept one are known exactly rather than rounded. Returns `None` if `b` is
+/// zero or the quotient is too large.
+fn divide(a: Decimal, b: Decimal, digits: u32, rounding: Rounding) -> Option<Decimal> {
+    if b.is_zero() {
+        return None;
+    }
+    if a.is_zero() {
+        return Some(Decimal::ZERO);
+    }
+    let negative = a.is_sign_negative() != b.is_sign_negative();
+    let mut quotient = LongDivision::new(a.mantissa().unsigned_abs(), b.mantissa().unsigned_abs());
+    // The quotient of the mantissas is scaled by 10^shift; `position` is the power of ten
+    // of the next digit
+    let shift = b.scale() as i32 - a.scale() as i32;
+    let mut position = shift + quotient.whole_digits() as i32 - 1;
+    let lowest = -(MAX_DIGITS as i32);
+    let mut kept: u128 = 0;
+    let mut significant = 0;
+    while significant < digits && position >= lowest {
+        kept = kept * 10 + quotient.next_digit();
+        if kept > 0 {
+            significant += 1;
+        }
+        position -= 1;
+    }
+    // A quotient too small to reach the lowest place keeps no digit
+    let exponent = (position + 1).max(lowest);
+    let next = if position == exponent - 1 {
+        quotient.next_digit()
+    } else {
+        0
+    };
+    let rest = !quotient.is_exhausted();
+    let inexact = next != 0 || rest;
+    let round_up = match rounding {
+        Rounding::HalfEven => next > 5 || (next == 5 && (rest || kept % 2 == 1)),
+        Rounding::HalfUp => next >= 5,
+        Rounding::TowardZero => false,
+        Rounding::Floor => negative && inexact,
+        Rounding::Ceiling => !negative && inexact,
+    };
+    if round_up {
+        kept += 1;
+    }
+    let (mantissa, scale) = if exponent >= 0 {
+        (kept.checked_mul(10u128.checked_pow(exponent as u32)?)?, 0)
+    } else {
+        (kept, exponent.unsigned_abs())
+    };
+    let mantissa = i128::try_from(mantissa).ok()?;
+    let mantissa = if negative { -mantissa } else { mantissa };
+    let quotient = Decimal::try_from_i128_with_scale(mantissa, scale).ok()?;
+    Some(if quotient.is_zero() {
+        Decimal::ZERO
+    } else {
+        quotient.normalize()
+    })
+}
+
+/// The decimal digits of the quotient of two whole numbers, most significant first.
+struct LongDivision {
+    /// The digits of the whole part not yet read, most significant first.
+    whole: std::vec::IntoIter<u128>,
+    remainder: u128,
+    divisor: u128,
+}
+
+impl LongDivision {
+    fn new(dividend: u128, divisor: u128) -> Self {
+        let mut whole = Vec::new();
+        let mut rest = dividend / divisor;
+        while rest > 0 {
+            whole.push(rest % 10);
+            rest /= 10;
+        }
+        whole.reverse();
+        LongDivision {
+            whole: whole.into_iter(),
+            remainder: dividend % divisor,
+            divisor,
+        }
+    }
+
+    /// Returns the number of digits of the whole part not yet read.
+    fn whole_digits(&self) -> usize {
+        self.whole.len()
+    }
+
+    fn next_digit(&mut self) -> u128 {
+        if let Some(digit) = self.whole.next() {
+            return digit;
+        }
+        // The remainder is below the divisor, which fits in 96 bits, so this cannot overflow
+        self.remainder *= 10;
+        let digit = self.remainder / self.divisor;
+        self.remainder %= self.divisor;
+        digit
+    }
+
+    /// Returns whether every digit not yet read is zero.
+    fn is_exhausted(&mut self) -> bool {
+        self.remainder == 0 && self.whole.all(|digit| digit == 0)
+    }
+}
+
+/// Rounds a value to `places` decimal places with the given strategy.
+///
+/// A negative `places` rounds to tens, hundreds and so on. Returns `None` if the rounded
+/// value cannot be represented.
+pub fn round_places(value: Decimal, places: i64, rounding: Rounding) -> Option<Decimal> {
+    let rounded = if places >= 0 {
+        value.round_dp_with_strategy(places.min(MAX_DIGITS as i64) as u32, rounding.strategy())
+    } else {
+        let exponent = u32::try_from(-places).ok()?;
+        if exponent > MAX_DIGITS {
+            return Some(Decimal::ZERO);
+        }
+        let factor = Decimal::from_i128(10i128.pow(exponent))?;
+        (value / factor)
+            .round_dp_with_strategy(0, rounding.strategy())
+            .checked_mul(factor)?
+    };
+    Some(if rounded.is_zero() {
+        Decimal::ZERO
+    } else {
+        rounded.normalize()
+    })
+}
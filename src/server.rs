@@ -0,0 +1,322 @@
+//! Server module for the calculator application.
+//!
+//! This module answers JSON-RPC 2.0 requests so that editors and other tools can evaluate
+//! expressions without starting a process for each one. Messages are exchanged one per
+//! line: every request is a JSON object on its own line, and every response is written on
+//! its own line in the same order. A batch of requests may be sent as an array. Each
+//! connection has its own [`Session`], so the variables it defines last until it closes.
+//!
+//! The methods are:
+//!
+//! - `eval {expression}`: evaluates an expression with the connection's variables,
+//!   returning the description of [`json::evaluation`].
+//! - `define {name, expression}`: evaluates an expression and stores it in a variable,
+//!   returning the same description with the `name` added.
+//! - `complete {expression, position?}`: returns the `prefix` of the name that ends at
+//!   `position` (a character offset, the end of the input by default), where it `start`s,
+//!   the `candidates` that complete it, each with its `name` and `kind`, and the
+//!   expression `completed` with its open parentheses closed, or `null`.
+//! - `diagnostics {expression}`: returns the syntax problems of an expression, in the
+//!   form of the `diagnostics` of an evaluation error.
+//! - `reset`: forgets every variable of the connection and returns `null`.
+//!
+//! A failed evaluation is a successful call whose result has `"ok": false`; JSON-RPC
+//! errors are reserved for messages that cannot be read, unknown methods and bad
+//! parameters, with the standard codes.
+
+use crate::engine::Evaluator;
+use crate::json::{self, Json};
+use crate::session::Session;
+use std::io::{self, BufRead, Write};
+
+/// The message is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The message is not a request object.
+const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters are missing or of the wrong type.
+const INVALID_PARAMS: i64 = -32602;
+
+/// An error answered instead of a result.
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// The state of one client's connection.
+///
+/// ```
+/// use fermat::engine::Evaluator;
+/// use fermat::server::Connection;
+///
+/// let mut connection = Connection::new(Evaluator::new());
+/// let response = connection
+///     .handle(r#"{"jsonrpc": "2.0", "id": 1, "method": "eval", "params": {"expression": "2 + 2"}}"#)
+///     .unwrap();
+/// assert!(response.contains(r#""formatted":"4""#));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Connection {
+    session: Session,
+}
+
+impl Connection {
+    /// Starts a connection that evaluates with the given evaluator's options.
+    pub fn new(evaluator: Evaluator) -> Self {
+        Connection {
+            session: Session::new(evaluator),
+        }
+    }
+
+    /// Answers a message, returning the response line, or `None` if nothing is to be
+    /// answered, as for a notification or a blank line.
+    pub fn handle(&mut self, message: &str) -> Option<String> {
+        if message.trim().is_empty() {
+            return None;
+        }
+        let response = match Json::parse(message) {
+            Ok(Json::Array(requests)) if !requests.is_empty() => {
+                let responses: Vec<Json> = requests
+                    .iter()
+                    .filter_map(|request| self.request(request))
+                    .collect();
+                if responses.is_empty() {
+                    return None;
+                }
+                Json::Array(responses)
+            }
+            Ok(Json::Array(_)) => response(
+                Json::Null,
+                Err(RpcError::new(INVALID_REQUEST, "Empty batch")),
+            ),
+            Ok(request) => self.request(&request)?,
+            Err(e) => response(Json::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+        Some(response.to_string())
+    }
+
+    /// Answers a single request, or returns `None` for a notification.
+    fn request(&mut self, request: &Json) -> Option<Json> {
+        let id = request.get("id").cloned();
+        let method = request.get("method").and_then(Json::as_str);
+        let result = match (request, method) {
+            (Json::Object(_), Some(method))
+                if request.get("jsonrpc").and_then(Json::as_str) == Some("2.0") =>
+            {
+                let params = request.get("params").unwrap_or(&Json::Null);
+                self.call(method, params)
+            }
+            _ => Err(RpcError::new(
+                INVALID_REQUEST,
+                "Expected an object with \"jsonrpc\": \"2.0\" and a method",
+            )),
+        };
+        match id {
+            // A valid request without an id is a notification, which is never answered
+            None if method.is_some() && matches!(request, Json::Object(_)) => None,
+            id => Some(response(id.unwrap_or(Json::Null), result)),
+        }
+    }
+
+    /// Carries out a method.
+    fn call(&mut self, method: &str, params: &Json) -> Result<Json, RpcError> {
+        match method {
+            "eval" => {
+                let expression = string(params, "expression")?;
+                let result = self.session.evaluator().compute(expression);
+                Ok(json::evaluation(
+                    self.session.evaluator(),
+                    expression,
+                    &result,
+                ))
+            }
+            "define" => {
+                let name = string(params, "name")?;
+                let expression = string(params, "expression")?;
+                let result = self.session.define(name, expression);
+                let mut report = json::evaluation(self.session.evaluator(), expression, &result);
+                report.insert("name", Json::from(name));
+                Ok(report)
+            }
+            "complete" => {
+                let expression = string(params, "expression")?;
+                let length = expression.chars().count();
+                let position = match params.get("position") {
+                    None | Some(Json::Null) => length,
+                    Some(position) => position
+                        .as_usize()
+                        .filter(|position| *position <= length)
+                        .ok_or_else(|| {
+                            RpcError::new(
+                                INVALID_PARAMS,
+                                format!("position must be from 0 to {}", length),
+                            )
+                        })?,
+                };
+                Ok(self.complete(expression, position))
+            }
+            "diagnostics" => {
+                let expression = string(params, "expression")?;
+                let problems = self.session.evaluator().diagnose(expression);
+                Ok(json::diagnostics(expression, &problems))
+            }
+            "reset" => {
+                self.session.reset();
+                Ok(Json::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", method),
+            )),
+        }
+    }
+
+    /// Lists the names that complete the one ending at `position` characters into the
+    /// expression.
+    fn complete(&self, expression: &str, position: usize) -> Json {
+        let before: Vec<char> = expression.chars().take(position).collect();
+        let start = before
+            .iter()
+            .rposition(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+            .map_or(0, |i| i + 1);
+        let prefix: String = before[start..].iter().collect();
+        let evaluator = self.session.evaluator();
+        let candidates = if prefix.is_empty() || prefix.starts_with(|c: char| c.is_ascii_digit()) {
+            Vec::new()
+        } else {
+            evaluator
+                .completions(&prefix)
+                .into_iter()
+                .map(|(name, kind)| {
+                    Json::object([("name", Json::from(name)), ("kind", Json::from(kind))])
+                })
+                .collect()
+        };
+        Json::object([
+            ("prefix", Json::from(prefix)),
+            ("start", Json::from(start)),
+            ("candidates", Json::Array(candidates)),
+            ("completed", Json::from(evaluator.complete(expression))),
+        ])
+    }
+}
+
+/// Reads a string parameter.
+fn string<'a>(params: &'a Json, name: &str) -> Result<&'a str, RpcError> {
+    params.get(name).and_then(Json::as_str).ok_or_else(|| {
+        RpcError::new(
+            INVALID_PARAMS,
+            format!("Expected a string parameter \"{}\"", name),
+        )
+    })
+}
+
+/// Builds a response with a result or an error.
+fn response(id: Json, result: Result<Json, RpcError>) -> Json {
+    let outcome = match result {
+        Ok(result) => ("result", result),
+        Err(e) => (
+            "error",
+            Json::object([
+                ("code", Json::Number(e.code.to_string())),
+                ("message", Json::String(e.message)),
+            ]),
+        ),
+    };
+    Json::object([("jsonrpc", Json::from("2.0")), outcome, ("id", id)])
+}
+
+/// Answers the requests read from `reader` on `writer` until the input ends, as one
+/// connection.
+pub fn serve(evaluator: Evaluator, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+    let mut connection = Connection::new(evaluator);
+    for line in reader.lines() {
+        if let Some(response) = connection.handle(&line?) {
+            writeln!(writer, "{}", response)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Accepts connections on a Unix socket, serving each one on its own thread with its own
+/// session. Runs until the process is stopped.
+///
+/// A socket file left behind by a server that is no longer running is replaced, but any
+/// other file at the path is an error and is left alone. The socket file is removed when
+/// this returns. A connection that cannot be accepted is reported on stderr and the server
+/// goes on.
+#[cfg(unix)]
+pub fn listen(path: &std::path::Path, evaluator: Evaluator) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the path exists and is not a socket",
+            ));
+        }
+        Ok(_) if UnixStream::connect(path).is_ok() => {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another server is listening on this socket",
+            ));
+        }
+        Ok(_) => std::fs::remove_file(path)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = UnixListener::bind(path)?;
+    let _socket = SocketFile(path);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                // Such as running out of file descriptors: wait for some to be released
+                eprintln!("error: cannot accept a connection: {}", e);
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                continue;
+            }
+        };
+        let evaluator = evaluator.clone();
+        std::thread::spawn(move || {
+            let reader = io::BufReader::new(&stream);
+            // A client that disconnects abruptly only ends its own connection
+            let _ = serve(evaluator, reader, &stream);
+        });
+    }
+    Ok(())
+}
+
+/// Removes the socket file of a server when it is dropped.
+#[cfg(unix)]
+struct SocketFile<'a>(&'a std::path::Path);
+
+#[cfg(unix)]
+impl Drop for SocketFile<'_> {
+    fn drop(&mut self) {
+        remove_socket(self.0);
+    }
+}
+
+/// Removes the socket file of a server that is shutting down, if it is still a socket.
+#[cfg(unix)]
+pub fn remove_socket(path: &std::path::Path) {
+    use std::os::unix::fs::FileTypeExt;
+
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        let _ = std::fs::remove_file(path);
+    }
+}
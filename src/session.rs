@@ -0,0 +1,269 @@
+//! Session module for the calculator application.
+//!
+//! This module evaluates a sequence of lines that share their variables, as in a script
+//! or an interactive session. Each line is an expression, an assignment `name = expression`
+//! that defines a variable for the lines after it, or blank; `#` starts a comment. A
+//! [`Script`] reads lines from a file and splices in other scripts named by
+//! `#include "path"` lines.
+
+use crate::engine::{EvalError, Evaluator};
+use crate::value::Computed;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What a line asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Statement<'a> {
+    /// Nothing but whitespace or a comment.
+    Blank,
+    /// `#include "path"`, importing another script.
+    Include(&'a str),
+    /// `name = expression`, defining a variable.
+    Assign(&'a str, &'a str),
+    /// An expression to evaluate.
+    Expression(&'a str),
+}
+
+impl<'a> Statement<'a> {
+    /// Reads a line, dropping any comment and surrounding whitespace.
+    ///
+    /// ```
+    /// use fermat::session::Statement;
+    ///
+    /// assert_eq!(Statement::parse("rate = 0.2  # VAT"), Statement::Assign("rate", "0.2"));
+    /// assert_eq!(Statement::parse("#include \"units.fermat\""), Statement::Include("units.fermat"));
+    /// ```
+    pub fn parse(line: &'a str) -> Self {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix("#include") {
+            let path = path.trim();
+            let path = path
+                .strip_prefix('"')
+                .and_then(|p| p.strip_suffix('"'))
+                .unwrap_or(path);
+            return Statement::Include(path);
+        }
+        let line = match line.find('#') {
+            Some(comment) => line[..comment].trim_end(),
+            None => line,
+        };
+        if line.is_empty() {
+            return Statement::Blank;
+        }
+        if let Some((name, expression)) = line.split_once('=') {
+            let name = name.trim();
+            let mut chars = name.chars();
+            let is_name = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if is_name {
+                return Statement::Assign(name, expression.trim());
+            }
+        }
+        Statement::Expression(line)
+    }
+}
+
+/// Returns a line without its label: what follows the last `: `, or the whole line.
+pub(crate) fn without_label(line: &str) -> &str {
+    match line.rfind(": ") {
+        Some(colon) => line[colon + 2..].trim(),
+        None => line.trim(),
+    }
+}
+
+/// Returns the calculation of a line of prose: what follows the last `: `, or the line
+/// without its list, heading or quote markers.
+pub(crate) fn calculation(line: &str) -> &str {
+    if line.contains(": ") {
+        return without_label(line);
+    }
+    let mut rest = line.trim();
+    while let Some(after) = without_marker(rest) {
+        rest = after.trim_start();
+    }
+    rest
+}
+
+/// Returns what follows a list, heading or quote marker at the start of a line, such as
+/// `- `, `1. ` or `## `.
+fn without_marker(text: &str) -> Option<&str> {
+    if let Some(after) = text.strip_prefix('>') {
+        return Some(after);
+    }
+    let digits = text.trim_start_matches(|c: char| c.is_ascii_digit());
+    let after = if text.starts_with('#') {
+        text.trim_start_matches('#')
+    } else if digits.len() < text.len() {
+        digits.strip_prefix(['.', ')'])?
+    } else {
+        text.strip_prefix(['-', '*', '+'])?
+    };
+    after.strip_prefix(' ')
+}
+
+/// The result of a line that was evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The variable the line assigned, if it was an assignment.
+    pub name: Option<String>,
+    /// The value of the expression.
+    pub computed: Computed,
+}
+
+/// Evaluates lines one after another, keeping the variables they define.
+///
+/// ```
+/// use fermat::session::Session;
+///
+/// let mut session = Session::default();
+/// session.run("price = 80").unwrap();
+/// let entry = session.run("price * 1.25").unwrap().unwrap();
+/// assert_eq!(session.evaluator().format(&entry.computed.value), "100");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    evaluator: Evaluator,
+}
+
+impl Session {
+    /// Creates a session that evaluates with the given evaluator's options.
+    pub fn new(evaluator: Evaluator) -> Self {
+        Session { evaluator }
+    }
+
+    /// Returns the evaluator, whose registry holds the variables defined so far.
+    pub fn evaluator(&self) -> &Evaluator {
+        &self.evaluator
+    }
+
+    /// Returns the evaluator for changing its options.
+    pub fn evaluator_mut(&mut self) -> &mut Evaluator {
+        &mut self.evaluator
+    }
+
+    /// Evaluates a line, returning `None` if it is blank.
+    ///
+    /// An `#include` line is an error here, since only a [`Script`] knows where to look
+    /// for the file.
+    pub fn run(&mut self, line: &str) -> Result<Option<Entry>, EvalError> {
+        match Statement::parse(line) {
+            Statement::Blank => Ok(None),
+            Statement::Include(_) => Err(EvalError::Evaluation(
+                "#include is only allowed in script files".to_string(),
+            )),
+            Statement::Assign(name, expression) => {
+                let computed = self.define(name, expression)?;
+                Ok(Some(Entry {
+                    name: Some(name.to_string()),
+                    computed,
+                }))
+            }
+            Statement::Expression(expression) => Ok(Some(Entry {
+                name: None,
+                computed: self.evaluator.compute(expression)?,
+            })),
+        }
+    }
+
+    /// Evaluates an expression and stores its value in a variable.
+    pub fn define(&mut self, name: &str, input: &str) -> Result<Computed, EvalError> {
+        let computed = self.evaluator.compute(input)?;
+        self.evaluator
+            .functions_mut()
+            .set_variable(name, computed)
+            .map_err(EvalError::from)?;
+        Ok(computed)
+    }
+
+    /// Returns the variables and their values in order of name.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Computed)> {
+        self.evaluator.functions().variables()
+    }
+
+    /// Forgets every variable, keeping the evaluator's options.
+    pub fn reset(&mut self) {
+        let names: Vec<String> = self.variables().map(|(name, _)| name.to_string()).collect();
+        for name in names {
+            self.evaluator.functions_mut().remove_variable(&name);
+        }
+    }
+}
+
+/// A line of a script, after the scripts it includes have been spliced in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptLine {
+    /// The file the line is in.
+    pub path: PathBuf,
+    /// The line number in that file, counting from 1.
+    pub number: usize,
+    /// The text of the line.
+    pub text: String,
+    /// Whether the line comes from an included script rather than the script itself.
+    pub included: bool,
+}
+
+/// The lines of a script file and of every script it includes, in evaluation order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub lines: Vec<ScriptLine>,
+}
+
+impl Script {
+    /// Reads a script file.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Script::parse(&source, path)
+    }
+
+    /// Reads a script from text, as if it were the file at `path`.
+    ///
+    /// Included paths are relative to the directory of `path`. Including a script that is
+    /// already being read, directly or through others, is an error.
+    pub fn parse(source: &str, path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut script = Script::default();
+        script.splice(source, path, &mut vec![identity(path)])?;
+        Ok(script)
+    }
+
+    /// Appends the lines of `source`, expanding includes; `open` lists the scripts being
+    /// read, outermost first.
+    fn splice(
+        &mut self,
+        source: &str,
+        path: &Path,
+        open: &mut Vec<PathBuf>,
+    ) -> Result<(), Box<dyn Error>> {
+        for (i, text) in source.lines().enumerate() {
+            let Statement::Include(target) = Statement::parse(text) else {
+                self.lines.push(ScriptLine {
+                    path: path.to_path_buf(),
+                    number: i + 1,
+                    text: text.to_string(),
+                    included: open.len() > 1,
+                });
+                continue;
+            };
+            let location = format!("{}:{}", path.display(), i + 1);
+            if target.is_empty() {
+                return Err(format!("{}: #include needs a path", location).into());
+            }
+            let included = path.parent().unwrap_or(Path::new("")).join(target);
+            if open.contains(&identity(&included)) {
+                return Err(format!("{}: {} includes itself", location, target).into());
+            }
+            let source = fs::read_to_string(&included)
+                .map_err(|e| format!("{}: cannot read {}: {}", location, target, e))?;
+            open.push(identity(&included));
+            self.splice(&source, &included, open)?;
+            open.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Returns a path that is the same for every way of naming a file, where possible.
+fn identity(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
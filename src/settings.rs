@@ -0,0 +1,269 @@
+//! Settings module for the Calculator TUI.
+//!
+//! This module reads the parts of the configuration file that belong to the TUI: the
+//! colors of the `[theme]` table, the keys of the `[keys]` table and the button layout of
+//! the `[grid]` table. Every setting is checked when the program starts, so that a typo is
+//! reported instead of silently ignored.
+
+use crate::button_grid;
+use crossterm::event::KeyCode;
+use fermat::config::{self, Config, Entry, Setting};
+use ratatui::style::Color;
+use std::error::Error;
+use std::str::FromStr;
+
+/// The colors of the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub button: Color,
+    pub button_text: Color,
+    pub pressed: Color,
+    pub pressed_text: Color,
+    /// Syntax errors and their carets.
+    pub error: Color,
+    /// Previews, the normalized input and status messages.
+    pub muted: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            button: Color::DarkGray,
+            button_text: Color::White,
+            pressed: Color::LightBlue,
+            pressed_text: Color::Black,
+            error: Color::Red,
+            muted: Color::DarkGray,
+        }
+    }
+}
+
+/// The keys for the actions that are not buttons of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keys {
+    pub quit: char,
+    pub fewer_digits: char,
+    pub more_digits: char,
+    pub toggle_precision: char,
+    pub cycle_rounding: char,
+    pub toggle_money: char,
+    pub export_latex: char,
+    pub export_mathml: char,
+    pub worksheet: char,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            quit: 'q',
+            fewer_digits: '<',
+            more_digits: '>',
+            toggle_precision: '#',
+            cycle_rounding: '~',
+            toggle_money: '$',
+            export_latex: 'l',
+            export_mathml: 'm',
+            worksheet: 'w',
+        }
+    }
+}
+
+impl Keys {
+    /// Returns each action's name and key, as written in the `[keys]` table.
+    fn actions(&self) -> [(&'static str, char); 9] {
+        [
+            ("quit", self.quit),
+            ("fewer_digits", self.fewer_digits),
+            ("more_digits", self.more_digits),
+            ("toggle_precision", self.toggle_precision),
+            ("cycle_rounding", self.cycle_rounding),
+            ("toggle_money", self.toggle_money),
+            ("export_latex", self.export_latex),
+            ("export_mathml", self.export_mathml),
+            ("worksheet", self.worksheet),
+        ]
+    }
+
+    fn action_mut(&mut self, name: &str) -> Option<&mut char> {
+        Some(match name {
+            "quit" => &mut self.quit,
+            "fewer_digits" => &mut self.fewer_digits,
+            "more_digits" => &mut self.more_digits,
+            "toggle_precision" => &mut self.toggle_precision,
+            "cycle_rounding" => &mut self.cycle_rounding,
+            "toggle_money" => &mut self.toggle_money,
+            "export_latex" => &mut self.export_latex,
+            "export_mathml" => &mut self.export_mathml,
+            "worksheet" => &mut self.worksheet,
+            _ => return None,
+        })
+    }
+}
+
+/// The TUI's settings.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub theme: Theme,
+    pub keys: Keys,
+    /// The labels of the buttons, row by row.
+    pub grid: Vec<Vec<String>>,
+}
+
+impl Settings {
+    /// Reads the TUI's tables of the configuration, using the defaults for anything they do
+    /// not set.
+    pub fn from_config(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let mut settings = Settings {
+            grid: button_grid::DEFAULT_LAYOUT
+                .iter()
+                .map(|row| row.iter().map(|label| label.to_string()).collect())
+                .collect(),
+            ..Settings::default()
+        };
+        for entry in config.table("theme").map_or(&[][..], |t| &t.entries) {
+            settings.theme_setting(entry)?;
+        }
+        for entry in config.table("keys").map_or(&[][..], |t| &t.entries) {
+            let expected = "quit, fewer_digits, more_digits, toggle_precision, \
+                            cycle_rounding, toggle_money, export_latex, export_mathml, \
+                            worksheet";
+            let key = key(entry)?;
+            let action = settings
+                .keys
+                .action_mut(&entry.key)
+                .ok_or_else(|| config::unknown(entry, "keys", expected))?;
+            *action = key;
+        }
+        for entry in config.table("grid").map_or(&[][..], |t| &t.entries) {
+            match entry.key.as_str() {
+                "rows" => settings.grid = rows(entry)?,
+                _ => return Err(config::unknown(entry, "grid", "rows")),
+            }
+        }
+        settings.check_keys(config)?;
+        Ok(settings)
+    }
+
+    fn theme_setting(&mut self, entry: &Entry) -> Result<(), Box<dyn Error>> {
+        let color = Color::from_str(config::string(entry)?).map_err(|_| {
+            format!(
+                "line {}: unknown color {} (expected a name such as \"light blue\", \
+                 a number from 0 to 255, or \"#rrggbb\")",
+                entry.line, entry.value
+            )
+        })?;
+        let theme = &mut self.theme;
+        let field = match entry.key.as_str() {
+            "button" => &mut theme.button,
+            "button_text" => &mut theme.button_text,
+            "pressed" => &mut theme.pressed,
+            "pressed_text" => &mut theme.pressed_text,
+            "error" => &mut theme.error,
+            "muted" => &mut theme.muted,
+            _ => {
+                return Err(config::unknown(
+                    entry,
+                    "theme",
+                    "button, button_text, pressed, pressed_text, error, muted",
+                ));
+            }
+        };
+        *field = color;
+        Ok(())
+    }
+
+    /// Checks that no key is given two meanings, as an action would hide another action or
+    /// a button on the same key.
+    fn check_keys(&self, config: &Config) -> Result<(), Box<dyn Error>> {
+        let line = config
+            .table("keys")
+            .or(config.table("grid"))
+            .map_or(0, |table| table.line);
+        let actions = self.keys.actions();
+        for (i, (name, key)) in actions.iter().enumerate() {
+            if let Some((other, _)) = actions[..i].iter().find(|(_, k)| k == key) {
+                return Err(format!(
+                    "line {}: '{}' is the key of both {} and {}",
+                    line, key, other, name
+                )
+                .into());
+            }
+            let button = self
+                .grid
+                .iter()
+                .flatten()
+                .find(|label| button_grid::default_key(label) == Some(KeyCode::Char(*key)));
+            if let Some(label) = button {
+                return Err(format!(
+                    "line {}: '{}' is the key of both {} and the {} button",
+                    line, key, name, label
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a key given as a string of one character.
+fn key(entry: &Entry) -> Result<char, Box<dyn Error>> {
+    let text = config::string(entry)?;
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_whitespace() => Ok(c),
+        _ => Err(format!(
+            "line {}: {} must be a single character, not {}",
+            entry.line, entry.key, entry.value
+        )
+        .into()),
+    }
+}
+
+/// Reads the button layout, an array of rows that each hold the same number of labels.
+fn rows(entry: &Entry) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+    let invalid = |why: &str| -> Box<dyn Error> {
+        format!(
+            "line {}: rows must be an array of rows of button labels, such as \
+             [[\"7\", \"8\"], [\"+\", \"-\"]]: {}",
+            entry.line, why
+        )
+        .into()
+    };
+    let rows = entry
+        .value
+        .as_array()
+        .ok_or_else(|| invalid("not an array"))?;
+    if rows.is_empty() || rows.len() > button_grid::MAX_ROWS {
+        return Err(invalid(&format!(
+            "there must be from 1 to {} rows",
+            button_grid::MAX_ROWS
+        )));
+    }
+    let mut grid = Vec::new();
+    for row in rows {
+        let labels = row
+            .as_array()
+            .ok_or_else(|| invalid(&format!("{} is not a row", row)))?;
+        let labels = labels
+            .iter()
+            .map(|label| match label {
+                Setting::String(s) if !s.trim().is_empty() => Ok(s.trim().to_string()),
+                _ => Err(invalid(&format!("{} is not a label", label))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if labels.is_empty() || labels.len() > button_grid::MAX_COLUMNS {
+            return Err(invalid(&format!(
+                "each row must have from 1 to {} buttons",
+                button_grid::MAX_COLUMNS
+            )));
+        }
+        if grid
+            .first()
+            .is_some_and(|first: &Vec<String>| first.len() != labels.len())
+        {
+            return Err(invalid("every row must have the same number of buttons"));
+        }
+        grid.push(labels);
+    }
+    Ok(grid)
+}
@@ -0,0 +1,142 @@
+use crate::annotate::annotate;
+use crate::engine::EvalError;
+use crate::session::Session;
+
+const NOTES: &str = "\
+# Trip budget
+
+Flights: 2 * 180 =
+- Hotel: hotel = 3 * 95 =
+- Per person: (2 * 180 + hotel) / 3 =
+10 / 4 =
+Not a calculation = here
+Heading
+=======
+
+```fermat
+fuel = 120
+tolls = 35.5  # toll roads
+fuel + tolls
+share = (fuel + tolls) / 3
+```
+
+```python
+x = 1 / 0 =
+```
+";
+
+const ANNOTATED: &str = "\
+# Trip budget
+
+Flights: 2 * 180 = `360`
+- Hotel: hotel = 3 * 95 = `285`
+- Per person: (2 * 180 + hotel) / 3 = `215`
+10 / 4 = `2.5`
+Not a calculation = here
+Heading
+=======
+
+```fermat
+fuel = 120
+tolls = 35.5  # toll roads
+fuel + tolls  # = 155.5
+share = (fuel + tolls) / 3  # ≈ 51.8333333333
+```
+
+```python
+x = 1 / 0 =
+```
+";
+
+#[test]
+fn test_annotate_notes() {
+    let annotated = annotate(NOTES, &mut Session::default());
+    assert_eq!(annotated.text, ANNOTATED);
+    assert!(annotated.failures.is_empty());
+    assert_eq!(annotated.changed_lines(NOTES), vec![3, 4, 5, 6, 14, 15]);
+
+    // Annotating again changes nothing
+    let again = annotate(&annotated.text, &mut Session::default());
+    assert_eq!(again.text, ANNOTATED);
+    assert!(again.changed_lines(ANNOTATED).is_empty());
+}
+
+#[test]
+fn test_stale_results_are_updated() {
+    let stale = ANNOTATED
+        .replace("3 * 95 = `285`", "3 * 95 = `250`")
+        .replace("fuel = 120", "fuel = 150");
+    let annotated = annotate(&stale, &mut Session::default());
+    let expected = ANNOTATED
+        .replace("fuel = 120", "fuel = 150")
+        .replace("# = 155.5", "# = 185.5")
+        .replace("# ≈ 51.8333333333", "# ≈ 61.8333333333");
+    assert_eq!(annotated.text, expected);
+    assert_eq!(annotated.changed_lines(&stale), vec![4, 14, 15]);
+
+    // Line endings are kept
+    let crlf = "a: 1 + 1 = `3`\r\nb: 2 * 2 =\r\n";
+    assert_eq!(
+        annotate(crlf, &mut Session::default()).text,
+        "a: 1 + 1 = `2`\r\nb: 2 * 2 = `4`\r\n"
+    );
+}
+
+#[test]
+fn test_failures() {
+    let text = "Bad: 1 / 0 = `5`\nUnknown: y + 1 = `3`\n```fermat\n2 + * 3  # = 4\n```";
+    let annotated = annotate(text, &mut Session::default());
+    // Results that can no longer be computed are removed, and the lines are reported
+    assert_eq!(
+        annotated.text,
+        "Bad: 1 / 0 =\nUnknown: y + 1 =\n```fermat\n2 + * 3\n```"
+    );
+    let lines: Vec<(usize, &str)> = annotated
+        .failures
+        .iter()
+        .map(|failure| (failure.line, failure.input.as_str()))
+        .collect();
+    assert_eq!(lines, vec![(1, "1 / 0"), (2, "y + 1"), (4, "2 + * 3")]);
+    assert!(matches!(annotated.failures[2].error, EvalError::Syntax(_)));
+}
+
+#[test]
+fn test_prose_is_left_alone() {
+    // Lines ending in `=` that are not calculations, without a result written before
+    let text = "What is 2 + 2 =\nThe total: 2 + * 3 =\nSee the table =\n";
+    let annotated = annotate(text, &mut Session::default());
+    assert_eq!(annotated.text, text);
+    assert!(annotated.failures.is_empty());
+}
+
+#[test]
+fn test_only_results_are_replaced() {
+    let text = "\
+```fermat
+rent = 1200  # = see the lease
+rent / 3  # = TODO check
+rent / 7 # = 171
+rent / 4  # = 250  # = old
+2026-10-16 + 1 day  # = Mon 2000-01-03
+-5 EUR  # = CHF 1.00
+$10 / 3  # = -$3.33 × 1, $3.34 × 2
+interval(1, 2) * 2  # = [1, 2]
+```
+";
+    let expected = "\
+```fermat
+rent = 1200  # = see the lease
+rent / 3  # = TODO check  # = 400
+rent / 7 # = 171  # ≈ 171.4285714286
+rent / 4  # = 250  # = old  # = 300
+2026-10-16 + 1 day  # = Sat 2026-10-17
+-5 EUR  # = -€5.00
+$10 / 3  # = $3.34 × 1, $3.33 × 2
+interval(1, 2) * 2  # = [2, 4]
+```
+";
+    let annotated = annotate(text, &mut Session::default());
+    assert_eq!(annotated.text, expected);
+    assert!(annotated.failures.is_empty());
+    assert_eq!(annotate(expected, &mut Session::default()).text, expected);
+}
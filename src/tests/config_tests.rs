@@ -0,0 +1,134 @@
+use crate::config::{Config, Setting};
+use crate::limits::Limits;
+use crate::money::Currency;
+use crate::rounding::{Precision, Rounding};
+use rust_decimal_macros::dec;
+use std::time::Duration;
+
+#[test]
+fn test_parse_settings() {
+    let config = Config::parse(
+        r#"
+# Defaults for every session
+[calculator]
+significant = 6        # digits
+rounding = "half-up"
+money = "eur"
+backend = 'decimal'
+
+[limits]
+max_input_length = 120
+max_magnitude = "1e20"
+max_time_ms = 250
+
+[grid]
+rows = [
+    ["7", "8", "9"],   # first row
+    ["C", "CE", "+"],
+]
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.rounding.precision, Precision::SignificantDigits(6));
+    assert_eq!(config.rounding.rounding, Rounding::HalfUp);
+    assert_eq!(config.money, Currency::new("EUR"));
+
+    let limits = config.limits(Limits::default());
+    assert_eq!(limits.max_input_length, 120);
+    assert_eq!(limits.max_magnitude, dec!(100000000000000000000));
+    assert_eq!(limits.max_time, Duration::from_millis(250));
+    assert_eq!(limits.max_depth, Limits::default().max_depth);
+
+    let grid = config.table("grid").unwrap();
+    assert_eq!(grid.entries[0].line, 15);
+    let rows = grid.entries[0].value.as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(
+        rows[1].as_array().unwrap()[1],
+        Setting::String("CE".to_string())
+    );
+
+    let evaluator = config.evaluator_builder(Limits::default()).build();
+    assert_eq!(evaluator.money(), Currency::new("EUR"));
+    assert_eq!(evaluator.limits().max_input_length, 120);
+}
+
+#[test]
+fn test_empty_config_keeps_defaults() {
+    let config = Config::parse("# nothing here\n\n").unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(config.limits(Limits::default()), Limits::default());
+    assert!(config.table("theme").is_none());
+}
+
+#[test]
+fn test_invalid_settings() {
+    let cases = [
+        (
+            "[calculator]\nprecision = \"ten\"",
+            "line 2: precision must be a whole number",
+        ),
+        ("[calculator]\nprecision = 40", "from 0 to 28"),
+        (
+            "[calculator]\nprecision = 2\nsignificant = 3",
+            "line 3: set either precision",
+        ),
+        (
+            "[calculator]\nrounding = \"nearest\"",
+            "line 2: Unknown rounding: nearest",
+        ),
+        (
+            "[calculator]\nmoney = \"dollars\"",
+            "unknown currency 'dollars'",
+        ),
+        (
+            "[calculator]\nangle = \"deg\"",
+            "angle modes are not supported",
+        ),
+        (
+            "[calculator]\nbackend = \"float\"",
+            "only decimal is available",
+        ),
+        (
+            "[calculator]\nprecison = 3",
+            "unknown setting precison in [calculator]",
+        ),
+        ("[limits]\nmax_depth = 0", "at least 1"),
+        (
+            "[limits]\nmax_magnitude = \"big\"",
+            "max_magnitude must be a positive number",
+        ),
+        (
+            "[colours]\nbutton = \"red\"",
+            "line 1: unknown table [colours]",
+        ),
+        ("precision = 3", "must be inside a table"),
+        (
+            "[calculator]\nprecision = 3\nprecision = 4",
+            "line 3: precision is set twice",
+        ),
+        ("[limits]\n[limits]", "line 2: table [limits] appears twice"),
+        ("[calculator]\nprecision = 2.5", "2.5 is not a whole number"),
+        ("[calculator]\nrounding = half-up", "strings need quotes"),
+        ("[calculator]\nrounding = \"half-up", "unterminated string"),
+        (
+            "[calculator]\nprecision = 3 3",
+            "unexpected '3' after the value",
+        ),
+        (
+            "[grid]\nrows = [[\"1\", \"2\"]\n",
+            "line 3: unterminated array",
+        ),
+        ("[calculator.display]", "dotted keys are not supported"),
+    ];
+    for (text, expected) in cases {
+        let error = Config::parse(text).unwrap_err().to_string();
+        assert!(
+            error.contains(expected),
+            "{:?}: expected {:?}, got {:?}",
+            text,
+            expected,
+            error
+        );
+    }
+}
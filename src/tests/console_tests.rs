@@ -0,0 +1,190 @@
+use crate::console::{LineEditor, Reply, command, show};
+use crate::money::Currency;
+use crate::rounding::{Precision, Rounding};
+use crate::session::Session;
+
+fn shown(text: &str) -> Reply {
+    Reply::Show(text.to_string())
+}
+
+fn typed(text: &str) -> LineEditor {
+    let mut editor = LineEditor::new();
+    text.chars().for_each(|c| editor.insert(c));
+    editor
+}
+
+#[test]
+fn test_settings_commands() {
+    let mut session = Session::default();
+    assert_eq!(
+        command(&mut session, "prec 3").unwrap(),
+        shown("Showing 3 dp")
+    );
+    let entry = session.run("1 / 3").unwrap().unwrap();
+    assert_eq!(show(&session, &entry), "≈ 0.333");
+
+    assert_eq!(
+        command(&mut session, "sig  2").unwrap(),
+        shown("Showing 2 sig")
+    );
+    assert_eq!(
+        session.evaluator().rounding().precision,
+        Precision::SignificantDigits(2)
+    );
+    assert_eq!(
+        command(&mut session, "round floor").unwrap(),
+        shown("Rounding floor")
+    );
+    assert_eq!(session.evaluator().rounding().rounding, Rounding::Floor);
+
+    assert_eq!(
+        command(&mut session, "mode money eur").unwrap(),
+        shown("Money mode in EUR")
+    );
+    assert_eq!(session.evaluator().money(), Currency::new("EUR"));
+    assert_eq!(
+        command(&mut session, "mode plain").unwrap(),
+        shown("Money mode off")
+    );
+    assert_eq!(session.evaluator().money(), None);
+
+    for (input, error) in [
+        ("prec two", ":prec expects a whole number"),
+        ("mode money dollars", "Unknown currency: dollars"),
+        (
+            "mode money USD EUR",
+            ":mode expects money [CURRENCY] or plain",
+        ),
+        ("frobnicate", "Unknown command :frobnicate (try :help)"),
+    ] {
+        let result = command(&mut session, input);
+        assert_eq!(result.unwrap_err().to_string(), error, "{}", input);
+    }
+    assert!(command(&mut session, "round sideways").is_err());
+    assert!(command(&mut session, "mode deg").is_err());
+    // A failed command leaves the settings as they were
+    assert_eq!(session.evaluator().rounding().rounding, Rounding::Floor);
+}
+
+#[test]
+fn test_session_commands() {
+    let mut session = Session::default();
+    assert_eq!(
+        command(&mut session, "vars").unwrap(),
+        shown("No variables defined")
+    );
+    let entry = session.run("width = 3").unwrap().unwrap();
+    assert_eq!(show(&session, &entry), "width = 3");
+    session.run("area = width * 4").unwrap();
+    assert_eq!(
+        command(&mut session, "vars").unwrap(),
+        shown("area = 12\nwidth = 3")
+    );
+    assert_eq!(
+        command(&mut session, "reset").unwrap(),
+        shown("Variables cleared")
+    );
+    assert!(session.variables().next().is_none());
+
+    assert!(
+        matches!(command(&mut session, "help").unwrap(), Reply::Show(text) if text.contains(":vars"))
+    );
+    for quit in ["q", "quit", "exit"] {
+        assert_eq!(command(&mut session, quit).unwrap(), Reply::Quit);
+    }
+}
+
+#[test]
+fn test_cursor_edits() {
+    let mut editor = typed("12 + 34");
+    assert_eq!(editor.cursor(), 7);
+    editor.home();
+    editor.insert('(');
+    editor.end();
+    editor.insert(')');
+    assert_eq!(editor.line(), "(12 + 34)");
+
+    editor.left();
+    editor.left();
+    editor.backspace();
+    assert_eq!((editor.line().as_str(), editor.cursor()), ("(12 + 4)", 6));
+    editor.delete();
+    assert_eq!(editor.line(), "(12 + )");
+    editor.right();
+    editor.right();
+    // Moving and deleting past either end does nothing
+    editor.delete();
+    assert_eq!((editor.line().as_str(), editor.cursor()), ("(12 + )", 7));
+    editor.home();
+    editor.left();
+    editor.backspace();
+    assert_eq!((editor.line().as_str(), editor.cursor()), ("(12 + )", 0));
+}
+
+#[test]
+fn test_deleting_words_and_ends() {
+    let mut editor = typed("price * rate  ");
+    editor.delete_word();
+    assert_eq!(editor.line(), "price * ");
+    editor.delete_word();
+    assert_eq!(editor.line(), "price ");
+
+    let mut editor = typed("price * rate");
+    (0..4).for_each(|_| editor.left());
+    editor.delete_to_end();
+    assert_eq!(editor.line(), "price * ");
+    (0..2).for_each(|_| editor.left());
+    editor.delete_to_start();
+    assert_eq!((editor.line().as_str(), editor.cursor()), ("* ", 0));
+    editor.delete_word();
+    assert_eq!(editor.line(), "* ");
+}
+
+#[test]
+fn test_history() {
+    let mut editor = LineEditor::new();
+    for line in ["1 + 1", "   ", "x = 2", "x = 2", "x * 3"] {
+        line.chars().for_each(|c| editor.insert(c));
+        assert_eq!(editor.finish(), line);
+        assert!(editor.is_empty());
+    }
+    // Blank lines and repeats are not kept
+    assert_eq!(editor.history(), ["1 + 1", "x = 2", "x * 3"]);
+
+    // Up recalls older lines and stops at the oldest; Down returns to the unfinished line
+    "x -".chars().for_each(|c| editor.insert(c));
+    editor.older();
+    assert_eq!((editor.line().as_str(), editor.cursor()), ("x * 3", 5));
+    editor.older();
+    editor.older();
+    editor.older();
+    assert_eq!(editor.line(), "1 + 1");
+    editor.newer();
+    assert_eq!(editor.line(), "x = 2");
+    editor.newer();
+    editor.newer();
+    assert_eq!(editor.line(), "x -");
+    editor.newer();
+    assert_eq!(editor.line(), "x -");
+
+    // Entering a recalled line adds it again and starts below the history
+    editor.older();
+    editor.older();
+    assert_eq!(editor.finish(), "x = 2");
+    assert_eq!(editor.history(), ["1 + 1", "x = 2", "x * 3", "x = 2"]);
+    editor.newer();
+    assert!(editor.is_empty());
+    editor.older();
+    assert_eq!(editor.line(), "x = 2");
+
+    // Cancelling discards the line and the draft
+    editor.cancel();
+    assert!(editor.is_empty());
+    editor.older();
+    editor.newer();
+    assert!(editor.is_empty());
+
+    editor.remember("2 ^ 10");
+    editor.older();
+    assert_eq!(editor.line(), "2 ^ 10");
+}
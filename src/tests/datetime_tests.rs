@@ -0,0 +1,56 @@
+use crate::datetime::{self, TimeUnit};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_date_round_trip() {
+    assert_eq!(datetime::date(1970, 1, 1), Some(0));
+    assert_eq!(datetime::date(2000, 3, 1), Some(11_017));
+    for days in [-719_162, -1, 0, 59, 11_016, 20_742, 2_932_896] {
+        let (year, month, day) = datetime::civil(days);
+        assert_eq!(datetime::date(year, month, day), Some(days));
+    }
+}
+
+#[test]
+fn test_invalid_dates() {
+    assert_eq!(datetime::date(2026, 2, 29), None);
+    assert!(datetime::date(2024, 2, 29).is_some());
+    assert_eq!(datetime::date(1900, 2, 29), None);
+    assert!(datetime::date(2000, 2, 29).is_some());
+    assert_eq!(datetime::date(2026, 13, 1), None);
+    assert_eq!(datetime::date(2026, 4, 31), None);
+    assert_eq!(datetime::date(0, 1, 1), None);
+    assert_eq!(datetime::time(24, 0, 0), None);
+    assert_eq!(datetime::time(23, 59, 59), Some(86_399));
+}
+
+#[test]
+fn test_weekday() {
+    // 1970-01-01 was a Thursday, 2026-10-16 is a Friday
+    assert_eq!(datetime::weekday(0), 4);
+    assert_eq!(datetime::weekday(-1), 3);
+    let days = datetime::date(2026, 10, 16).unwrap();
+    assert_eq!(datetime::weekday(days), 5);
+    assert_eq!(datetime::format_date_with_weekday(days), "Fri 2026-10-16");
+}
+
+#[test]
+fn test_format_duration() {
+    let format = |s: Decimal| datetime::format_duration(s, |s| s.to_string());
+    assert_eq!(format(dec!(273600)), "3d 4h");
+    assert_eq!(format(dec!(5400)), "1h 30min");
+    assert_eq!(format(dec!(90.5)), "1min 30.5s");
+    assert_eq!(format(Decimal::ZERO), "0s");
+    assert_eq!(format(dec!(-86400)), "-1d");
+    assert_eq!(format(dec!(-90000)), "-(1d 1h)");
+}
+
+#[test]
+fn test_time_unit_names() {
+    assert_eq!(TimeUnit::from_name("min"), Some(TimeUnit::Minute));
+    assert_eq!(TimeUnit::from_name("hours"), Some(TimeUnit::Hour));
+    assert_eq!(TimeUnit::from_name("wk"), Some(TimeUnit::Week));
+    assert_eq!(TimeUnit::from_name("months"), None);
+    assert_eq!(TimeUnit::Week.seconds(), dec!(604800));
+}
@@ -0,0 +1,177 @@
+use crate::decimal_math::{
+    acosh, asinh, atanh, cbrt, cosh, coth, csch, exp, ln, root, sech, sinh, sqrt, tanh,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+/// Asserts agreement with a high-precision reference value to within `1e-24` relative error.
+fn assert_close(actual: Decimal, expected: Decimal) {
+    let tolerance = (expected.abs() * dec!(1e-24)).max(dec!(1e-27));
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn test_exp() {
+    assert_eq!(exp(Decimal::ZERO).unwrap(), Decimal::ONE);
+    assert_close(
+        exp(dec!(-30)).unwrap(),
+        dec!(0.0000000000000935762296884017),
+    );
+    assert_close(exp(dec!(66)).unwrap(), dec!(46071866343312915426773184428));
+    assert_eq!(exp(dec!(-100)).unwrap(), Decimal::ZERO);
+    assert!(exp(dec!(67)).is_err());
+}
+
+#[test]
+fn test_ln() {
+    assert_eq!(ln(Decimal::ONE).unwrap(), Decimal::ZERO);
+    assert_close(
+        ln(dec!(1e-20)).unwrap(),
+        dec!(-46.051701859880913680359829094),
+    );
+    assert_close(
+        ln(dec!(123456.789)).unwrap(),
+        dec!(11.723646487185880981139958984),
+    );
+    assert_close(
+        ln(dec!(1e28)).unwrap(),
+        dec!(64.472382603833279152503760731),
+    );
+    assert!(ln(Decimal::ZERO).is_err());
+    assert!(ln(dec!(-1)).is_err());
+}
+
+#[test]
+fn test_sinh_cosh_tanh() {
+    assert_eq!(sinh(Decimal::ZERO).unwrap(), Decimal::ZERO);
+    assert_close(sinh(dec!(1)).unwrap(), dec!(1.1752011936438014568823818506));
+    assert_close(
+        sinh(dec!(0.001)).unwrap(),
+        dec!(0.0010000001666666750000001984),
+    );
+    assert_close(
+        sinh(dec!(-2.5)).unwrap(),
+        dec!(-6.0502044810397873214503236384),
+    );
+    assert_close(sinh(dec!(60)).unwrap(), dec!(57100369490784214183147859.16));
+    assert_eq!(cosh(Decimal::ZERO).unwrap(), Decimal::ONE);
+    assert_close(cosh(dec!(3)).unwrap(), dec!(10.067661995777765841953936035));
+    assert_close(
+        tanh(dec!(-0.3)).unwrap(),
+        dec!(-0.2913126124515909058182212728),
+    );
+    assert_close(
+        tanh(dec!(20)).unwrap(),
+        dec!(0.9999999999999999915032914894),
+    );
+    assert_eq!(tanh(dec!(1000)).unwrap(), Decimal::ONE);
+    assert!(cosh(dec!(100)).is_err());
+}
+
+#[test]
+fn test_reciprocal_functions() {
+    assert_close(sech(dec!(2)).unwrap(), dec!(0.2658022288340796921208627398));
+    assert_close(csch(dec!(2)).unwrap(), dec!(0.2757205647717832077583514822));
+    assert_close(coth(dec!(2)).unwrap(), dec!(1.0373147207275480958778097648));
+    assert_eq!(sech(dec!(1000)).unwrap(), Decimal::ZERO);
+    assert!(csch(Decimal::ZERO).is_err());
+    assert!(coth(Decimal::ZERO).is_err());
+}
+
+#[test]
+fn test_inverse_functions() {
+    assert_close(
+        asinh(dec!(1)).unwrap(),
+        dec!(0.8813735870195430252326093250),
+    );
+    assert_close(
+        asinh(dec!(-0.25)).unwrap(),
+        dec!(-0.2474664615472634529447815498),
+    );
+    assert_close(
+        asinh(dec!(1e12)).unwrap(),
+        dec!(28.324168296488493517633129828),
+    );
+    assert_close(
+        acosh(dec!(1.5)).unwrap(),
+        dec!(0.9624236501192068949955178268),
+    );
+    assert_close(
+        acosh(dec!(1e15)).unwrap(),
+        dec!(35.231923575470630569687103942),
+    );
+    assert_eq!(acosh(Decimal::ONE).unwrap(), Decimal::ZERO);
+    assert_close(
+        atanh(dec!(0.5)).unwrap(),
+        dec!(0.5493061443340548456976226185),
+    );
+    assert_close(
+        atanh(dec!(0.9)).unwrap(),
+        dec!(1.4722194895832202300045137159),
+    );
+    assert_close(
+        atanh(dec!(-0.1)).unwrap(),
+        dec!(-0.1003353477310755806357265521),
+    );
+}
+
+#[test]
+fn test_domain_errors() {
+    let err = acosh(dec!(0.5)).unwrap_err().to_string();
+    assert!(err.contains("acosh is only defined for x >= 1"));
+    let err = atanh(Decimal::ONE).unwrap_err().to_string();
+    assert!(err.contains("atanh is only defined for -1 < x < 1"));
+}
+
+#[test]
+fn test_roots() {
+    assert_eq!(sqrt(dec!(2)).unwrap(), dec!(1.4142135623730950488016887242));
+    assert_close(cbrt(dec!(2)).unwrap(), dec!(1.2599210498948731647672106073));
+    assert_close(
+        root(dec!(10), 7).unwrap(),
+        dec!(1.3894954943731376371299852174),
+    );
+    assert_close(
+        sqrt(dec!(0.000000000000000000000000002)).unwrap(),
+        dec!(0.0000000000000447213595499958),
+    );
+    // Inputs beyond the range of an exact f64 conversion
+    assert_close(
+        sqrt(Decimal::MAX).unwrap(),
+        dec!(281474976710655.9999999999999),
+    );
+    assert_eq!(cbrt(dec!(-27)).unwrap(), dec!(-3));
+    assert_eq!(sqrt(Decimal::ZERO).unwrap(), Decimal::ZERO);
+    assert!(sqrt(dec!(-4)).is_err());
+    assert!(root(dec!(-16), 4).is_err());
+    assert!(root(dec!(16), 0).is_err());
+}
+
+#[test]
+fn test_exact_roots() {
+    let cases = [
+        (dec!(16), 2, dec!(4)),
+        (dec!(2.25), 2, dec!(1.5)),
+        (dec!(0.0001), 2, dec!(0.01)),
+        (dec!(1e28), 2, dec!(1e14)),
+        (dec!(15241578750190521), 2, dec!(123456789)),
+        (dec!(0.001), 3, dec!(0.1)),
+        (dec!(32), 5, dec!(2)),
+    ];
+    for (x, n, expected) in cases {
+        let result = root(x, n).unwrap();
+        assert_eq!(result, expected, "root({}, {})", x, n);
+        assert_eq!(
+            result.to_string(),
+            expected.to_string(),
+            "root({}, {})",
+            x,
+            n
+        );
+    }
+}
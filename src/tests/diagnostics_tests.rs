@@ -0,0 +1,105 @@
+use crate::diagnostics::{Diagnostic, caret_line, closest};
+use crate::evaluator::diagnose;
+
+/// Returns the message and spanned text of each diagnostic for `input`.
+fn problems(input: &str) -> Vec<(String, &str)> {
+    diagnose(input)
+        .into_iter()
+        .map(|d| (d.message, &input[d.span]))
+        .collect()
+}
+
+#[test]
+fn test_valid_input_has_no_diagnostics() {
+    for input in [
+        "",
+        "2 + 3 * (4 - 1)",
+        "-(2 + 3)",
+        "gcd(12, 18)",
+        "3d 4h",
+        "2026-10-16 14:30 + 1h",
+        "5! + 1",
+        "$100 in EUR",
+    ] {
+        assert_eq!(diagnose(input), Vec::<Diagnostic>::new(), "{}", input);
+    }
+}
+
+#[test]
+fn test_collects_all_problems() {
+    assert_eq!(
+        problems("(2 + * 3))"),
+        vec![
+            ("Missing operand before '*'".to_string(), "*"),
+            ("Unmatched ')'".to_string(), ")"),
+        ]
+    );
+    assert_eq!(
+        problems("sqrtt(4) + (1 +"),
+        vec![
+            ("Unknown function: sqrtt".to_string(), "sqrtt"),
+            ("Unclosed '('".to_string(), "("),
+            ("Expression ends with operator '+'".to_string(), "+"),
+        ]
+    );
+    assert_eq!(
+        problems("2 @ 3 4"),
+        vec![
+            ("Unexpected character '@'".to_string(), "@"),
+            ("Missing operator before '4'".to_string(), ""),
+        ]
+    );
+}
+
+#[test]
+fn test_other_problems() {
+    let cases = [
+        ("sqrt 4", "Expected '(' after sqrt"),
+        ("1, 2", "Unexpected ',' outside of a function call"),
+        ("gcd(, 2)", "Missing operand before ','"),
+        ("()", "Missing operand before ')'"),
+        ("2026-13-01", "Invalid date, time or duration: 2026-13-01"),
+        ("3d in fortnights", "Unknown unit or currency: fortnights"),
+        ("* 2", "Missing operand before '*'"),
+        ("rate * 2", "Unknown name: rate"),
+        ("rate (2)", "Unknown function: rate"),
+        ("w", "'w' is reserved as a duration unit"),
+        ("min * 3", "'min' is reserved as a duration unit"),
+    ];
+    for (input, expected) in cases {
+        let messages: Vec<String> = diagnose(input).into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, vec![expected.to_string()], "{}", input);
+    }
+}
+
+#[test]
+fn test_suggestions() {
+    let problems = diagnose("flor(2.5)");
+    assert_eq!(
+        problems[0].suggestion.as_deref(),
+        Some("did you mean 'floor'?")
+    );
+    assert_eq!(
+        problems[0].to_string(),
+        "Unknown function: flor (did you mean 'floor'?)"
+    );
+    assert_eq!(
+        diagnose("2 * flor")[0].to_string(),
+        "Unknown name: flor (did you mean 'floor'?)"
+    );
+    assert_eq!(diagnose("xyzzy(1)")[0].suggestion, None);
+    assert_eq!(closest("cosj", ["cosh", "sinh"]), Some("cosh"));
+    assert_eq!(closest("banana", ["cosh", "sinh"]), None);
+}
+
+#[test]
+fn test_caret_line() {
+    let input = "(2 + * 3))";
+    assert_eq!(caret_line(input, &diagnose(input)), "     ^   ^");
+    let input = "1 +";
+    assert_eq!(caret_line(input, &diagnose(input)), "  ^");
+    let missing = Diagnostic::new("Missing", 4..4);
+    assert_eq!(caret_line("1 ± 2", &[missing]), "   ^");
+    let end = Diagnostic::new("At end", 3..3);
+    assert_eq!(caret_line("abc", &[end]), "   ^");
+}
@@ -0,0 +1,73 @@
+use crate::engine::{EvalError, Evaluator};
+use crate::limits::{LimitError, Limits};
+use crate::money::{Currency, RateTable};
+use crate::rounding::{Precision, Rounding};
+use crate::value::Value;
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_eval_and_format() {
+    let evaluator = Evaluator::new();
+    let value = evaluator.eval("(3 + 4) * 2").unwrap();
+    assert_eq!(value, Value::Number(dec!(14)));
+    assert_eq!(evaluator.format(&value), "14");
+    let computed = evaluator.compute("1 / 3").unwrap();
+    assert_eq!(evaluator.format_computed(&computed), "≈ 0.3333333333");
+}
+
+#[test]
+fn test_builder_options() {
+    let evaluator = Evaluator::builder()
+        .precision(Precision::SignificantDigits(3))
+        .rounding(Rounding::Ceiling)
+        .build();
+    let value = evaluator.eval("2 / 3").unwrap();
+    assert_eq!(evaluator.format(&value), "0.667");
+
+    let mut rates = RateTable::new();
+    rates.insert(
+        Currency::new("USD").unwrap(),
+        Currency::new("EUR").unwrap(),
+        dec!(0.5),
+    );
+    let evaluator = Evaluator::builder()
+        .money(Currency::new("USD").unwrap())
+        .rates(rates)
+        .now(0)
+        .build();
+    assert_eq!(
+        evaluator.format(&evaluator.eval("10 in EUR").unwrap()),
+        "€5.00"
+    );
+    assert_eq!(
+        evaluator.format(&evaluator.eval("today").unwrap()),
+        "Thu 1970-01-01"
+    );
+}
+
+#[test]
+fn test_errors_are_classified() {
+    let evaluator = Evaluator::builder()
+        .limits(Limits {
+            max_input_length: 10,
+            ..Limits::default()
+        })
+        .build();
+    assert!(matches!(
+        evaluator.eval("1 + 2 + 3 + 4"),
+        Err(EvalError::Limit(LimitError::InputTooLong { .. }))
+    ));
+    match evaluator.eval("2 +* 3)") {
+        Err(EvalError::Syntax(diagnostics)) => assert!(!diagnostics.is_empty()),
+        other => panic!("expected syntax errors, got {:?}", other),
+    }
+    assert_eq!(
+        evaluator.eval("1 / 0"),
+        Err(EvalError::Evaluation("division by zero".to_string()))
+    );
+    let kinds: Vec<&str> = ["1 + 2 + 3 + 4", "2 +* 3)", "1 / 0"]
+        .iter()
+        .map(|input| evaluator.eval(input).unwrap_err().kind())
+        .collect();
+    assert_eq!(kinds, ["limit", "syntax", "evaluation"]);
+}
@@ -1,8 +1,12 @@
 use crate::evaluator::{evaluate, tokenize};
+use crate::session::Session;
+use crate::value::Value;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::FromPrimitive;
+use rust_decimal_macros::dec;
 
-fn assert_decimal_eq(result: Decimal, expected: f64) {
+fn assert_decimal_eq(result: Value, expected: f64) {
+    let result = result.as_number().expect("expected an exact number");
     let expected_decimal = Decimal::from_f64(expected).unwrap();
     assert!((result - expected_decimal).abs() < Decimal::new(1, 10));
 }
@@ -28,6 +32,41 @@ fn test_unary_minus() {
     assert_decimal_eq(result, -3.0);
 }
 
+#[test]
+fn test_unary_minus_negates_whole_operand() {
+    for (input, expected) in [
+        ("2^-(2)", 0.25),
+        ("2^-(-2)", 4.0),
+        ("2^-sqrt(4)", 0.25),
+        ("-(1 + 2) * 3", -9.0),
+        ("- -(2)", 2.0),
+        ("-(3)!", -6.0),
+        // Every spelling of a negated base is squared as a whole, as `-3^2` is
+        ("-3^2", 9.0),
+        ("-(3)^2", 9.0),
+        ("-(3^2)", -9.0),
+    ] {
+        let result = evaluate(&tokenize(input).unwrap()).unwrap();
+        assert_eq!(result.as_number(), Decimal::from_f64(expected), "{}", input);
+    }
+
+    let mut session = Session::default();
+    session.run("x = 2").unwrap();
+    for (input, expected) in [
+        ("2^-x", dec!(0.25)),
+        ("-x^2", dec!(4)),
+        ("-x * 3", dec!(-6)),
+    ] {
+        let entry = session.run(input).unwrap().unwrap();
+        assert_eq!(
+            entry.computed.value.as_number(),
+            Some(expected),
+            "{}",
+            input
+        );
+    }
+}
+
 #[test]
 fn test_multiplication() {
     let tokens = tokenize("4 * 3").unwrap();
@@ -82,7 +121,7 @@ fn test_division_by_zero() {
 fn test_non_integer_exponent() {
     let tokens = tokenize("2 ^ 0.5").unwrap();
     let result = evaluate(&tokens).unwrap();
-    assert_decimal_eq(result, 1.4142135623730951);
+    assert_decimal_eq(result, std::f64::consts::SQRT_2);
 }
 
 #[test]
@@ -119,7 +158,7 @@ fn test_large_number_precision() {
     let result = evaluate(&tokens).unwrap();
     let expected = Decimal::from_i128(999999999999i128).unwrap()
         * Decimal::from_i128(999999999999i128).unwrap();
-    assert_eq!(result, expected);
+    assert_eq!(result, Value::Number(expected));
 }
 
 #[test]
@@ -170,6 +209,49 @@ fn test_negative_sqrt() {
     );
 }
 
+#[test]
+fn test_roots_at_full_precision() {
+    use rust_decimal_macros::dec;
+
+    let cases = [
+        ("sqrt(2)", dec!(1.41421356237309504880168872)),
+        ("sqrt(2.25)", dec!(1.5)),
+        ("sqrt(79228162514264337593543950335)", dec!(281474976710656)),
+        ("cbrt(-8)", dec!(-2)),
+        ("root(1024, 10)", dec!(2)),
+        ("root(-32, 5)", dec!(-2)),
+    ];
+    for (input, expected) in cases {
+        let result = evaluate(&tokenize(input).unwrap()).unwrap();
+        assert_eq!(result, Value::Number(expected), "{}", input);
+    }
+    for (input, expected) in [
+        (
+            "root(-16, 4)",
+            "Cannot compute an even root of a negative number",
+        ),
+        ("root(16, 0)", "root requires a positive degree"),
+        ("root(16, 1.5)", "root requires integer arguments"),
+    ] {
+        let err = evaluate(&tokenize(input).unwrap()).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", input, err);
+    }
+}
+
+#[test]
+fn test_non_integer_factorial() {
+    // Constant factorials are already computed while tokenizing
+    let result = tokenize("2.5!").and_then(|tokens| evaluate(&tokens));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Factorial requires an integer"
+    );
+    assert_eq!(
+        evaluate(&tokenize("2.0!").unwrap()).unwrap(),
+        Value::Number(Decimal::TWO)
+    );
+}
+
 #[test]
 fn test_negative_factorial() {
     let tokens = tokenize("(-5)!").unwrap();
@@ -189,3 +271,540 @@ fn test_decimal_modulo() {
     let result = evaluate(&tokens).unwrap();
     assert_decimal_eq(result, 1.5);
 }
+
+#[test]
+fn test_number_theory_functions() {
+    let cases = [
+        ("gcd(48, 18)", 6.0),
+        ("lcm(4, 6)", 12.0),
+        ("is_prime(97)", 1.0),
+        ("is_prime(91)", 0.0),
+        ("next_prime(100)", 101.0),
+        ("totient(36)", 12.0),
+        ("powmod(2, 10, 1000)", 24.0),
+        ("powmod(3, -1, 11)", 4.0),
+        ("modinv(3, 11)", 4.0),
+        ("crt(2, 3, 3, 5, 2, 7)", 23.0),
+        ("fermat(341)", 1.0),
+        ("fermat(341, 3)", 0.0),
+        ("gcd(12, 8) * 2 + 1", 9.0),
+        ("powmod(-2, 3, 5)", 2.0),
+    ];
+    for (input, expected) in cases {
+        let tokens = tokenize(input).unwrap();
+        let result = evaluate(&tokens).unwrap();
+        assert_decimal_eq(result, expected);
+    }
+}
+
+#[test]
+fn test_function_argument_errors() {
+    let cases = [
+        ("gcd(1.5, 2)", "requires integer arguments"),
+        ("gcd(4)", "expects 2 arguments"),
+        ("modinv(4, 8)", "has no inverse"),
+        ("crt(1, 4, 2, 6)", "No solution"),
+        ("(1, 2)", "Unexpected ','"),
+        ("gcd(1, )", "missing operand"),
+    ];
+    for (input, expected) in cases {
+        let tokens = tokenize(input).unwrap();
+        let err = evaluate(&tokens).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", input, err);
+    }
+}
+
+#[test]
+fn test_unknown_function() {
+    let err = tokenize("frobnicate(2)").unwrap_err().to_string();
+    assert!(err.contains("Unknown function: frobnicate"));
+}
+
+#[test]
+fn test_hyperbolic_functions() {
+    let tokens = tokenize("cosh(1) + sinh(1)").unwrap();
+    let result = evaluate(&tokens).unwrap();
+    assert_decimal_eq(result, std::f64::consts::E);
+
+    let tokens = tokenize("tanh(atanh(0.5))").unwrap();
+    let result = evaluate(&tokens).unwrap();
+    assert_decimal_eq(result, 0.5);
+
+    let tokens = tokenize("acosh(0)").unwrap();
+    let err = evaluate(&tokens).unwrap_err().to_string();
+    assert!(err.contains("acosh is only defined"));
+}
+
+#[test]
+fn test_rounding_functions() {
+    let cases = [
+        ("round(2.5)", 2.0),
+        ("round(1.23456, 2)", 1.23),
+        ("round(1250, -2)", 1200.0),
+        ("floor(-2.5)", -3.0),
+        ("ceil(2.01)", 3.0),
+        ("trunc(-2.7)", -2.0),
+        ("floor(2.678, 1)", 2.6),
+        ("frac(-2.75)", -0.75),
+    ];
+    for (input, expected) in cases {
+        let tokens = tokenize(input).unwrap();
+        let result = evaluate(&tokens).unwrap();
+        assert_decimal_eq(result, expected);
+    }
+}
+
+#[test]
+fn test_rounding_context_applies_to_division_and_round() {
+    use crate::evaluator::{EvalOptions, evaluate_with};
+    use crate::rounding::{Precision, Rounding, RoundingContext};
+
+    let ctx = RoundingContext {
+        precision: Precision::DecimalPlaces(2),
+        rounding: Rounding::HalfUp,
+    };
+    let options = |rounding| EvalOptions {
+        rounding,
+        ..Default::default()
+    };
+    let tokens = tokenize("round(2.5)").unwrap();
+    assert_eq!(
+        evaluate_with(&tokens, &options(ctx)).unwrap(),
+        Value::Number(Decimal::from(3))
+    );
+
+    let ctx = RoundingContext {
+        rounding: Rounding::Floor,
+        ..ctx
+    };
+    let tokens = tokenize("-2 / 3").unwrap();
+    let result = evaluate_with(&tokens, &options(ctx)).unwrap();
+    assert_eq!(result.format(&ctx), "-0.67");
+}
+
+#[test]
+fn test_uncertainty_propagation() {
+    let cases = [
+        ("3 ± 0.1", "3.00 ± 0.10"),
+        ("3 +- 0.1", "3.00 ± 0.10"),
+        ("(3 ± 0.3) + (4 ± 0.4)", "7.00 ± 0.50"),
+        ("2 * 3 ± 0.1", "6.00 ± 0.20"),
+        ("(10 ± 0.5) / (2 ± 0.1)", "5.00 ± 0.35"),
+        ("sqrt(16 ± 0.8)", "4.00 ± 0.10"),
+        ("100 ± 12 + 0.004", "100 ± 12"),
+    ];
+    for (input, expected) in cases {
+        let tokens = tokenize(input).unwrap();
+        let result = evaluate(&tokens).unwrap();
+        assert_eq!(result.format(&Default::default()), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_interval_arithmetic() {
+    let cases = [
+        ("interval(1, 2) + interval(10, 20)", "[11, 22]"),
+        ("interval(1, 2) - interval(10, 20)", "[-19, -8]"),
+        ("interval(-1, 2) * interval(3, 4)", "[-4, 8]"),
+        ("1 / interval(2, 4)", "[0.25, 0.5]"),
+        ("interval(-2, 3) ^ 2", "[0, 9]"),
+        ("abs(interval(-2, 1))", "[0, 2]"),
+        ("frac(interval(1.25, 1.5))", "[0.25, 0.5]"),
+        ("frac(interval(1.5, 2.5))", "[0, 1]"),
+        ("1 / interval(1, 3)", "[0.3333333333, 1]"),
+    ];
+    for (input, expected) in cases {
+        let tokens = tokenize(input).unwrap();
+        let result = evaluate(&tokens).unwrap();
+        assert_eq!(result.format(&Default::default()), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_interval_and_uncertainty_errors() {
+    let cases = [
+        ("1 / interval(-1, 1)", "division by zero"),
+        ("interval(1, 2) + 3 ± 1", "Cannot combine an interval"),
+        ("gcd(4 ± 1, 2)", "requires exact arguments"),
+        ("(3 ± 1) ± 1", "± requires exact operands"),
+        ("3 ± -1", "must not be negative"),
+    ];
+    for (input, expected) in cases {
+        let tokens = tokenize(input).unwrap();
+        let err = evaluate(&tokens).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", input, err);
+    }
+}
+
+/// Evaluates `input` with the clock fixed at 2026-10-18 09:15 UTC.
+fn evaluate_at_fixed_time(input: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    use crate::evaluator::{EvalOptions, evaluate_with};
+
+    let today = crate::datetime::date(2026, 10, 18).unwrap();
+    let options = EvalOptions {
+        now: Some(today * 86_400 + 9 * 3_600 + 15 * 60),
+        ..Default::default()
+    };
+    evaluate_with(&tokenize(input)?, &options)
+}
+
+#[test]
+fn test_date_arithmetic() {
+    let cases = [
+        ("2026-10-16", "Fri 2026-10-16"),
+        ("2026-10-16 + 45 days", "Mon 2026-11-30"),
+        ("2028-03-01 - 1d", "Tue 2028-02-29"),
+        ("2026-10-16 + 36h", "Sat 2026-10-17 12:00"),
+        ("2026-10-16 14:30", "Fri 2026-10-16 14:30"),
+        ("2026-10-16 + 14:30", "Fri 2026-10-16 14:30"),
+        ("2026-12-25 - 2026-10-16", "70d"),
+        ("(2026-12-25 - today) in weeks", "9.7142857143 weeks"),
+        ("today", "Sun 2026-10-18"),
+        ("now + 90min", "Sun 2026-10-18 10:45"),
+        ("now - 2026-10-18", "9h 15min"),
+        ("weekday(2026-10-16)", "5"),
+        ("weekday(now)", "7"),
+    ];
+    for (input, expected) in cases {
+        let result = evaluate_at_fixed_time(input).unwrap();
+        assert_eq!(result.format(&Default::default()), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_time_and_duration_arithmetic() {
+    let cases = [
+        ("3d 4h", "3d 4h"),
+        ("90min", "1h 30min"),
+        ("14:30 + 45min", "15:15"),
+        ("23:30 + 1h", "00:30"),
+        ("17:00 - 9:15", "7h 45min"),
+        ("2 * 3d 4h", "6d 8h"),
+        ("(1 + 2) days", "3d"),
+        ("-(1d 2h)", "-(1d 2h)"),
+        ("1w / 2", "3d 12h"),
+        ("1w / 1d", "7"),
+        ("90min in hours", "1.5 hours"),
+        ("24h in days", "1 day"),
+        ("1.5h", "1h 30min"),
+    ];
+    for (input, expected) in cases {
+        let result = evaluate_at_fixed_time(input).unwrap();
+        assert_eq!(result.format(&Default::default()), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_date_and_time_errors() {
+    let cases = [
+        ("2026-02-30", "Invalid date, time or duration: 2026-02-30"),
+        ("25:00", "Invalid date, time or duration: 25:00"),
+        (
+            "2026-10-16 + 2026-10-17",
+            "Cannot apply addition to a date and a date",
+        ),
+        ("3d + 1", "Cannot apply addition to a duration and a number"),
+        (
+            "today * 2",
+            "Cannot apply multiplication to a date and a number",
+        ),
+        ("5 in days", "Cannot convert a number to days"),
+        ("sinh(3d)", "Expected a number, got a duration"),
+        ("weekday(3)", "weekday expects a date, got a number"),
+        ("9999-12-31 + 1d", "Date out of range"),
+        ("1d / 0", "division by zero"),
+    ];
+    for (input, expected) in cases {
+        let err = evaluate_at_fixed_time(input).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", input, err);
+    }
+}
+
+#[test]
+fn test_subtraction_without_spaces() {
+    let tokens = tokenize("5-3").unwrap();
+    assert_decimal_eq(evaluate(&tokens).unwrap(), 2.0);
+    let tokens = tokenize("-(2+3)").unwrap();
+    assert_decimal_eq(evaluate(&tokens).unwrap(), -5.0);
+}
+
+/// Evaluates `input` in money mode for `currency`, with a small rate table.
+fn evaluate_money(
+    input: &str,
+    currency: Option<&str>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    use crate::evaluator::{EvalOptions, evaluate_with};
+    use crate::money::{Currency, RateTable};
+
+    let rates = RateTable::parse("USD EUR 0.92\nUSD JPY 150").unwrap();
+    let options = EvalOptions {
+        money: currency.and_then(Currency::new),
+        rates: Some(&rates),
+        ..Default::default()
+    };
+    evaluate_with(&tokenize(input)?, &options)
+}
+
+#[test]
+fn test_money_arithmetic() {
+    let cases = [
+        ("$1234.5", "$1,234.50"),
+        ("12.345 USD + 1", "$13.34"),
+        ("$10 * 1.075", "$10.75"),
+        ("$100 / 3", "$33.34 × 1, $33.33 × 2"),
+        ("$100 / 4", "$25.00"),
+        ("$100 / 2.5", "$40.00"),
+        ("$100 / -3", "-$33.34 × 1, -$33.33 × 2"),
+        ("-$100 / -4", "$25.00"),
+        ("$0 / -3", "$0.00"),
+        ("-$5 + 2", "-$3.00"),
+        ("$100 / $40", "$2.50"),
+        ("€5 + 5 EUR", "€10.00"),
+        ("$100 in EUR", "€92.00"),
+        ("(€46 + €46) in JPY", "¥15,000"),
+        ("100 / 3", "$33.34 × 1, $33.33 × 2"),
+        ("2 * 50 - 0.5", "$99.50"),
+        ("1000000", "$1,000,000.00"),
+        ("100 in EUR", "€92.00"),
+    ];
+    for (input, expected) in cases {
+        let result = evaluate_money(input, Some("USD")).unwrap();
+        assert_eq!(result.format(&Default::default()), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_money_errors() {
+    let cases = [
+        (
+            "$5 + 1",
+            "Cannot apply addition to a money amount and a number",
+        ),
+        ("$5 + €5", "Cannot apply addition to USD and EUR"),
+        (
+            "$5 * $5",
+            "Cannot apply multiplication to a money amount and a money amount",
+        ),
+        ("$100 / 3 + $1", "Cannot apply addition to an allocation"),
+        ("sqrt($4)", "Cannot apply square root to a money amount"),
+        ("$5 in GBP", "No exchange rate from USD to GBP"),
+        ("5 in EUR", "Cannot convert a number to EUR"),
+        ("$1 / 0", "division by zero"),
+        (
+            "$100 / 10000000000",
+            "Cannot split an amount into more than 4294967295 parts",
+        ),
+    ];
+    for (input, expected) in cases {
+        let err = evaluate_money(input, None).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", input, err);
+    }
+}
+
+#[test]
+fn test_complete_unfinished_input() {
+    use crate::evaluator::complete;
+
+    let cases = [
+        ("12 * (3 +", Some("12 * (3)")),
+        ("12 * (3 + 4", Some("12 * (3 + 4)")),
+        ("2 ^ (1 + (2 *", Some("2 ^ (1 + (2))")),
+        ("gcd(12, 18,", Some("gcd(12, 18)")),
+        ("10 + sqrt(", Some("10")),
+        ("5 -", Some("5")),
+        ("12 * (3 + 4)", None),
+        ("sqrt(", None),
+        ("(2 + * 3", None),
+        ("2 + 3)", None),
+        ("2 + flor(", None),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(complete(input).as_deref(), expected, "{}", input);
+    }
+    let completed = complete("12 * (3 +").unwrap();
+    let result = evaluate(&tokenize(&completed).unwrap()).unwrap();
+    assert_decimal_eq(result, 36.0);
+}
+
+#[test]
+fn test_overflow_is_an_error() {
+    let errors = [
+        ("123456789012345678901234567890", "Number too large"),
+        ("¥123456789012345678901234567890", "Number too large"),
+        ("79228162514264337593543950335 + 1", "Result too large"),
+        ("-79228162514264337593543950335 - 1", "Result too large"),
+        ("79228162514264337593543950335 / 0.1", "Result too large"),
+        ("0.0000000000000000000000000001 ^ -2", "too large"),
+    ];
+    for (input, expected) in errors {
+        let err = tokenize(input)
+            .and_then(|tokens| evaluate(&tokens))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(expected), "{}: {}", input, err);
+    }
+
+    // Values at the edge of the range evaluate and display without overflowing
+    let ctx = crate::rounding::RoundingContext::default();
+    let values = [
+        "interval(-79228162514264337593543950335, 79228162514264337593543950335)",
+        "79228162514264337593543950335 ± 0.0000000000000000000000000001",
+        "79228162514264337593543950335 % 0.1",
+        "90min / 99999999999999",
+        "coth(79228162514264337593543950335)",
+        "acosh(79228162514264337593543950335)",
+    ];
+    for input in values {
+        let value = evaluate(&tokenize(input).unwrap()).unwrap();
+        assert!(!value.format(&ctx).is_empty(), "{}", input);
+    }
+}
+
+/// Random expressions built from fragments that stress overflow, precision and type errors.
+#[test]
+fn test_random_expressions_never_panic() {
+    use crate::evaluator::{EvalOptions, complete, diagnose, evaluate_with};
+    use crate::money::{Currency, RateTable};
+    use crate::rounding::RoundingContext;
+
+    const FRAGMENTS: &[&str] = &[
+        "0",
+        "1",
+        "2",
+        "-3",
+        "0.5",
+        "0.0000000000000000000000000001",
+        "79228162514264337593543950335",
+        "123456789012345678901234567890",
+        "99999999999999",
+        "1e5",
+        "+",
+        "-",
+        "*",
+        "/",
+        "%",
+        "^",
+        "!",
+        "±",
+        "(",
+        ")",
+        ",",
+        "sqrt(",
+        "cbrt(",
+        "root(",
+        "abs(",
+        "gcd(",
+        "lcm(",
+        "powmod(",
+        "modinv(",
+        "crt(",
+        "totient(",
+        "next_prime(",
+        "is_prime(",
+        "fermat(",
+        "sinh(",
+        "acosh(",
+        "atanh(",
+        "coth(",
+        "round(",
+        "interval(",
+        "weekday(",
+        "2026-10-16",
+        "9999-12-31",
+        "0001-01-01",
+        "23:59:59",
+        "3d",
+        "90min",
+        "1000000000000 weeks",
+        "today",
+        "now",
+        "$12.50",
+        "¥99999999999999999999999",
+        "5 EUR",
+        "in EUR",
+        "in JPY",
+        "in hours",
+        "days",
+        "flor(",
+        "@",
+    ];
+
+    let rates = RateTable::parse("USD EUR 0.92\nUSD JPY 150").unwrap();
+    let ctx = RoundingContext::default();
+    // A fixed seed keeps failures reproducible
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    };
+    for round in 0..5_000 {
+        let length = 1 + next(8);
+        let input = (0..length)
+            .map(|_| FRAGMENTS[next(FRAGMENTS.len())])
+            .collect::<Vec<_>>()
+            .join(" ");
+        let options = EvalOptions {
+            now: Some(1_792_300_000),
+            money: (round % 2 == 0).then(|| Currency::new("USD")).flatten(),
+            rates: Some(&rates),
+            ..Default::default()
+        };
+        let outcome = std::panic::catch_unwind(|| {
+            diagnose(&input);
+            complete(&input);
+            if let Ok(tokens) = tokenize(&input)
+                && let Ok(value) = evaluate_with(&tokens, &options)
+            {
+                value.format(&ctx);
+                value.to_string();
+            }
+        });
+        assert!(outcome.is_ok(), "panicked on {:?}", input);
+    }
+}
+
+#[test]
+fn test_exactness() {
+    use crate::evaluator::{EvalOptions, compute};
+
+    let options = EvalOptions::default();
+    let exact = [
+        "1/4",
+        "2 + 3 * 4",
+        "1.5 ^ 2",
+        "2 ^ -1",
+        "4 ^ 0.5",
+        "sqrt(16)",
+        "sqrt(2.25)",
+        "cbrt(-27)",
+        "root(32, 5)",
+        "sinh(0)",
+        "5!",
+        "gcd(12, 18)",
+        "round(2.345, 2)",
+        "$10 * 2",
+        "3h / 2",
+    ];
+    for input in exact {
+        let computed = compute(&tokenize(input).unwrap(), &options).unwrap();
+        assert!(computed.exact, "{} should be exact", input);
+    }
+    let approximate = [
+        "1/3",
+        "1/3 * 3",
+        "2 ^ 0.5",
+        "3 ^ -1",
+        "sqrt(2)",
+        "cbrt(2)",
+        "tanh(1)",
+        "(2 ± 0.1) * 2",
+        "$10 * 1.0755",
+        "$0.01 * 0.5",
+    ];
+    for input in approximate {
+        let computed = compute(&tokenize(input).unwrap(), &options).unwrap();
+        assert!(!computed.exact, "{} should be approximate", input);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::export::{latex, latex_equation, mathml, mathml_equation};
+use crate::expr::parse;
+use crate::rounding::{Precision, RoundingContext};
+use crate::value::{Computed, Value};
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_latex() {
+    let cases = [
+        ("1 + 2 * 3", r"1 + 2 \cdot 3"),
+        ("(1 + 2) * 3", r"\left( 1 + 2 \right) \cdot 3"),
+        ("1 / (x + 1)", r"\frac{1}{x + 1}"),
+        ("(1/2) * x", r"\frac{1}{2} \cdot x"),
+        ("(1/2)^2", r"\left( \frac{1}{2} \right)^{2}"),
+        ("-2^2", r"\left( -2 \right)^{2}"),
+        ("-(2^2)", r"-\left( 2^{2} \right)"),
+        ("-x * y", r"-x \cdot y"),
+        ("2^(x + 1)", r"2^{x + 1}"),
+        (
+            "sqrt(2) + cbrt(x) + root(32, 5)",
+            r"\sqrt{2} + \sqrt[3]{x} + \sqrt[5]{32}",
+        ),
+        ("abs(x - 1)", r"\left\lvert x - 1 \right\rvert"),
+        ("floor(x)", r"\left\lfloor x \right\rfloor"),
+        (
+            "gcd(12, 18) + is_prime(7)",
+            r"\gcd\left( 12, 18 \right) + \operatorname{is\_prime}\left( 7 \right)",
+        ),
+        ("5!", "5!"),
+        ("10 ± 0.5", r"10 \pm 0.5"),
+        ("7 % 3", r"7 \bmod 3"),
+        ("$12.50 * 2", r"\text{\$12.50} \cdot 2"),
+        ("(1 + 2) d", r"\left( 1 + 2 \right) \, \text{days}"),
+        ("3h in min", r"\text{3h} \text{ in } \text{minutes}"),
+        ("rate * 2", r"\mathit{rate} \cdot 2"),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(latex(&parse(input).unwrap()), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_mathml() {
+    let expr = parse("-2^2 / x").unwrap();
+    assert_eq!(
+        mathml(&expr),
+        concat!(
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML">"#,
+            "<mfrac><msup><mrow><mo>(</mo><mrow><mo>−</mo><mn>2</mn></mrow><mo>)</mo></mrow>",
+            "<mn>2</mn></msup><mi>x</mi></mfrac></math>"
+        )
+    );
+    let expr = parse("sqrt(x) * gcd(a, b)").unwrap();
+    assert_eq!(
+        mathml(&expr),
+        concat!(
+            r#"<math xmlns="http://www.w3.org/1998/Math/MathML">"#,
+            "<mrow><msqrt><mi>x</mi></msqrt><mo>⋅</mo>",
+            "<mrow><mi>gcd</mi><mo>\u{2061}</mo><mrow><mo>(</mo>",
+            "<mrow><mi>a</mi><mo>,</mo><mi>b</mi></mrow><mo>)</mo></mrow></mrow></mrow></math>"
+        )
+    );
+    assert!(mathml(&parse("today").unwrap()).contains("<mtext>today</mtext>"));
+}
+
+#[test]
+fn test_equations() {
+    let ctx = RoundingContext {
+        precision: Precision::DecimalPlaces(2),
+        ..RoundingContext::default()
+    };
+    let third = Computed {
+        value: Value::Number(dec!(1) / dec!(3)),
+        exact: false,
+    };
+    let expr = parse("1 / 3").unwrap();
+    assert_eq!(
+        latex_equation(&expr, &third, &ctx),
+        r"\frac{1}{3} \approx 0.33"
+    );
+    assert!(
+        mathml_equation(&expr, &third, &ctx).ends_with("<mo>≈</mo><mn>0.33</mn></mrow></math>")
+    );
+
+    let measured = Computed {
+        value: Value::Uncertain {
+            mean: dec!(5),
+            sigma: dec!(0.25),
+        },
+        exact: false,
+    };
+    let expr = parse("(10 ± 0.5) / 2").unwrap();
+    assert_eq!(
+        latex_equation(&expr, &measured, &ctx),
+        r"\frac{10 \pm 0.5}{2} \approx 5.00 \pm 0.25"
+    );
+}
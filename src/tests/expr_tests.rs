@@ -0,0 +1,122 @@
+use crate::expr::{Expr, parse, simplify};
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_print_shows_grouping() {
+    let cases = [
+        ("-2^2", "(-2)^2"),
+        ("-(2)^2", "(-(2))^2"),
+        ("-(2^2)", "-(2^2)"),
+        ("-x^2", "(-x)^2"),
+        ("-(x^2)", "-(x^2)"),
+        ("-x!", "-x!"),
+        ("(-x)!", "(-x)!"),
+        ("2^3^2", "2^3^2"),
+        ("(2^3)^2", "(2^3)^2"),
+        ("((1+2))*3", "(1 + 2) * 3"),
+        ("1-(2-3)", "1 - (2 - 3)"),
+        ("(1-2)-3", "1 - 2 - 3"),
+        ("2*-x", "2 * -x"),
+        ("2^-x", "2^(-x)"),
+        ("2 ^ -3", "2^-3"),
+        ("3!+x!", "3! + x!"),
+        ("(1+2)!", "(1 + 2)!"),
+        ("(10±0.5)/2", "10 ± 0.5 / 2"),
+        ("10 ± (0.5/2)", "10 ± (0.5 / 2)"),
+        ("gcd( 12 ,18 )", "gcd(12, 18)"),
+        ("sqrt(x+1)*abs(y)", "sqrt(x + 1) * abs(y)"),
+        ("2026-10-16   14:30 + 3d  4h", "2026-10-16 14:30 + 3d 4h"),
+        ("(1 + 2) d", "(1 + 2) days"),
+        ("1 + 2 in weeks", "1 + 2 in weeks"),
+        ("2 * (3h in min)", "2 * (3h in minutes)"),
+        ("$12.50*2", "$12.50 * 2"),
+    ];
+    for (input, expected) in cases {
+        let expr = parse(input).unwrap();
+        assert_eq!(expr.to_string(), expected, "{}", input);
+        // Printing is stable: reading the printed form gives the same tree
+        assert_eq!(parse(expected).unwrap(), expr, "{}", expected);
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    for input in [
+        "", "1 +", "(1 + 2", "1 2", "x y", "1 + 2)", "2 + @", "sqrt 4",
+    ] {
+        assert!(parse(input).is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn test_parse_variables_and_calls() {
+    assert_eq!(
+        parse("tax(x)").unwrap(),
+        Expr::Call("tax".to_string(), vec![Expr::Variable("x".to_string())])
+    );
+    assert_eq!(parse("-2").unwrap(), Expr::Number(dec!(-2)));
+}
+
+#[test]
+fn test_simplify() {
+    let cases = [
+        ("1 + 2 * 3", "7"),
+        ("1/4", "0.25"),
+        ("1/3", "1 / 3"),
+        ("sqrt(16) + x*1 + 0", "x + 4"),
+        ("2*x + 3*x", "5 * x"),
+        ("x - x", "0"),
+        ("x*x*x", "x^3"),
+        ("x^2 * x^3", "x^5"),
+        ("(x^2)^3", "x^6"),
+        ("x^1 + y^0", "x + 1"),
+        ("x/2 + x/2", "x"),
+        ("2*(x + 1) - 2*x", "2"),
+        ("0 - x", "-x"),
+        ("-(-x)", "x"),
+        ("x*0 + y", "y"),
+        ("(x + 1)*(x + 1)", "(x + 1)^2"),
+        ("x / (x + 1)", "x / (x + 1)"),
+        ("3*x*y - y*x", "2 * x * y"),
+        ("x^-2 * 3", "3 / x^2"),
+        ("2^0.5 * 2^0.5", "2"),
+        ("x + 1 + x - 3", "2 * x - 2"),
+        ("sqrt(x) + sqrt(x)", "2 * sqrt(x)"),
+        ("(2 * x)^2", "4 * x^2"),
+        ("x^-1 * x^-2", "1 / x^3"),
+        ("x^0.5 * 4", "4 * x^0.5"),
+    ];
+    for (input, expected) in cases {
+        let simplified = simplify(&parse(input).unwrap());
+        assert_eq!(simplified.to_string(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_simplify_keeps_where_defined() {
+    // Each of these fails for some x, so it is not rewritten into one that does not
+    let cases = [
+        ("x / x", "x / x"),
+        ("0 / x", "0 / x"),
+        ("x^0.5 * x^0.5", "x^0.5 * x^0.5"),
+        ("x^2 / x", "x^2 / x"),
+        ("(x^2)^0.5", "(x^2)^0.5"),
+        ("(x^0.5)^2", "(x^0.5)^2"),
+        ("(x * y)^0.5", "(x * y)^0.5"),
+        ("1/x - 1/x", "1 / x - 1 / x"),
+        ("0 * sqrt(x)", "0 * sqrt(x)"),
+        ("(1/x)^0", "(1 / x)^0"),
+        // Powers of a positive number are always defined
+        ("2^0.5 * 2^-0.5 * x", "x"),
+    ];
+    for (input, expected) in cases {
+        let simplified = simplify(&parse(input).unwrap());
+        assert_eq!(simplified.to_string(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_readme_example() {
+    let expr = parse("sqrt(16) + x*1 + 0 + x^2 * x").unwrap();
+    assert_eq!(simplify(&expr).to_string(), "x + x^3 + 4");
+}
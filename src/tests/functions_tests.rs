@@ -0,0 +1,138 @@
+use crate::engine::{EvalError, Evaluator};
+use crate::functions::{Function, Registry};
+use crate::value::Value;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::error::Error;
+
+/// A function defined by the arguments it takes and a closure over exact numbers.
+struct Numeric {
+    name: &'static str,
+    arity: usize,
+    f: fn(&[Decimal]) -> Decimal,
+}
+
+impl Function for Numeric {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn doc(&self) -> &str {
+        "A test function"
+    }
+
+    fn call(&self, args: &[Value]) -> Result<Value, Box<dyn Error>> {
+        Value::propagate(args, |x| Ok((self.f)(x)))
+    }
+}
+
+fn evaluator(functions: Registry) -> Evaluator {
+    Evaluator::builder().functions(functions).build()
+}
+
+fn tax() -> Numeric {
+    Numeric {
+        name: "tax",
+        arity: 1,
+        f: |x| x[0] * dec!(0.2),
+    }
+}
+
+#[test]
+fn test_custom_functions() {
+    let mut functions = Registry::new();
+    functions.insert(tax()).unwrap();
+    functions
+        .insert(Numeric {
+            name: "fx",
+            arity: 2,
+            f: |x| x[0] * x[1],
+        })
+        .unwrap();
+    let evaluator = evaluator(functions);
+    assert_eq!(
+        evaluator.eval("100 + tax(100)").unwrap(),
+        Value::Number(dec!(120.0))
+    );
+    assert_eq!(
+        evaluator.eval("fx(2, tax(10)) * 3").unwrap(),
+        Value::Number(dec!(12.0))
+    );
+    assert_eq!(
+        evaluator.eval("fx(2)"),
+        Err(EvalError::Evaluation(
+            "fx expects 2 arguments, got 1".to_string()
+        ))
+    );
+    let names: Vec<&str> = evaluator
+        .functions()
+        .functions()
+        .map(|f| f.name())
+        .collect();
+    assert_eq!(names, ["fx", "tax"]);
+}
+
+#[test]
+fn test_override_and_remove_builtins() {
+    let mut functions = Registry::new();
+    functions
+        .insert(Numeric {
+            name: "round",
+            arity: 1,
+            f: |x| x[0].ceil(),
+        })
+        .unwrap();
+    functions.remove("gcd");
+    let evaluator = evaluator(functions);
+    assert_eq!(
+        evaluator.eval("round(1.2)").unwrap(),
+        Value::Number(dec!(2))
+    );
+    match evaluator.eval("gcd(4, 6)") {
+        Err(EvalError::Syntax(diagnostics)) => {
+            assert_eq!(diagnostics[0].message, "Unknown function: gcd");
+        }
+        other => panic!("expected an unknown function, got {:?}", other),
+    }
+    assert!(evaluator.eval("lcm(4, 6)").is_ok());
+}
+
+#[test]
+fn test_registry_names() {
+    let mut functions = Registry::new();
+    assert!(
+        functions
+            .insert(Numeric {
+                name: "in",
+                ..tax()
+            })
+            .is_err()
+    );
+    assert!(
+        functions
+            .insert(Numeric {
+                name: "2x",
+                ..tax()
+            })
+            .is_err()
+    );
+    functions.insert(tax()).unwrap();
+    let diagnostics = evaluator(functions).diagnose("taxx(5)");
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "Unknown function: taxx (did you mean 'tax'?)"
+    );
+}
+
+#[test]
+fn test_uncertain_arguments_are_approximate() {
+    let mut functions = Registry::new();
+    functions.insert(tax()).unwrap();
+    let evaluator = evaluator(functions);
+    assert!(evaluator.compute("tax(10)").unwrap().exact);
+    assert!(!evaluator.compute("tax(10 ± 1)").unwrap().exact);
+}
@@ -0,0 +1,155 @@
+use crate::engine::Evaluator;
+use crate::json::{Json, SCHEMA_VERSION, evaluation};
+
+fn describe(input: &str) -> Json {
+    let evaluator = Evaluator::new();
+    evaluation(&evaluator, input, &evaluator.compute(input))
+}
+
+#[test]
+fn test_serialize() {
+    let json = Json::object([
+        ("text", Json::from("say \"hi\"\\\n\u{1}±")),
+        ("none", Json::Null),
+        (
+            "list",
+            Json::Array(vec![Json::Bool(true), Json::from(3_usize)]),
+        ),
+        ("empty", Json::object([])),
+    ]);
+    assert_eq!(
+        json.to_string(),
+        r#"{"text":"say \"hi\"\\\n\u0001±","none":null,"list":[true,3],"empty":{}}"#
+    );
+}
+
+#[test]
+fn test_evaluation_success() {
+    let json = describe("1/3");
+    assert_eq!(
+        json.to_string(),
+        format!(
+            concat!(
+                r#"{{"schema":{},"input":"1/3","normalized":"1 / 3","ok":true,"exact":false,"#,
+                r#""relation":"≈","value":{{"type":"number","exact":"0.333333333333333333333333333","#,
+                r#""formatted":"0.3333333333","unit":null}},"error":null}}"#
+            ),
+            SCHEMA_VERSION
+        )
+    );
+
+    let json = describe("2 + 3");
+    assert_eq!(json.get("exact"), Some(&Json::Bool(true)));
+    assert_eq!(json.get("relation"), Some(&Json::from("=")));
+}
+
+#[test]
+fn test_evaluation_values() {
+    let value = |input: &str| describe(input).get("value").cloned().unwrap();
+
+    let interval = value("interval(1, 2) * 2");
+    assert_eq!(interval.get("type"), Some(&Json::from("interval")));
+    assert_eq!(interval.get("lo"), Some(&Json::from("2")));
+    assert_eq!(interval.get("hi"), Some(&Json::from("4")));
+
+    let money = value("$12.50 * 2");
+    assert_eq!(money.get("type"), Some(&Json::from("money")));
+    assert_eq!(money.get("unit"), Some(&Json::from("USD")));
+    assert_eq!(money.get("amount"), Some(&Json::from("25")));
+
+    let duration = value("90 min in hours");
+    assert_eq!(duration.get("type"), Some(&Json::from("duration")));
+    assert_eq!(duration.get("unit"), Some(&Json::from("hours")));
+    assert_eq!(duration.get("seconds"), Some(&Json::from("5400")));
+}
+
+#[test]
+fn test_evaluation_errors() {
+    let json = describe("2 + * 3");
+    assert_eq!(json.get("ok"), Some(&Json::Bool(false)));
+    assert_eq!(json.get("value"), Some(&Json::Null));
+    let error = json.get("error").unwrap();
+    assert_eq!(error.get("kind"), Some(&Json::from("syntax")));
+    let Some(Json::Array(diagnostics)) = error.get("diagnostics") else {
+        panic!("no diagnostics in {}", json);
+    };
+    let span = diagnostics[0].get("span").unwrap();
+    assert_eq!(span.get("start"), Some(&Json::from(4_usize)));
+    assert_eq!(span.get("end"), Some(&Json::from(5_usize)));
+
+    // Offsets count characters, not bytes
+    let json = describe("10±0.5 + * 3");
+    let error = json.get("error").unwrap();
+    let Some(Json::Array(diagnostics)) = error.get("diagnostics") else {
+        panic!("no diagnostics in {}", json);
+    };
+    let span = diagnostics[0].get("span").unwrap();
+    assert_eq!(span.get("start"), Some(&Json::from(9_usize)));
+
+    let json = describe("1 / 0");
+    let error = json.get("error").unwrap();
+    assert_eq!(error.get("kind"), Some(&Json::from("evaluation")));
+    assert_eq!(error.get("diagnostics"), Some(&Json::Array(vec![])));
+    assert_eq!(json.get("normalized"), Some(&Json::from("1 / 0")));
+}
+
+#[test]
+fn test_parse() {
+    let json = Json::parse(
+        r#" {"a": [1, -2.5e3, true, false, null], "b": {"c": "x\"\\\/\n\u00e9\ud83d\ude00"}, "d": []} "#,
+    )
+    .unwrap();
+    assert_eq!(
+        json.get("a"),
+        Some(&Json::Array(vec![
+            Json::Number("1".to_string()),
+            Json::Number("-2.5e3".to_string()),
+            Json::Bool(true),
+            Json::Bool(false),
+            Json::Null,
+        ]))
+    );
+    assert_eq!(
+        json.get("b").and_then(|b| b.get("c")),
+        Some(&Json::from("x\"\\/\né😀"))
+    );
+    assert_eq!(json.get("d"), Some(&Json::Array(vec![])));
+
+    // Writing and reading again gives the same value
+    assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+}
+
+#[test]
+fn test_parse_errors() {
+    let cases = [
+        ("", "expected a value at character 0"),
+        ("{\"a\" 1}", "expected ':', found '1' at character 5"),
+        ("[1, 2", "unterminated array"),
+        ("{\"a\": 1,}", "expected a member name at character 8"),
+        ("\"abc", "unterminated string at character 0"),
+        ("01", "invalid number 01"),
+        ("1.", "invalid number 1."),
+        ("tru", "unexpected 't'"),
+        ("\"\\x\"", "invalid escape"),
+        ("\"\\ud800\"", "unpaired surrogate"),
+        ("[1] 2", "unexpected '2' after the value at character 4"),
+        ("\"é\" x", "at character 4"),
+    ];
+    for (text, expected) in cases {
+        let error = Json::parse(text).unwrap_err().to_string();
+        assert!(
+            error.contains(expected),
+            "{:?}: expected {:?}, got {:?}",
+            text,
+            expected,
+            error
+        );
+    }
+    let deep = "[".repeat(1000);
+    assert!(
+        Json::parse(&deep)
+            .unwrap_err()
+            .to_string()
+            .contains("too deeply nested")
+    );
+}
@@ -0,0 +1,149 @@
+use crate::evaluator::{EvalOptions, evaluate_with, tokenize, tokenize_with};
+use crate::limits::{LimitError, Limits};
+use crate::value::Value;
+use rust_decimal_macros::dec;
+use std::error::Error;
+use std::time::Duration;
+
+fn evaluate_limited(input: &str, limits: Limits) -> Result<Value, Box<dyn Error>> {
+    let options = EvalOptions {
+        limits,
+        ..Default::default()
+    };
+    evaluate_with(&tokenize_with(input, &limits)?, &options)
+}
+
+fn limit_error(result: Result<Value, Box<dyn Error>>) -> LimitError {
+    *result
+        .expect_err("expected a limit violation")
+        .downcast::<LimitError>()
+        .expect("expected a LimitError")
+}
+
+#[test]
+fn test_input_length_limit() {
+    let limits = Limits {
+        max_input_length: 5,
+        ..Limits::default()
+    };
+    assert!(evaluate_limited("1+2+3", limits).is_ok());
+    assert_eq!(
+        limit_error(evaluate_limited("1+2+34", limits)),
+        LimitError::InputTooLong {
+            length: 6,
+            limit: 5
+        }
+    );
+    // Characters are counted, not bytes
+    assert!(limits.check_input("2±0.1").is_ok());
+}
+
+#[test]
+fn test_magnitude_limit() {
+    let limits = Limits {
+        max_magnitude: dec!(1000),
+        ..Limits::default()
+    };
+    assert!(evaluate_limited("999 + 1", limits).is_ok());
+    let exceeded = LimitError::MagnitudeExceeded { limit: dec!(1000) };
+    assert_eq!(limit_error(evaluate_limited("1001", limits)), exceeded);
+    assert_eq!(limit_error(evaluate_limited("999 + 2", limits)), exceeded);
+    // Intermediate results count too, even when the final result is small
+    assert_eq!(
+        limit_error(evaluate_limited("100 * 100 / 100", limits)),
+        exceeded
+    );
+    assert_eq!(limit_error(evaluate_limited("7!", limits)), exceeded);
+    // Durations are measured in seconds, but points in time are not measured
+    assert_eq!(limit_error(evaluate_limited("1h", limits)), exceeded);
+    assert!(evaluate_limited("2026-10-16 14:30", limits).is_ok());
+}
+
+#[test]
+fn test_depth_limit() {
+    let limits = Limits {
+        max_depth: 3,
+        ..Limits::default()
+    };
+    assert!(evaluate_limited("((1 + (2)))", limits).is_ok());
+    assert_eq!(
+        limit_error(evaluate_limited("((((1))))", limits)),
+        LimitError::TooDeep { limit: 3 }
+    );
+    assert_eq!(
+        limit_error(evaluate_limited("abs(abs(abs(abs(1))))", limits)),
+        LimitError::TooDeep { limit: 3 }
+    );
+}
+
+#[test]
+fn test_step_limit() {
+    let limits = Limits {
+        max_steps: 10,
+        ..Limits::default()
+    };
+    assert!(evaluate_limited("1 + 2 + 3", limits).is_ok());
+    assert_eq!(
+        limit_error(evaluate_limited("1 + 2 + 3 + 4 + 5 + 6", limits)),
+        LimitError::TooManySteps { limit: 10 }
+    );
+}
+
+#[test]
+fn test_time_limit() {
+    let limits = Limits {
+        max_time: Duration::ZERO,
+        ..Limits::default()
+    };
+    assert_eq!(
+        limit_error(evaluate_limited("1 + 2", limits)),
+        LimitError::TimedOut {
+            limit: Duration::ZERO
+        }
+    );
+}
+
+#[test]
+fn test_time_limit_interrupts_a_function() {
+    // Factoring a 27-digit semiprime takes far longer than the limit
+    let limits = Limits {
+        max_time: Duration::from_millis(10),
+        ..Limits::default()
+    };
+    assert_eq!(
+        limit_error(evaluate_limited(
+            "totient(1000000000004010000000001147)",
+            limits
+        )),
+        LimitError::TimedOut {
+            limit: Duration::from_millis(10)
+        }
+    );
+}
+
+#[test]
+fn test_limits_can_be_lifted() {
+    let long = vec!["1"; 2_000].join("+");
+    assert!(matches!(
+        tokenize(&long).unwrap_err().downcast_ref::<LimitError>(),
+        Some(LimitError::InputTooLong { .. })
+    ));
+    let result = evaluate_limited(&long, Limits::unlimited()).unwrap();
+    assert_eq!(result, Value::Number(dec!(2000)));
+}
+
+#[test]
+fn test_results_limited_only_by_precision() {
+    // Neither factorials past 20 nor powers of many-digit bases are capped any more
+    let cases = [
+        ("25!", dec!(15511210043330985984000000)),
+        ("1.5 ^ 10", dec!(57.6650390625)),
+        ("10 ^ 28", dec!(10000000000000000000000000000)),
+    ];
+    for (input, expected) in cases {
+        let result = evaluate_limited(input, Limits::default()).unwrap();
+        assert_eq!(result, Value::Number(expected), "{}", input);
+    }
+    assert!(evaluate_limited("28!", Limits::default()).is_err());
+    assert!(evaluate_limited("10 ^ 29", Limits::default()).is_err());
+}
@@ -0,0 +1,90 @@
+use crate::money::{self, Currency, RateTable};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn currency(code: &str) -> Currency {
+    Currency::new(code).unwrap()
+}
+
+#[test]
+fn test_currency_codes() {
+    assert_eq!(Currency::from_symbol("€"), Some(currency("EUR")));
+    assert_eq!(Currency::new("usd"), None);
+    assert_eq!(Currency::new("US"), None);
+    assert_eq!(currency("JPY").scale(), 0);
+    assert_eq!(currency("KWD").scale(), 3);
+    assert_eq!(currency("CHF").scale(), 2);
+    assert_eq!(currency("CHF").symbol(), None);
+}
+
+#[test]
+fn test_round_is_bankers() {
+    let usd = currency("USD");
+    assert_eq!(money::round(dec!(2.345), usd).unwrap(), dec!(2.34));
+    assert_eq!(money::round(dec!(2.355), usd).unwrap(), dec!(2.36));
+    assert_eq!(money::round(dec!(2.5), currency("JPY")).unwrap(), dec!(2));
+}
+
+#[test]
+fn test_allocate_distributes_remainder() {
+    let usd = currency("USD");
+    assert_eq!(
+        money::allocate(dec!(100), 3, usd).unwrap(),
+        vec![(dec!(33.34), 1), (dec!(33.33), 2)]
+    );
+    assert_eq!(
+        money::allocate(dec!(-100), 3, usd).unwrap(),
+        vec![(dec!(-33.34), 1), (dec!(-33.33), 2)]
+    );
+    assert_eq!(
+        money::allocate(dec!(0.05), 3, usd).unwrap(),
+        vec![(dec!(0.02), 2), (dec!(0.01), 1)]
+    );
+    assert_eq!(
+        money::allocate(dec!(10), 4, usd).unwrap(),
+        vec![(dec!(2.5), 4)]
+    );
+    assert!(money::allocate(dec!(10), 0, usd).is_err());
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(
+        money::format(dec!(1234567.5), currency("USD")),
+        "$1,234,567.50"
+    );
+    assert_eq!(money::format(dec!(-999.999), currency("EUR")), "-€1,000.00");
+    assert_eq!(money::format(dec!(1234), currency("JPY")), "¥1,234");
+    assert_eq!(money::format(dec!(12.5), currency("CHF")), "CHF 12.50");
+    assert_eq!(money::format(Decimal::ZERO, currency("GBP")), "£0.00");
+}
+
+#[test]
+fn test_rate_table() {
+    let table = RateTable::parse(
+        "# rates as of 2026-10-16\n\
+         USD EUR 0.92\n\
+         \n\
+         USD JPY 150\n",
+    )
+    .unwrap();
+    let (usd, eur, jpy) = (currency("USD"), currency("EUR"), currency("JPY"));
+    assert_eq!(table.convert(dec!(100), usd, eur).unwrap(), dec!(92.00));
+    assert_eq!(table.convert(dec!(92), eur, usd).unwrap(), dec!(100.00));
+    assert_eq!(table.convert(dec!(92), eur, jpy).unwrap(), dec!(15000));
+    assert!(table.convert(dec!(1), usd, currency("GBP")).is_err());
+}
+
+#[test]
+fn test_rate_table_errors() {
+    let cases = [
+        ("USD EUR", "line 1: expected `FROM TO RATE`"),
+        ("\nusd EUR 1", "line 2: invalid currency code 'usd'"),
+        ("USD EUR -1", "line 1: invalid rate '-1'"),
+        ("USD EUR abc", "line 1: invalid rate 'abc'"),
+    ];
+    for (text, expected) in cases {
+        let err = RateTable::parse(text).unwrap_err().to_string();
+        assert_eq!(err, expected);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::notation::{Notation, format_number, format_radix};
+use crate::rounding::{Precision, RoundingContext};
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_scientific_and_engineering() {
+    let ctx = RoundingContext::default();
+    let cases = [
+        (dec!(1025), Notation::Scientific, "1.025e3"),
+        (dec!(1025), Notation::Engineering, "1.025e3"),
+        (dec!(12500), Notation::Engineering, "12.5e3"),
+        (dec!(0.0125), Notation::Scientific, "1.25e-2"),
+        (dec!(0.0125), Notation::Engineering, "12.5e-3"),
+        (dec!(-0.5), Notation::Scientific, "-5e-1"),
+        (dec!(7), Notation::Scientific, "7e0"),
+        (dec!(0), Notation::Scientific, "0e0"),
+        (dec!(1025), Notation::Plain, "1025"),
+    ];
+    for (n, notation, expected) in cases {
+        assert_eq!(
+            format_number(n, notation, &ctx),
+            expected,
+            "{} {}",
+            n,
+            notation
+        );
+    }
+}
+
+#[test]
+fn test_notation_rounds_the_mantissa() {
+    let ctx = RoundingContext {
+        precision: Precision::SignificantDigits(3),
+        ..RoundingContext::default()
+    };
+    assert_eq!(
+        format_number(dec!(123456), Notation::Scientific, &ctx),
+        "1.23e5"
+    );
+    // Rounding up carries into the exponent
+    assert_eq!(
+        format_number(dec!(99960), Notation::Scientific, &ctx),
+        "1e5"
+    );
+    assert_eq!(
+        format_number(dec!(999600), Notation::Engineering, &ctx),
+        "1e6"
+    );
+    assert_eq!(
+        "engineering".parse::<Notation>().unwrap(),
+        Notation::Engineering
+    );
+    assert!("sci".parse::<Notation>().is_err());
+}
+
+#[test]
+fn test_format_radix() {
+    assert_eq!(format_radix(dec!(1025), 2).unwrap(), "10000000001");
+    assert_eq!(format_radix(dec!(255), 16).unwrap(), "ff");
+    assert_eq!(format_radix(dec!(-8), 8).unwrap(), "-10");
+    assert_eq!(format_radix(dec!(0), 2).unwrap(), "0");
+    assert_eq!(format_radix(dec!(35), 36).unwrap(), "z");
+    assert!(format_radix(dec!(1.5), 2).is_err());
+    assert!(format_radix(dec!(10), 1).is_err());
+}
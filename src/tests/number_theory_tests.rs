@@ -0,0 +1,148 @@
+use crate::limits::{Deadline, LimitError};
+use crate::number_theory::{
+    crt, divisors, factor, factor_within, fermat_test, gcd, integer_sqrt, is_prime, lcm, modinv,
+    next_prime, next_prime_within, powmod, totient, totient_within,
+};
+use std::time::Duration;
+
+#[test]
+fn test_gcd_and_lcm() {
+    assert_eq!(gcd(48, 18), 6);
+    assert_eq!(gcd(0, 7), 7);
+    assert_eq!(lcm(4, 6), Some(12));
+    assert_eq!(lcm(0, 6), Some(0));
+    assert_eq!(lcm(u128::MAX, u128::MAX - 1), None);
+}
+
+#[test]
+fn test_is_prime_small() {
+    let primes: Vec<u128> = (0..60).filter(|&n| is_prime(n)).collect();
+    assert_eq!(
+        primes,
+        vec![
+            2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59
+        ]
+    );
+}
+
+#[test]
+fn test_is_prime_pseudoprimes() {
+    // Carmichael numbers and strong pseudoprimes to several bases
+    for n in [561u128, 1105, 1729, 3215031751, 3825123056546413051] {
+        assert!(!is_prime(n), "{} is composite", n);
+    }
+}
+
+#[test]
+fn test_is_prime_large() {
+    assert!(is_prime(18446744073709551557)); // largest prime below 2^64
+    assert!(is_prime(170141183460469231731687303715884105727)); // 2^127 - 1
+    assert!(!is_prime(18446744073709551557 * 3));
+    // Product of two primes above 2^50, beyond the deterministic Miller-Rabin range
+    let p = next_prime(1 << 50).unwrap();
+    let q = next_prime(p).unwrap();
+    assert!(!is_prime(p * q));
+}
+
+#[test]
+fn test_next_prime() {
+    assert_eq!(next_prime(0), Some(2));
+    assert_eq!(next_prime(2), Some(3));
+    assert_eq!(next_prime(13), Some(17));
+    assert_eq!(next_prime(1_000_000), Some(1_000_003));
+}
+
+#[test]
+fn test_factor() {
+    assert_eq!(factor(1), vec![]);
+    assert_eq!(factor(360), vec![(2, 3), (3, 2), (5, 1)]);
+    assert_eq!(factor(97), vec![(97, 1)]);
+    // Semiprime with two large factors, which trial division alone cannot split
+    assert_eq!(
+        factor(1_000_000_007 * 998_244_353),
+        vec![(998_244_353, 1), (1_000_000_007, 1)]
+    );
+}
+
+#[test]
+fn test_factor_large_semiprime() {
+    // 27 digits, with factors too large for the rho steps to be taken one gcd at a time
+    let p = next_prime(10_u128.pow(13)).unwrap();
+    let q = next_prime(10_u128.pow(14)).unwrap();
+    assert_eq!(
+        factor_within(p * q, &Deadline::never()),
+        Ok(vec![(p, 1), (q, 1)])
+    );
+    assert_eq!(totient(p * q), (p - 1) * (q - 1));
+    // Above 2^127, where the sum in Montgomery reduction overflows
+    let r = next_prime(1 << 98).unwrap();
+    assert!(r * 1_000_000_007 > 1 << 127);
+    assert_eq!(factor(r * 1_000_000_007), vec![(1_000_000_007, 1), (r, 1)]);
+    let largest = u128::MAX - 158; // largest prime below 2^128
+    assert!(is_prime(largest));
+    assert_eq!(powmod(3, largest - 1, largest), 1);
+}
+
+#[test]
+fn test_deadline() {
+    let p = next_prime(10_u128.pow(13)).unwrap();
+    let q = next_prime(10_u128.pow(14)).unwrap();
+    let passed = Deadline::after(Duration::ZERO);
+    std::thread::sleep(Duration::from_millis(1));
+    let timed_out = LimitError::TimedOut {
+        limit: Duration::ZERO,
+    };
+    assert_eq!(totient_within(p * q, &passed), Err(timed_out));
+    assert_eq!(next_prime_within(10_u128.pow(30), &passed), Err(timed_out));
+    // Work that needs no search finishes regardless
+    assert_eq!(totient_within(97, &passed), Ok(96));
+    assert_eq!(next_prime_within(u128::MAX, &Deadline::never()), Ok(None));
+}
+
+#[test]
+fn test_totient_and_divisors() {
+    assert_eq!(totient(1), 1);
+    assert_eq!(totient(36), 12);
+    assert_eq!(totient(97), 96);
+    assert_eq!(divisors(12), vec![1, 2, 3, 4, 6, 12]);
+    assert_eq!(divisors(0), vec![]);
+}
+
+#[test]
+fn test_powmod_and_modinv() {
+    assert_eq!(powmod(2, 10, 1000), 24);
+    assert_eq!(powmod(5, 0, 7), 1);
+    assert_eq!(powmod(5, 3, 1), 0);
+    // Modulus above 2^64 exercises the overflow-free multiplication
+    let m = 18446744073709551629u128 * 3;
+    assert_eq!(powmod(m - 1, 2, m), 1);
+    assert_eq!(modinv(3, 11), Some(4));
+    assert_eq!(modinv(4, 8), None);
+}
+
+#[test]
+fn test_crt() {
+    assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+    // Non-coprime moduli that are consistent
+    assert_eq!(crt(&[(2, 4), (4, 6)]), Some((10, 12)));
+    // Non-coprime moduli that conflict
+    assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+    assert_eq!(crt(&[(1, 0)]), None);
+}
+
+#[test]
+fn test_fermat_test() {
+    assert!(fermat_test(97, 2));
+    assert!(!fermat_test(91, 2));
+    // 341 is the smallest base-2 Fermat pseudoprime
+    assert!(fermat_test(341, 2));
+    assert!(!fermat_test(341, 3));
+}
+
+#[test]
+fn test_integer_sqrt() {
+    assert_eq!(integer_sqrt(0), 0);
+    assert_eq!(integer_sqrt(15), 3);
+    assert_eq!(integer_sqrt(16), 4);
+    assert_eq!(integer_sqrt(u128::MAX), u64::MAX as u128);
+}
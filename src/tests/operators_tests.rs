@@ -0,0 +1,258 @@
+use crate::decimal_math;
+use crate::engine::{EvalError, Evaluator};
+use crate::export::latex;
+use crate::expr::{parse_with_functions, simplify};
+use crate::functions::Registry;
+use crate::operators::{
+    ADDITIVE, Associativity, FACTORIAL, Fixity, MULTIPLICATIVE, Operator, POWER,
+};
+use crate::value::Value;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal_macros::dec;
+use std::error::Error;
+
+/// An operator on exact numbers.
+struct Numeric {
+    symbol: &'static str,
+    fixity: Fixity,
+    precedence: u8,
+    associativity: Associativity,
+    f: fn(&[Decimal]) -> Decimal,
+}
+
+impl Operator for Numeric {
+    fn symbol(&self) -> &str {
+        self.symbol
+    }
+
+    fn fixity(&self) -> Fixity {
+        self.fixity
+    }
+
+    fn precedence(&self) -> u8 {
+        self.precedence
+    }
+
+    fn associativity(&self) -> Associativity {
+        self.associativity
+    }
+
+    fn apply(&self, args: &[Value]) -> Result<Value, Box<dyn Error>> {
+        Value::propagate(args, |x| Ok((self.f)(x)))
+    }
+}
+
+fn infix(symbol: &'static str, precedence: u8, f: fn(&[Decimal]) -> Decimal) -> Numeric {
+    Numeric {
+        symbol,
+        fixity: Fixity::Infix,
+        precedence,
+        associativity: Associativity::Left,
+        f,
+    }
+}
+
+fn power(x: &[Decimal]) -> Decimal {
+    decimal_math::powi(x[0], x[1].to_u32().unwrap()).unwrap()
+}
+
+fn evaluator() -> Evaluator {
+    let mut registry = Registry::new();
+    let operators = [
+        infix("//", MULTIPLICATIVE, |x| (x[0] / x[1]).floor()),
+        infix("mod", MULTIPLICATIVE, |x| x[0] % x[1]),
+        infix("choose", MULTIPLICATIVE, |x| {
+            (0..x[1].to_u64().unwrap()).fold(Decimal::ONE, |acc, i| {
+                acc * (x[0] - Decimal::from(i)) / Decimal::from(i + 1)
+            })
+        }),
+        infix("⊕", ADDITIVE, |x| x[0] * x[1] + x[0] + x[1]),
+        Numeric {
+            associativity: Associativity::Right,
+            ..infix("^^", POWER, power)
+        },
+        Numeric {
+            fixity: Fixity::Prefix,
+            ..infix("neg", ADDITIVE, |x| -x[0])
+        },
+        Numeric {
+            fixity: Fixity::Prefix,
+            ..infix("√", POWER, |x| decimal_math::sqrt(x[0]).unwrap())
+        },
+        Numeric {
+            fixity: Fixity::Postfix,
+            ..infix("!!", FACTORIAL, |x| {
+                let n = x[0].to_i64().unwrap();
+                (1..=n).rev().step_by(2).map(Decimal::from).product()
+            })
+        },
+        Numeric {
+            fixity: Fixity::Postfix,
+            ..infix("pct", ADDITIVE, |x| x[0] / dec!(100))
+        },
+    ];
+    for operator in operators {
+        registry.insert_operator(operator).unwrap();
+    }
+    Evaluator::builder().functions(registry).build()
+}
+
+#[test]
+fn test_infix_operators() {
+    let evaluator = evaluator();
+    let cases = [
+        ("7 // 2 + 1", dec!(4)),
+        ("8 / 2", dec!(4)),
+        ("10 mod 3", dec!(1)),
+        ("2 * 10 mod 4", dec!(0)),
+        ("5 choose 2", dec!(10)),
+        ("1 + 2 ⊕ 3", dec!(15)),
+        ("2 * 2 ⊕ 3", dec!(19)),
+        ("2 ^^ 3 ^^ 2", dec!(512)),
+        ("2 ^ 3 ^^ 2", dec!(512)),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(
+            evaluator.eval(input).unwrap(),
+            Value::Number(expected),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_prefix_and_postfix_operators() {
+    let evaluator = evaluator();
+    let cases = [
+        ("neg 2 * 3 + 1", dec!(-5)),
+        ("2 - neg 3", dec!(5)),
+        ("√9 + 1", dec!(4)),
+        ("√(2 * 8)", dec!(4)),
+        ("5!! + 1", dec!(16)),
+        ("5!", dec!(120)),
+        ("2 * 3!!", dec!(6)),
+        ("150 + 50 pct", dec!(2)),
+        ("neg -2", dec!(2)),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(
+            evaluator.eval(input).unwrap(),
+            Value::Number(expected),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_operator_syntax_problems() {
+    let evaluator = evaluator();
+    let messages = |input| match evaluator.eval(input) {
+        Err(EvalError::Syntax(diagnostics)) => diagnostics
+            .into_iter()
+            .map(|d| d.message)
+            .collect::<Vec<_>>(),
+        other => panic!("expected syntax problems for {}, got {:?}", input, other),
+    };
+    assert_eq!(messages("10 mod"), ["Expression ends with operator 'mod'"]);
+    assert_eq!(messages("mod 3"), ["Missing operand before 'mod'"]);
+    assert_eq!(messages("2 neg 3"), ["Missing operator before 'neg'"]);
+    assert_eq!(messages("modulo(3)"), ["Unknown function: modulo"]);
+    assert_eq!(evaluator.complete("7 // 2 mod").as_deref(), Some("7 // 2"));
+}
+
+#[test]
+fn test_operator_symbols() {
+    let mut registry = Registry::new();
+    for symbol in ["", "in", "2x", ".5", "a b", "f(", ","] {
+        assert!(
+            registry
+                .insert_operator(infix(symbol, ADDITIVE, |x| x[0]))
+                .is_err(),
+            "{:?}",
+            symbol
+        );
+    }
+    registry
+        .insert_operator(infix("mod", ADDITIVE, |x| x[0]))
+        .unwrap();
+    assert_eq!(registry.operators().count(), 1);
+    registry.remove_operator("mod");
+    assert_eq!(registry.operators().count(), 0);
+}
+
+#[test]
+fn test_operators_in_expressions() {
+    let evaluator = evaluator();
+    let parse = |input| parse_with_functions(input, evaluator.functions()).unwrap();
+    let cases = [
+        ("neg 2*3 + 1", "neg 2 * 3 + 1"),
+        ("(1 + 2) ⊕ 3", "1 + 2 ⊕ 3"),
+        ("1 + (2 ⊕ 3)", "1 + (2 ⊕ 3)"),
+        ("2 ^^ (3 ^^ 2)", "2 ^^ 3 ^^ 2"),
+        ("(2 ^^ 3) ^^ 2", "(2 ^^ 3) ^^ 2"),
+        ("√(2*8) + √9", "√(2 * 8) + √9"),
+        ("√4^2", "√(4^2)"),
+        ("(√4)^2", "(√4)^2"),
+        ("2^√4^2", "2^√(4^2)"),
+        ("√4 ^^ 2", "√(4 ^^ 2)"),
+        ("(5!!) + 1", "5!! + 1"),
+        ("150 + 50 pct", "150 + 50 pct"),
+        ("150 + (50 pct)", "150 + (50 pct)"),
+        ("neg -2", "neg (-2)"),
+        ("x mod 3", "x mod 3"),
+    ];
+    for (input, expected) in cases {
+        let expr = parse(input);
+        assert_eq!(expr.to_string(), expected, "{}", input);
+        // Printing is stable, and reads back the way the evaluator groups it
+        assert_eq!(parse(expected), expr, "{}", expected);
+        if !expr.has_variables() {
+            assert_eq!(
+                evaluator.eval(input).unwrap(),
+                evaluator.eval(expected).unwrap(),
+                "{}",
+                expected
+            );
+        }
+    }
+    assert_eq!(
+        simplify(&parse("√(x + x) mod 2 * 3")).to_string(),
+        "3 * (√(2 * x) mod 2)"
+    );
+    assert_eq!(latex(&parse("10 mod √x")), r"10 \operatorname{mod} √ x");
+}
+
+#[test]
+fn test_printed_operators_evaluate_the_same() {
+    let evaluator = evaluator();
+    let inputs = [
+        "√4^2",
+        "2^√4^2",
+        "√4 ^^ 2",
+        "2 ^^ √4 ^^ 2",
+        "√4 * 2",
+        "√9!! + 1",
+        "√4 pct",
+        "-√4^2",
+        "√-4^2",
+        "neg 2 ⊕ 3",
+        "neg 2 ^ 2",
+        "neg 50 pct",
+        "2 - neg 3 * 4",
+    ];
+    for input in inputs {
+        let printed = parse_with_functions(input, evaluator.functions())
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            evaluator.eval(&printed).unwrap(),
+            evaluator.eval(input).unwrap(),
+            "{} printed as {}",
+            input,
+            printed
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::rounding::{Precision, Rounding, RoundingContext, round_places};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn context(precision: Precision, rounding: Rounding) -> RoundingContext {
+    RoundingContext {
+        precision,
+        rounding,
+    }
+}
+
+#[test]
+fn test_default_format() {
+    let ctx = RoundingContext::default();
+    assert_eq!(ctx.format(dec!(2)), "2");
+    assert_eq!(ctx.format(dec!(0.1) + dec!(0.2)), "0.3");
+    assert_eq!(ctx.format(Decimal::ONE / dec!(3)), "0.3333333333");
+    assert_eq!(ctx.format(dec!(-0.00000000001)), "0");
+}
+
+#[test]
+fn test_rounding_strategies() {
+    let cases = [
+        (Rounding::HalfEven, dec!(2.5), "2"),
+        (Rounding::HalfEven, dec!(3.5), "4"),
+        (Rounding::HalfUp, dec!(2.5), "3"),
+        (Rounding::HalfUp, dec!(-2.5), "-3"),
+        (Rounding::TowardZero, dec!(-2.7), "-2"),
+        (Rounding::Floor, dec!(-2.1), "-3"),
+        (Rounding::Ceiling, dec!(2.1), "3"),
+    ];
+    for (rounding, value, expected) in cases {
+        let ctx = context(Precision::DecimalPlaces(0), rounding);
+        assert_eq!(ctx.format(value), expected, "{} {}", rounding, value);
+    }
+}
+
+#[test]
+fn test_significant_digits() {
+    let ctx = context(Precision::SignificantDigits(3), Rounding::HalfEven);
+    assert_eq!(ctx.format(dec!(123456)), "123000");
+    assert_eq!(ctx.format(dec!(0.00123456)), "0.00123");
+    assert_eq!(ctx.format(dec!(9.999)), "10");
+    assert_eq!(ctx.format(Decimal::ZERO), "0");
+}
+
+#[test]
+fn test_round_intermediate_follows_strategy() {
+    let third = dec!(2) / dec!(3);
+    let floor = context(Precision::default(), Rounding::Floor).round_intermediate(third);
+    let ceiling = context(Precision::default(), Rounding::Ceiling).round_intermediate(third);
+    assert!(floor < ceiling);
+    assert_eq!(floor, dec!(0.666666666666666666666666666));
+    assert_eq!(ceiling, dec!(0.666666666666666666666666667));
+}
+
+#[test]
+fn test_divide_rounds_once() {
+    // The exact quotient is 0.10000000000000000000000000046..., just below the tie at the
+    // 27th digit, although its nearest 28-digit `Decimal` is the tie itself
+    let (a, b) = (dec!(0.3000000000000000000000000014), dec!(3));
+    let half_up = context(Precision::default(), Rounding::HalfUp);
+    assert_eq!(half_up.divide(a, b), Some(dec!(0.1)));
+    let ceiling = context(Precision::default(), Rounding::Ceiling);
+    assert_eq!(
+        ceiling.divide(a, b),
+        Some(dec!(0.100000000000000000000000001))
+    );
+
+    // 0.09999999999999999999999999996... truncates to 27 nines, not to 0.1
+    let toward_zero = context(Precision::default(), Rounding::TowardZero);
+    assert_eq!(
+        toward_zero.divide(dec!(0.2999999999999999999999999999), dec!(3)),
+        Some(dec!(0.0999999999999999999999999999))
+    );
+    assert_eq!(
+        toward_zero.divide(dec!(-2), dec!(3)),
+        Some(dec!(-0.666666666666666666666666666))
+    );
+
+    let ctx = RoundingContext::default();
+    assert_eq!(ctx.divide(dec!(2.5), dec!(0.5)), Some(dec!(5)));
+    assert_eq!(ctx.divide(dec!(1), dec!(0)), None);
+    assert_eq!(ctx.divide(Decimal::MAX, dec!(0.1)), None);
+    assert_eq!(ctx.divide(dec!(1e-20), dec!(1e20)), Some(Decimal::ZERO));
+}
+
+#[test]
+fn test_round_places() {
+    assert_eq!(
+        round_places(dec!(3.14159), 2, Rounding::HalfEven),
+        Some(dec!(3.14))
+    );
+    assert_eq!(
+        round_places(dec!(1250), -2, Rounding::HalfEven),
+        Some(dec!(1200))
+    );
+    assert_eq!(
+        round_places(dec!(1250), -2, Rounding::HalfUp),
+        Some(dec!(1300))
+    );
+    assert_eq!(
+        round_places(dec!(-0.001), 1, Rounding::TowardZero),
+        Some(Decimal::ZERO)
+    );
+}
+
+#[test]
+fn test_precision_adjustment() {
+    assert_eq!(
+        Precision::DecimalPlaces(0).with_digits(100),
+        Precision::DecimalPlaces(28)
+    );
+    assert_eq!(
+        Precision::SignificantDigits(1).with_digits(0),
+        Precision::SignificantDigits(1)
+    );
+    assert_eq!(
+        Precision::DecimalPlaces(0).toggle_kind(),
+        Precision::SignificantDigits(1)
+    );
+    assert_eq!(Rounding::Ceiling.next(), Rounding::HalfEven);
+}
+
+#[test]
+fn test_rounding_names() {
+    let mut rounding = Rounding::default();
+    for _ in 0..5 {
+        assert_eq!(rounding.to_string().parse::<Rounding>().unwrap(), rounding);
+        rounding = rounding.next();
+    }
+    assert_eq!(
+        "toward-zero".parse::<Rounding>().unwrap(),
+        Rounding::TowardZero
+    );
+    assert!("nearest".parse::<Rounding>().is_err());
+}
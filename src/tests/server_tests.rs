@@ -0,0 +1,210 @@
+use crate::engine::Evaluator;
+use crate::json::Json;
+use crate::server::{Connection, listen, serve};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// A client that sends requests over a stream and reads the responses.
+struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: usize,
+}
+
+impl Client {
+    fn new(stream: UnixStream) -> Self {
+        Client {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            writer: stream,
+            next_id: 1,
+        }
+    }
+
+    fn send(&mut self, line: &str) -> Json {
+        writeln!(self.writer, "{}", line).unwrap();
+        let mut response = String::new();
+        self.reader.read_line(&mut response).unwrap();
+        Json::parse(&response).unwrap()
+    }
+
+    /// Calls a method and returns its result, failing on an error response.
+    fn call(&mut self, method: &str, params: &str) -> Json {
+        let id = self.next_id;
+        self.next_id += 1;
+        let response = self.send(&format!(
+            r#"{{"jsonrpc": "2.0", "id": {}, "method": "{}", "params": {}}}"#,
+            id, method, params
+        ));
+        assert_eq!(response.get("id"), Some(&Json::from(id)), "{}", response);
+        response
+            .get("result")
+            .cloned()
+            .unwrap_or_else(|| panic!("error response: {}", response))
+    }
+}
+
+fn formatted(result: &Json) -> Option<&str> {
+    result.get("value")?.get("formatted")?.as_str()
+}
+
+#[test]
+fn test_methods() {
+    let (client, server) = UnixStream::pair().unwrap();
+    thread::spawn(move || serve(Evaluator::new(), BufReader::new(&server), &server));
+    let mut client = Client::new(client);
+
+    let result = client.call("eval", r#"{"expression": "1/3"}"#);
+    assert_eq!(formatted(&result), Some("0.3333333333"));
+    assert_eq!(result.get("relation"), Some(&Json::from("≈")));
+
+    let result = client.call("define", r#"{"name": "rate", "expression": "0.2"}"#);
+    assert_eq!(result.get("ok"), Some(&Json::Bool(true)));
+    assert_eq!(result.get("name"), Some(&Json::from("rate")));
+    let result = client.call("eval", r#"{"expression": "100 * rate"}"#);
+    assert_eq!(formatted(&result), Some("20"));
+
+    let result = client.call("define", r#"{"name": "sqrt", "expression": "1"}"#);
+    assert_eq!(result.get("ok"), Some(&Json::Bool(false)));
+
+    let result = client.call("complete", r#"{"expression": "2 * ra"}"#);
+    assert_eq!(result.get("prefix"), Some(&Json::from("ra")));
+    assert_eq!(result.get("start"), Some(&Json::from(4_usize)));
+    assert_eq!(
+        result.get("candidates").unwrap().to_string(),
+        r#"[{"name":"rate","kind":"variable"}]"#
+    );
+    let result = client.call(
+        "complete",
+        r#"{"expression": "sqrt(is + 1", "position": 7}"#,
+    );
+    assert_eq!(result.get("prefix"), Some(&Json::from("is")));
+    assert_eq!(
+        result.get("candidates").unwrap().to_string(),
+        r#"[{"name":"is_prime","kind":"function"}]"#
+    );
+    assert_eq!(result.get("completed"), Some(&Json::Null));
+    let result = client.call("complete", r#"{"expression": "sqrt(4 +"}"#);
+    assert_eq!(result.get("completed"), Some(&Json::from("sqrt(4)")));
+
+    let result = client.call("diagnostics", r#"{"expression": "2 + * 3"}"#);
+    let Json::Array(problems) = &result else {
+        panic!("not an array: {}", result);
+    };
+    assert_eq!(problems.len(), 1);
+    assert_eq!(
+        client.call("diagnostics", r#"{"expression": "2 + 3"}"#),
+        Json::Array(vec![])
+    );
+
+    assert_eq!(client.call("reset", "null"), Json::Null);
+    let result = client.call("eval", r#"{"expression": "rate"}"#);
+    assert_eq!(result.get("ok"), Some(&Json::Bool(false)));
+}
+
+#[test]
+fn test_protocol_errors() {
+    let mut connection = Connection::new(Evaluator::new());
+    let error_code = |response: Option<String>| {
+        let response = Json::parse(&response.unwrap()).unwrap();
+        response.get("error").unwrap().get("code").cloned().unwrap()
+    };
+    let code = |n: &str| Json::Number(n.to_string());
+
+    assert_eq!(error_code(connection.handle("{not json")), code("-32700"));
+    assert_eq!(error_code(connection.handle("[]")), code("-32600"));
+    assert_eq!(
+        error_code(connection.handle(r#"{"id": 1, "method": "eval"}"#)),
+        code("-32600")
+    );
+    assert_eq!(
+        error_code(connection.handle(r#"{"jsonrpc": "2.0", "id": 1, "method": "solve"}"#)),
+        code("-32601")
+    );
+    assert_eq!(
+        error_code(
+            connection.handle(
+                r#"{"jsonrpc": "2.0", "id": 1, "method": "eval", "params": {"expr": "1"}}"#
+            )
+        ),
+        code("-32602")
+    );
+    assert_eq!(
+        error_code(connection.handle(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "complete", "params": {"expression": "ab", "position": 3}}"#
+        )),
+        code("-32602")
+    );
+
+    // Notifications are carried out but not answered
+    let notification =
+        r#"{"jsonrpc": "2.0", "method": "define", "params": {"name": "x", "expression": "5"}}"#;
+    assert_eq!(connection.handle(notification), None);
+    assert_eq!(connection.handle("   "), None);
+
+    // A batch is answered with an array of responses, in order
+    let response = connection
+        .handle(
+            r#"[{"jsonrpc": "2.0", "id": "a", "method": "eval", "params": {"expression": "x + 1"}},
+                {"jsonrpc": "2.0", "method": "reset"},
+                {"jsonrpc": "2.0", "id": "b", "method": "eval", "params": {"expression": "x"}}]"#,
+        )
+        .unwrap();
+    let Json::Array(responses) = Json::parse(&response).unwrap() else {
+        panic!("not a batch: {}", response);
+    };
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0].get("id"), Some(&Json::from("a")));
+    assert_eq!(formatted(responses[0].get("result").unwrap()), Some("6"));
+    let result = responses[1].get("result").unwrap();
+    assert_eq!(result.get("ok"), Some(&Json::Bool(false)));
+}
+
+#[test]
+fn test_socket_sessions() {
+    let dir = std::env::temp_dir().join(format!("fermat-server-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("fermat.sock");
+    // A file that is not a socket is never replaced
+    std::fs::write(&path, "notes").unwrap();
+    let error = listen(&path, Evaluator::new()).unwrap_err();
+    assert!(error.to_string().contains("not a socket"), "{}", error);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "notes");
+    std::fs::remove_file(&path).unwrap();
+
+    // A stale socket file from an earlier run is replaced
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+    let listening = path.clone();
+    thread::spawn(move || listen(&listening, Evaluator::new()));
+
+    let connect = |path: &Path| {
+        for _ in 0..100 {
+            if let Ok(stream) = UnixStream::connect(path) {
+                return Client::new(stream);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("cannot connect to {}", path.display());
+    };
+    let mut first = connect(&path);
+    let mut second = connect(&path);
+
+    // Nor is the socket of a server that is running
+    let error = listen(&path, Evaluator::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::AddrInUse);
+
+    first.call("define", r#"{"name": "x", "expression": "2"}"#);
+    second.call("define", r#"{"name": "x", "expression": "10"}"#);
+    let result = first.call("eval", r#"{"expression": "x * 3"}"#);
+    assert_eq!(formatted(&result), Some("6"));
+    let result = second.call("eval", r#"{"expression": "x * 3"}"#);
+    assert_eq!(formatted(&result), Some("30"));
+
+    drop(first);
+    let result = second.call("eval", r#"{"expression": "x"}"#);
+    assert_eq!(formatted(&result), Some("10"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,103 @@
+use crate::session::{Script, Session, Statement};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_statements() {
+    assert_eq!(Statement::parse(""), Statement::Blank);
+    assert_eq!(Statement::parse("   # just a note"), Statement::Blank);
+    assert_eq!(
+        Statement::parse("1 + 2 # three"),
+        Statement::Expression("1 + 2")
+    );
+    assert_eq!(Statement::parse("x=2*3"), Statement::Assign("x", "2*3"));
+    assert_eq!(
+        Statement::parse("#include lib.fermat"),
+        Statement::Include("lib.fermat")
+    );
+    // Not a name, so left for the evaluator to report
+    assert_eq!(Statement::parse("2x = 3"), Statement::Expression("2x = 3"));
+}
+
+#[test]
+fn test_variables_persist() {
+    let mut session = Session::default();
+    assert_eq!(session.run("# setup").unwrap(), None);
+    let entry = session.run("width = 3").unwrap().unwrap();
+    assert_eq!(entry.name.as_deref(), Some("width"));
+    session.run("height = width + 1").unwrap();
+    let area = session.run("width * height").unwrap().unwrap();
+    assert_eq!(session.evaluator().format(&area.computed.value), "12");
+    assert!(area.computed.exact);
+
+    // An approximate variable makes results that use it approximate
+    session.run("third = 1 / 3").unwrap();
+    assert!(!session.run("third * 3").unwrap().unwrap().computed.exact);
+
+    // Redefining replaces the value
+    session.run("width = 10").unwrap();
+    let entry = session.run("width").unwrap().unwrap();
+    assert_eq!(session.evaluator().format(&entry.computed.value), "10");
+
+    let names: Vec<&str> = session.variables().map(|(name, _)| name).collect();
+    assert_eq!(names, ["height", "third", "width"]);
+    session.reset();
+    assert!(session.run("width").is_err());
+}
+
+#[test]
+fn test_invalid_assignments() {
+    let mut session = Session::default();
+    for line in ["sqrt = 2", "today = 1", "min = 5", "in = 1", "x = 1 +"] {
+        assert!(session.run(line).is_err(), "{}", line);
+    }
+    assert_eq!(
+        session.run("w = 5").unwrap_err().to_string(),
+        "Invalid variable name: w is reserved as a duration unit"
+    );
+    assert!(session.run("#include other.fermat").is_err());
+    assert_eq!(session.variables().count(), 0);
+}
+
+#[test]
+fn test_script_includes() {
+    let dir = std::env::temp_dir().join(format!("fermat-script-{}", std::process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("lib/rates.fermat"), "vat = 0.2\n").unwrap();
+    fs::write(dir.join("loop.fermat"), "#include \"loop.fermat\"\n").unwrap();
+    let main = dir.join("main.fermat");
+    fs::write(
+        &main,
+        "#include \"lib/rates.fermat\"\nprice = 50\nprice * (1 + vat)\n",
+    )
+    .unwrap();
+
+    let script = Script::load(&main).unwrap();
+    let lines: Vec<(&str, usize, bool)> = script
+        .lines
+        .iter()
+        .map(|line| (line.text.as_str(), line.number, line.included))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            ("vat = 0.2", 1, true),
+            ("price = 50", 2, false),
+            ("price * (1 + vat)", 3, false)
+        ]
+    );
+    assert_eq!(script.lines[0].path, dir.join("lib/rates.fermat"));
+
+    let mut session = Session::default();
+    let last = script
+        .lines
+        .iter()
+        .filter_map(|line| session.run(&line.text).unwrap())
+        .last()
+        .unwrap();
+    assert_eq!(session.evaluator().format(&last.computed.value), "60");
+
+    assert!(Script::load(&dir.join("loop.fermat")).is_err());
+    assert!(Script::parse("#include missing.fermat", Path::new("stdin")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,99 @@
+use crate::rounding::{Precision, Rounding, RoundingContext};
+use crate::value::{Computed, Value};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+#[test]
+fn test_format_uncertain() {
+    let ctx = RoundingContext::default();
+    let value = Value::uncertain(dec!(3.14159), dec!(0.0123)).unwrap();
+    assert_eq!(value.format(&ctx), "3.142 ± 0.012");
+    let value = Value::uncertain(dec!(4567), dec!(123)).unwrap();
+    assert_eq!(value.format(&ctx), "4570 ± 120");
+    let value = Value::uncertain(dec!(2), Decimal::ZERO).unwrap();
+    assert_eq!(value.format(&ctx), "2 ± 0");
+}
+
+#[test]
+fn test_format_interval_rounds_outward() {
+    let ctx = RoundingContext {
+        precision: Precision::DecimalPlaces(2),
+        rounding: Rounding::HalfEven,
+    };
+    let value = Value::interval(dec!(1.234), dec!(1.231));
+    assert_eq!(value, Value::interval(dec!(1.231), dec!(1.234)));
+    assert_eq!(value.format(&ctx), "[1.23, 1.24]");
+}
+
+#[test]
+fn test_propagate_exact() {
+    let args = [Value::Number(dec!(2)), Value::Number(dec!(3))];
+    let result = Value::propagate(&args, |x| Ok(x[0] * x[1])).unwrap();
+    assert_eq!(result, Value::Number(dec!(6)));
+}
+
+#[test]
+fn test_propagate_uncertain_in_quadrature() {
+    let args = [
+        Value::uncertain(dec!(10), dec!(3)).unwrap(),
+        Value::uncertain(dec!(20), dec!(4)).unwrap(),
+    ];
+    let result = Value::propagate(&args, |x| Ok(x[0] + x[1])).unwrap();
+    assert_eq!(result, Value::uncertain(dec!(30), dec!(5)).unwrap());
+}
+
+#[test]
+fn test_propagate_uncertain_at_domain_edge() {
+    // sqrt is undefined below zero, so a one-sided difference is used
+    let args = [Value::uncertain(dec!(0.01), dec!(0.02)).unwrap()];
+    let result = Value::propagate(&args, |x| {
+        if x[0] < Decimal::ZERO {
+            return Err("negative".into());
+        }
+        Ok(x[0] * dec!(2))
+    })
+    .unwrap();
+    assert_eq!(result, Value::uncertain(dec!(0.02), dec!(0.04)).unwrap());
+}
+
+#[test]
+fn test_center_and_max_abs() {
+    assert_eq!(Value::interval(dec!(-3), dec!(1)).center(), dec!(-1));
+    assert_eq!(Value::interval(dec!(-3), dec!(1)).max_abs(), dec!(3));
+    let value = Value::uncertain(dec!(-2), dec!(0.5)).unwrap();
+    assert_eq!(value.max_abs(), dec!(2.5));
+    assert_eq!(value.as_number(), None);
+}
+
+#[test]
+fn test_is_shown_exactly() {
+    let ctx = RoundingContext {
+        precision: Precision::DecimalPlaces(2),
+        rounding: Rounding::HalfEven,
+    };
+    assert!(Value::Number(dec!(1.25)).is_shown_exactly(&ctx));
+    assert!(!Value::Number(dec!(1.255)).is_shown_exactly(&ctx));
+    assert!(Value::interval(dec!(1), dec!(2.5)).is_shown_exactly(&ctx));
+    assert!(!Value::interval(dec!(1), dec!(2.501)).is_shown_exactly(&ctx));
+}
+
+#[test]
+fn test_computed_format() {
+    let ctx = RoundingContext::default();
+    let exact = Computed {
+        value: Value::Number(dec!(0.25)),
+        exact: true,
+    };
+    assert_eq!(exact.format(&ctx), "= 0.25");
+    let approximate = Computed {
+        exact: false,
+        ..exact
+    };
+    assert_eq!(approximate.format(&ctx), "≈ 0.25");
+    // An exact value rounded for display is approximate as shown
+    let rounded = Computed {
+        value: Value::Number(dec!(0.123456789012)),
+        exact: true,
+    };
+    assert_eq!(rounded.format(&ctx), "≈ 0.123456789");
+}
@@ -0,0 +1,127 @@
+use crate::engine::{EvalError, Evaluator};
+use crate::worksheet::{Outcome, Worksheet, reference};
+
+/// Returns each line's result as the worksheet shows it, or `-` for a blank line and `!`
+/// for an error.
+fn results(sheet: &Worksheet) -> Vec<String> {
+    sheet
+        .outcomes()
+        .iter()
+        .map(|outcome| match outcome {
+            Outcome::Blank => "-".to_string(),
+            Outcome::Value(entry) => sheet.evaluator().format_computed(&entry.computed),
+            Outcome::Error(_) => "!".to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_lines_and_references() {
+    let sheet = Worksheet::from_text(
+        Evaluator::new(),
+        "# Groceries\n\
+         Bread: 3.5\n\
+         - Cheese: cheese = 12\n\
+         \n\
+         Total: line2 + cheese\n\
+         line5 / 3\n\
+         line4 + 1\n\
+         Groceries\n",
+    );
+    assert_eq!(
+        results(&sheet),
+        [
+            "-",
+            "= 3.5",
+            "= 12",
+            "-",
+            "= 15.5",
+            "≈ 5.1666666667",
+            "!",
+            "!"
+        ]
+    );
+    // A blank line has no value to refer to
+    let Outcome::Error(EvalError::Syntax(problems)) = &sheet.outcomes()[6] else {
+        panic!("{:?}", sheet.outcomes()[6]);
+    };
+    assert_eq!(problems[0].message, "Unknown name: line4");
+    let Outcome::Value(entry) = &sheet.outcomes()[2] else {
+        panic!("{:?}", sheet.outcomes()[2]);
+    };
+    assert_eq!(entry.name.as_deref(), Some("cheese"));
+    assert_eq!(reference(3), "line3");
+    assert_eq!(sheet.text().lines().count(), 8);
+}
+
+#[test]
+fn test_only_labels_are_left_out() {
+    let sheet = Worksheet::from_text(Evaluator::new(), "- 3\nChange: - 3\n* 2\n1) 5");
+    assert_eq!(results(&sheet), ["= -3", "= -3", "!", "!"]);
+}
+
+#[test]
+fn test_edits_recompute_dependent_lines() {
+    let mut sheet = Worksheet::from_text(Evaluator::new(), "a = 2\nline1 * 10\na + 1");
+    assert_eq!(results(&sheet), ["= 2", "= 20", "= 3"]);
+
+    sheet.set_line(0, "a = 5".to_string());
+    assert_eq!(results(&sheet), ["= 5", "= 50", "= 6"]);
+
+    // Inserting a line above a reference renumbers it, so it still refers to `a = 5`
+    sheet.insert_line(0, "100".to_string());
+    assert_eq!(sheet.lines(), ["100", "a = 5", "line2 * 10", "a + 1"]);
+    assert_eq!(results(&sheet), ["= 100", "= 5", "= 50", "= 6"]);
+
+    sheet.remove_line(0);
+    assert_eq!(sheet.lines(), ["a = 5", "line1 * 10", "a + 1"]);
+    assert_eq!(results(&sheet), ["= 5", "= 50", "= 6"]);
+
+    // Removing the line that defines a variable breaks the lines that use it
+    sheet.remove_line(0);
+    assert_eq!(sheet.lines(), ["line0 * 10", "a + 1"]);
+    assert_eq!(results(&sheet), ["!", "!"]);
+    sheet.remove_line(1);
+    sheet.remove_line(0);
+    assert_eq!(sheet.lines(), [""]);
+    assert_eq!(results(&sheet), ["-"]);
+}
+
+#[test]
+fn test_references_follow_moved_lines() {
+    let mut sheet = Worksheet::from_text(
+        Evaluator::new(),
+        "2\n3\nline1 * line2\nline3 + 1  # line3 plus one\nline2b = 7\nline2b + line3",
+    );
+    assert_eq!(results(&sheet), ["= 2", "= 3", "= 6", "= 7", "= 7", "= 13"]);
+
+    // Inserted between the lines a reference uses, and written with the old numbers
+    sheet.insert_line(1, "line3 - 1".to_string());
+    assert_eq!(
+        sheet.lines(),
+        [
+            "2",
+            "line4 - 1",
+            "3",
+            "line1 * line3",
+            "line4 + 1  # line4 plus one",
+            "line2b = 7",
+            "line2b + line4"
+        ]
+    );
+    assert_eq!(results(&sheet)[3..], ["= 6", "= 7", "= 7", "= 13"]);
+    // A line cannot refer to the lines below it
+    assert_eq!(results(&sheet)[1], "!");
+
+    sheet.remove_line(1);
+    assert_eq!(results(&sheet), ["= 2", "= 3", "= 6", "= 7", "= 7", "= 13"]);
+    assert_eq!(sheet.lines()[5], "line2b + line3");
+
+    // Joining makes the references to either line refer to the joined line
+    sheet.insert_line(0, String::new());
+    sheet.join_lines(0);
+    assert_eq!(sheet.lines()[..3], ["2", "3", "line1 * line2"]);
+    sheet.join_lines(0);
+    assert_eq!(sheet.lines()[..2], ["23", "line1 * line1"]);
+    assert_eq!(results(&sheet)[..3], ["= 23", "= 529", "= 530"]);
+}
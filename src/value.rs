@@ -0,0 +1,466 @@
+//! Value module for the calculator application.
+//!
+//! This module defines the values produced by the evaluator: exact numbers, closed
+//! intervals, measurements with a standard uncertainty, dates, times and durations, and
+//! money amounts.
+//! It also implements how intervals and uncertainties propagate through any function of
+//! exact numbers, and how each kind of value is displayed, marked as exact or approximate.
+
+use crate::datetime::{self, TimeUnit};
+use crate::decimal_math;
+use crate::money::{self, Currency};
+use crate::rounding::{Rounding, RoundingContext, round_places};
+use rust_decimal::Decimal;
+use std::error::Error;
+use std::fmt;
+
+/// Significant figures shown for a standard uncertainty.
+const UNCERTAINTY_DIGITS: i64 = 2;
+
+/// A value produced by evaluating an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    /// An exact number.
+    Number(Decimal),
+    /// A closed interval `[lo, hi]` containing the true value.
+    Interval { lo: Decimal, hi: Decimal },
+    /// A measurement `mean ± sigma`, where `sigma` is the standard uncertainty.
+    Uncertain { mean: Decimal, sigma: Decimal },
+    /// A calendar date, in days since 1970-01-01.
+    Date(i64),
+    /// A time of day, in seconds since midnight.
+    Time(i64),
+    /// A date and time, in seconds since 1970-01-01 00:00.
+    DateTime(i64),
+    /// A length of time in seconds, displayed in `unit` if one was requested.
+    Duration {
+        seconds: Decimal,
+        unit: Option<TimeUnit>,
+    },
+    /// An amount of money, kept at the currency's fixed number of decimal places.
+    Money { amount: Decimal, currency: Currency },
+    /// An amount of money divided into `parts` shares that differ by at most one minor unit.
+    Split {
+        total: Decimal,
+        currency: Currency,
+        parts: u32,
+    },
+}
+
+impl From<Decimal> for Value {
+    fn from(n: Decimal) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl Value {
+    /// Creates an interval, ordering the bounds if necessary.
+    pub fn interval(a: Decimal, b: Decimal) -> Self {
+        Value::Interval {
+            lo: a.min(b),
+            hi: a.max(b),
+        }
+    }
+
+    /// Creates a measurement with the given standard uncertainty.
+    pub fn uncertain(mean: Decimal, sigma: Decimal) -> Result<Self, Box<dyn Error>> {
+        if sigma < Decimal::ZERO {
+            return Err("Uncertainty must not be negative".into());
+        }
+        Ok(Value::Uncertain { mean, sigma })
+    }
+
+    /// Creates a duration of the given number of seconds.
+    pub fn duration(seconds: Decimal) -> Self {
+        Value::Duration {
+            seconds,
+            unit: None,
+        }
+    }
+
+    /// Describes the kind of value with its article, such as "a date", for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "a number",
+            Value::Interval { .. } => "an interval",
+            Value::Uncertain { .. } => "an uncertain value",
+            Value::Date(_) => "a date",
+            Value::Time(_) => "a time",
+            Value::DateTime(_) => "a date and time",
+            Value::Duration { .. } => "a duration",
+            Value::Money { .. } => "a money amount",
+            Value::Split { .. } => "an allocation",
+        }
+    }
+
+    /// Returns true for exact numbers, intervals and uncertain values.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Value::Number(_) | Value::Interval { .. } | Value::Uncertain { .. }
+        )
+    }
+
+    /// Returns true for dates, times and durations.
+    pub fn is_temporal(&self) -> bool {
+        matches!(
+            self,
+            Value::Date(_) | Value::Time(_) | Value::DateTime(_) | Value::Duration { .. }
+        )
+    }
+
+    /// Returns the exact number, if this value is one.
+    pub fn as_number(&self) -> Option<Decimal> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the central value: the number itself, the interval midpoint, or the mean.
+    ///
+    /// Dates, times and durations return their number of seconds, counted from the epoch
+    /// or from midnight.
+    pub fn center(&self) -> Decimal {
+        match *self {
+            Value::Number(n) => n,
+            // The width only overflows when the bounds have opposite signs, and then their
+            // sum cannot
+            Value::Interval { lo, hi } => match hi.checked_sub(lo) {
+                Some(width) => lo + width / Decimal::TWO,
+                None => (lo + hi) / Decimal::TWO,
+            },
+            Value::Uncertain { mean, .. } => mean,
+            Value::Date(days) => Decimal::from(days) * Decimal::from(datetime::SECONDS_PER_DAY),
+            Value::Time(seconds) | Value::DateTime(seconds) => Decimal::from(seconds),
+            Value::Duration { seconds, .. } => seconds,
+            Value::Money { amount, .. } => amount,
+            Value::Split { total, .. } => total,
+        }
+    }
+
+    /// Returns the largest absolute value this value may take, used for magnitude checks.
+    pub fn max_abs(&self) -> Decimal {
+        match *self {
+            Value::Number(n) => n.abs(),
+            Value::Interval { lo, hi } => lo.abs().max(hi.abs()),
+            Value::Uncertain { mean, sigma } => mean.abs().saturating_add(sigma),
+            _ => self.center().abs(),
+        }
+    }
+
+    /// Applies a function of exact numbers to values, propagating intervals and uncertainties.
+    ///
+    /// With only exact arguments the function is applied directly. Interval arguments are
+    /// evaluated at their bounds (and at zero, where extrema of functions such as `abs` or
+    /// `cosh` lie) and the hull of the results is returned. Uncertain arguments use
+    /// first-order propagation with symmetric differences: each argument is shifted by its
+    /// own uncertainty and the resulting changes are combined in quadrature.
+    pub fn propagate<F>(args: &[Value], f: F) -> Result<Value, Box<dyn Error>>
+    where
+        F: Fn(&[Decimal]) -> Result<Decimal, Box<dyn Error>>,
+    {
+        if let Some(arg) = args.iter().find(|a| !a.is_numeric()) {
+            return Err(format!("Expected a number, got {}", arg.kind()).into());
+        }
+        let has_interval = args.iter().any(|a| matches!(a, Value::Interval { .. }));
+        let has_uncertain = args.iter().any(|a| matches!(a, Value::Uncertain { .. }));
+        match (has_interval, has_uncertain) {
+            (false, false) => {
+                let numbers: Vec<Decimal> = args.iter().map(Value::center).collect();
+                Ok(Value::Number(f(&numbers)?))
+            }
+            (true, false) => propagate_interval(args, f),
+            (false, true) => propagate_uncertain(args, f),
+            (true, true) => Err("Cannot combine an interval with an uncertain value".into()),
+        }
+    }
+
+    /// Formats the value for display.
+    ///
+    /// Numbers use the context's precision. Interval bounds use the same precision but are
+    /// rounded outward so the displayed interval still contains the exact one. Measurements
+    /// show the uncertainty to two significant figures and the mean to the same decimal place.
+    pub fn format(&self, ctx: &RoundingContext) -> String {
+        match *self {
+            Value::Number(n) => ctx.format(n),
+            Value::Interval { lo, hi } => {
+                let down = RoundingContext {
+                    rounding: Rounding::Floor,
+                    ..*ctx
+                };
+                let up = RoundingContext {
+                    rounding: Rounding::Ceiling,
+                    ..*ctx
+                };
+                format!("[{}, {}]", down.format(lo), up.format(hi))
+            }
+            Value::Uncertain { mean, sigma } => {
+                if sigma.is_zero() {
+                    return format!("{} ± 0", ctx.format(mean));
+                }
+                let places = uncertainty_places(sigma);
+                let round = |x: Decimal| round_places(x, places, ctx.rounding).unwrap_or(x);
+                let shown = places.max(0) as usize;
+                format!(
+                    "{} ± {}",
+                    fixed(round(mean), shown),
+                    fixed(round(sigma), shown)
+                )
+            }
+            Value::Date(days) => datetime::format_date_with_weekday(days),
+            Value::Time(seconds) => datetime::format_time(seconds),
+            Value::DateTime(seconds) => {
+                let (days, time) = datetime::split_datetime(seconds);
+                format!(
+                    "{} {}",
+                    datetime::format_date_with_weekday(days),
+                    datetime::format_time(time)
+                )
+            }
+            Value::Duration {
+                seconds,
+                unit: None,
+            } => datetime::format_duration(seconds, |s| ctx.format(s)),
+            Value::Duration {
+                seconds,
+                unit: Some(unit),
+            } => {
+                let count = ctx.round(seconds / unit.seconds());
+                let name = unit.to_string();
+                // "1 day" rather than "1 days"
+                let name = if count.abs() == Decimal::ONE {
+                    name.trim_end_matches('s')
+                } else {
+                    &name
+                };
+                format!("{} {}", count, name)
+            }
+            Value::Money { amount, currency } => money::format(amount, currency),
+            Value::Split { .. } => format_split(self, money::format),
+        }
+    }
+
+    /// Returns whether [`Value::format`] shows this value without rounding it.
+    pub fn is_shown_exactly(&self, ctx: &RoundingContext) -> bool {
+        let shown = |x: Decimal| ctx.round(x) == x;
+        match *self {
+            Value::Number(n) => shown(n),
+            Value::Interval { lo, hi } => shown(lo) && shown(hi),
+            Value::Uncertain { mean, sigma } if sigma.is_zero() => shown(mean),
+            Value::Uncertain { mean, sigma } => {
+                let places = uncertainty_places(sigma);
+                let shown = |x: Decimal| round_places(x, places, ctx.rounding) == Some(x);
+                shown(mean) && shown(sigma)
+            }
+            Value::Date(_) | Value::Time(_) | Value::DateTime(_) | Value::Split { .. } => true,
+            // Whole days, hours and minutes are shown as they are, the seconds rounded
+            Value::Duration {
+                seconds,
+                unit: None,
+            } => shown(seconds.abs() % Decimal::from(60)),
+            Value::Duration {
+                seconds,
+                unit: Some(unit),
+            } => {
+                let count = seconds / unit.seconds();
+                count * unit.seconds() == seconds && shown(count)
+            }
+            Value::Money { amount, currency } => {
+                money::round(amount, currency).ok() == Some(amount)
+            }
+        }
+    }
+}
+
+/// A computed value together with whether it is exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Computed {
+    /// The value.
+    pub value: Value,
+    /// False if any step of the computation rounded or approximated its result, as in
+    /// `1/3` or `2^0.5`.
+    pub exact: bool,
+}
+
+impl Computed {
+    /// Formats the value after `=` if what is shown is exactly the value, or after `≈` if
+    /// the value is approximate or is rounded for display.
+    pub fn format(&self, ctx: &RoundingContext) -> String {
+        format!("{} {}", self.relation(ctx), self.value.format(ctx))
+    }
+
+    /// Returns `=` if what is shown is exactly the value, or `≈` otherwise.
+    pub fn relation(&self, ctx: &RoundingContext) -> &'static str {
+        if self.exact && self.value.is_shown_exactly(ctx) {
+            "="
+        } else {
+            "≈"
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Interval { lo, hi } => write!(f, "[{}, {}]", lo, hi),
+            Value::Uncertain { mean, sigma } => write!(f, "{} ± {}", mean, sigma),
+            Value::Date(days) => f.write_str(&datetime::format_date(*days)),
+            Value::Time(seconds) => f.write_str(&datetime::format_time(*seconds)),
+            Value::DateTime(seconds) => {
+                let (days, time) = datetime::split_datetime(*seconds);
+                write!(
+                    f,
+                    "{} {}",
+                    datetime::format_date(days),
+                    datetime::format_time(time)
+                )
+            }
+            Value::Duration {
+                seconds,
+                unit: None,
+            } => f.write_str(&datetime::format_duration(*seconds, |s| s.to_string())),
+            Value::Duration {
+                seconds,
+                unit: Some(unit),
+            } => write!(f, "{} {}", seconds / unit.seconds(), unit),
+            Value::Money { amount, currency } => write!(f, "{} {}", amount, currency),
+            Value::Split { .. } => f.write_str(&format_split(self, |amount, currency| {
+                format!("{} {}", amount, currency)
+            })),
+        }
+    }
+}
+
+/// Formats an allocation as its distinct shares and their counts, as in `$33.34 × 1, $33.33 × 2`.
+fn format_split(value: &Value, amount: impl Fn(Decimal, Currency) -> String) -> String {
+    let Value::Split {
+        total,
+        currency,
+        parts,
+    } = *value
+    else {
+        return value.to_string();
+    };
+    match money::allocate(total, parts, currency) {
+        Ok(shares) => shares
+            .iter()
+            .map(|&(share, count)| format!("{} × {}", amount(share, currency), count))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Formats `x` with at least `places` decimal places, padding with zeros.
+///
+/// Unlike `format!("{:.*}")`, this cannot run out of room for a large `x` with many places.
+fn fixed(x: Decimal, places: usize) -> String {
+    let mut text = x.to_string();
+    let shown = text
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len());
+    if shown < places {
+        if shown == 0 {
+            text.push('.');
+        }
+        text.extend(std::iter::repeat_n('0', places - shown));
+    }
+    text
+}
+
+/// Returns the decimal places a measurement with uncertainty `sigma` is shown to.
+fn uncertainty_places(sigma: Decimal) -> i64 {
+    UNCERTAINTY_DIGITS - 1 - magnitude(sigma)
+}
+
+/// Returns the decimal exponent of the leading digit of a non-zero `x`.
+fn magnitude(x: Decimal) -> i64 {
+    let x = x.normalize();
+    let digits = x.mantissa().unsigned_abs().to_string().len() as i64;
+    digits - 1 - x.scale() as i64
+}
+
+fn propagate_interval<F>(args: &[Value], f: F) -> Result<Value, Box<dyn Error>>
+where
+    F: Fn(&[Decimal]) -> Result<Decimal, Box<dyn Error>>,
+{
+    // Candidate points for each argument: its bounds, plus zero when it lies inside
+    let candidates: Vec<Vec<Decimal>> = args
+        .iter()
+        .map(|arg| match *arg {
+            Value::Interval { lo, hi } if lo < Decimal::ZERO && hi > Decimal::ZERO => {
+                vec![lo, hi, Decimal::ZERO]
+            }
+            Value::Interval { lo, hi } => vec![lo, hi],
+            other => vec![other.center()],
+        })
+        .collect();
+
+    let mut lo: Option<Decimal> = None;
+    let mut hi: Option<Decimal> = None;
+    let mut indices = vec![0usize; args.len()];
+    loop {
+        let point: Vec<Decimal> = indices
+            .iter()
+            .zip(&candidates)
+            .map(|(&i, c)| c[i])
+            .collect();
+        let y = f(&point)?;
+        lo = Some(lo.map_or(y, |l| l.min(y)));
+        hi = Some(hi.map_or(y, |h| h.max(y)));
+
+        // Advance to the next combination of candidate points
+        let mut position = 0;
+        loop {
+            if position == indices.len() {
+                return Ok(Value::Interval {
+                    lo: lo.unwrap_or_default(),
+                    hi: hi.unwrap_or_default(),
+                });
+            }
+            indices[position] += 1;
+            if indices[position] < candidates[position].len() {
+                break;
+            }
+            indices[position] = 0;
+            position += 1;
+        }
+    }
+}
+
+fn propagate_uncertain<F>(args: &[Value], f: F) -> Result<Value, Box<dyn Error>>
+where
+    F: Fn(&[Decimal]) -> Result<Decimal, Box<dyn Error>>,
+{
+    let point: Vec<Decimal> = args.iter().map(Value::center).collect();
+    let mean = f(&point)?;
+
+    let mut variance = Decimal::ZERO;
+    for (i, arg) in args.iter().enumerate() {
+        let Value::Uncertain { sigma, .. } = *arg else {
+            continue;
+        };
+        if sigma.is_zero() {
+            continue;
+        }
+        let shifted = |delta: Decimal| {
+            let mut p = point.clone();
+            p[i] = p[i].checked_add(delta).ok_or("Uncertainty too large")?;
+            f(&p)
+        };
+        // Fall back to a one-sided difference at the edge of a function's domain
+        let change = match (shifted(sigma), shifted(-sigma)) {
+            (Ok(up), Ok(down)) => up.checked_sub(down).map(|d| d / Decimal::TWO),
+            (Ok(up), Err(_)) => up.checked_sub(mean),
+            (Err(_), Ok(down)) => mean.checked_sub(down),
+            (Err(e), Err(_)) => return Err(e),
+        }
+        .ok_or("Uncertainty too large")?;
+        variance = variance
+            .checked_add(change.checked_mul(change).ok_or("Uncertainty too large")?)
+            .ok_or("Uncertainty too large")?;
+    }
+    Value::uncertain(mean, decimal_math::sqrt(variance)?)
+}
@@ -0,0 +1,244 @@
+//! Worksheet module for the calculator application.
+//!
+//! This module evaluates a sheet of lines the way a notepad calculator does. Lines are
+//! evaluated from top to bottom with the variables of the lines above them, and each result
+//! can be used by the lines below as `lineN`, where `N` is its 1-based line number, as well
+//! as by the name it assigns. A line may start with a label ending in `: `, as in
+//! `Rent: 1200`, and `#` starts a comment. Nothing else is left out of a line, so `- 3`
+//! is -3 rather than an item of a list.
+//!
+//! When a line changes, it and the lines below it are evaluated again, so every result that
+//! may depend on it is brought up to date, while the results above it are kept. Inserting or
+//! removing a line renumbers the references to the lines that move, as a spreadsheet does,
+//! so every reference keeps referring to the same line. A reference to a removed line
+//! becomes `line0`, which refers to no line, so the lines using it fail rather than
+//! silently using another line, while joining two lines makes the references to either of
+//! them refer to the joined line.
+
+use crate::engine::{EvalError, Evaluator};
+use crate::session::{Entry, Session, Statement, without_label};
+
+/// What a line of a worksheet evaluated to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Nothing to evaluate: the line is blank or a comment.
+    Blank,
+    /// The line's value, and the variable it assigned if it was an assignment.
+    Value(Entry),
+    Error(EvalError),
+}
+
+/// Returns the name by which the result of a line is referred to, such as `line3`.
+pub fn reference(number: usize) -> String {
+    format!("line{}", number)
+}
+
+/// Lines of calculations, each with its result.
+///
+/// ```
+/// use fermat::worksheet::{Outcome, Worksheet};
+/// use fermat::Evaluator;
+///
+/// let mut sheet = Worksheet::from_text(Evaluator::new(), "Rent: 1200\nrent3 = line1 / 3\n");
+/// sheet.set_line(0, "Rent: 1500".to_string());
+/// let Outcome::Value(entry) = &sheet.outcomes()[1] else { panic!() };
+/// assert_eq!(sheet.evaluator().format(&entry.computed.value), "500");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Worksheet {
+    evaluator: Evaluator,
+    lines: Vec<String>,
+    /// The outcome of each line, in the same order.
+    outcomes: Vec<Outcome>,
+}
+
+impl Worksheet {
+    /// Creates a worksheet with a single empty line, evaluated with the given evaluator's
+    /// options and variables.
+    pub fn new(evaluator: Evaluator) -> Self {
+        Worksheet {
+            evaluator,
+            lines: vec![String::new()],
+            outcomes: vec![Outcome::Blank],
+        }
+    }
+
+    /// Creates a worksheet holding the lines of a text.
+    pub fn from_text(evaluator: Evaluator, text: &str) -> Self {
+        let mut worksheet = Worksheet::new(evaluator);
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        if !lines.is_empty() {
+            worksheet.lines = lines;
+        }
+        worksheet.recompute();
+        worksheet
+    }
+
+    /// Returns the text of the worksheet, with every line ended by a newline.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Returns the lines, of which there is always at least one.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Returns the outcome of each line.
+    pub fn outcomes(&self) -> &[Outcome] {
+        &self.outcomes
+    }
+
+    /// Returns the evaluator whose options the lines are evaluated with.
+    pub fn evaluator(&self) -> &Evaluator {
+        &self.evaluator
+    }
+
+    /// Returns the evaluator for changing its options; call [`Worksheet::recompute`]
+    /// afterwards for the results to follow.
+    pub fn evaluator_mut(&mut self) -> &mut Evaluator {
+        &mut self.evaluator
+    }
+
+    /// Replaces a line and evaluates it and the lines below it again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no line at `index`.
+    pub fn set_line(&mut self, index: usize, text: String) {
+        self.lines[index] = text;
+        self.recompute_from(index);
+    }
+
+    /// Inserts a line before the one at `index`, or at the end if `index` is the number of
+    /// lines, renumbers the references to the lines after it and evaluates the lines from
+    /// there on again.
+    ///
+    /// The references in the new line are renumbered too, as they are written with the line
+    /// numbers from before the insertion, as when a line is split in two.
+    pub fn insert_line(&mut self, index: usize, text: String) {
+        let renumbered = |number| if number > index { number + 1 } else { number };
+        let first = self.renumber(renumbered);
+        self.lines.insert(index, renumber_line(&text, renumbered));
+        self.recompute_from(first.min(index));
+    }
+
+    /// Removes a line, renumbers the references to the lines after it and evaluates the
+    /// lines below it again. Removing the only line leaves it empty instead.
+    pub fn remove_line(&mut self, index: usize) {
+        if self.lines.len() == 1 {
+            self.set_line(0, String::new());
+            return;
+        }
+        let removed = index + 1;
+        let first = self.renumber(|number| match number {
+            _ if number == removed => 0,
+            _ if number > removed => number - 1,
+            _ => number,
+        });
+        self.lines.remove(index);
+        self.recompute_from(first.min(index));
+    }
+
+    /// Appends the line after the one at `index` to it, renumbering the references to either
+    /// of them to the joined line and the references to the lines after them, and evaluates
+    /// the lines from there on again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no line after `index`.
+    pub fn join_lines(&mut self, index: usize) {
+        let second = index + 2;
+        let first = self.renumber(|number| if number >= second { number - 1 } else { number });
+        let joined = self.lines.remove(index + 1);
+        self.lines[index].push_str(&joined);
+        self.recompute_from(first.min(index));
+    }
+
+    /// Rewrites every `lineN` reference as `renumbered(N)`, returning the index of the first
+    /// line that changed, or the number of lines if none did.
+    fn renumber(&mut self, renumbered: impl Fn(usize) -> usize) -> usize {
+        let mut first = self.lines.len();
+        for (i, line) in self.lines.iter_mut().enumerate() {
+            let rewritten = renumber_line(line, &renumbered);
+            if rewritten != *line {
+                *line = rewritten;
+                first = first.min(i);
+            }
+        }
+        first
+    }
+
+    /// Evaluates every line again.
+    pub fn recompute(&mut self) {
+        self.recompute_from(0);
+    }
+
+    /// Evaluates the lines from `first` on, defining the variables of the lines above it
+    /// from their outcomes without evaluating them again.
+    fn recompute_from(&mut self, first: usize) {
+        let mut session = Session::new(self.evaluator.clone());
+        self.outcomes.truncate(first);
+        for (i, outcome) in self.outcomes.iter().enumerate() {
+            define(&mut session, i + 1, outcome);
+        }
+        for (i, line) in self.lines.iter().enumerate().skip(first) {
+            let outcome = evaluate(&mut session, line);
+            define(&mut session, i + 1, &outcome);
+            self.outcomes.push(outcome);
+        }
+    }
+}
+
+/// Rewrites the `lineN` references of a line as `renumbered(N)`, leaving names that only
+/// contain one, such as `subline2` or `line2b`, alone.
+fn renumber_line(line: &str, renumbered: impl Fn(usize) -> usize) -> String {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("line") {
+        let after = &rest[start + 4..];
+        let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let whole = !rest[..start].ends_with(is_name) && !after[digits..].starts_with(is_name);
+        match after[..digits].parse() {
+            Ok(number) if whole => {
+                result.push_str(&rest[..start]);
+                result.push_str(&reference(renumbered(number)));
+            }
+            _ => result.push_str(&rest[..start + 4 + digits]),
+        }
+        rest = &after[digits..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Evaluates a line, leaving out its label.
+fn evaluate(session: &mut Session, line: &str) -> Outcome {
+    if Statement::parse(line) == Statement::Blank {
+        return Outcome::Blank;
+    }
+    match session.run(without_label(line)) {
+        Ok(Some(entry)) => Outcome::Value(entry),
+        Ok(None) => Outcome::Blank,
+        Err(e) => Outcome::Error(e),
+    }
+}
+
+/// Defines the variables of a line that has a value: the one it assigned, if any, and its
+/// `lineN` reference.
+fn define(session: &mut Session, number: usize, outcome: &Outcome) {
+    let Outcome::Value(entry) = outcome else {
+        return;
+    };
+    let functions = session.evaluator_mut().functions_mut();
+    if let Some(name) = &entry.name {
+        // The name was accepted when the line was evaluated
+        let _ = functions.set_variable(name, entry.computed);
+    }
+    // A function named like a reference hides it, which leaves the line unnamed
+    let _ = functions.set_variable(&reference(number), entry.computed);
+}
@@ -0,0 +1,265 @@
+//! Worksheet view module for the Calculator TUI.
+//!
+//! This module shows a [`Worksheet`] as a scrollable multi-line editor, in the manner of a
+//! notepad calculator: every line is shown with its number and, right-aligned, its result,
+//! and the results are updated as the lines are typed. The error of the line under the
+//! cursor is shown at the bottom, and the line number of a line that fails is marked.
+
+use crate::settings::Theme;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use fermat::Evaluator;
+use fermat::worksheet::{Outcome, Worksheet};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File in the working directory a worksheet not opened from a file is saved to.
+const WORKSHEET_FILE: &str = "worksheet.txt";
+
+/// A worksheet being edited.
+pub struct WorksheetView {
+    pub sheet: Worksheet,
+    /// The line of the cursor.
+    row: usize,
+    /// The position of the cursor in its line, in characters.
+    column: usize,
+    /// The first line shown.
+    scroll: usize,
+    /// The file the worksheet was opened from and is saved to.
+    path: Option<PathBuf>,
+    /// Whether there are changes that have not been saved.
+    modified: bool,
+    /// The outcome of the last save, shown at the bottom until the next key press.
+    status: Option<String>,
+}
+
+impl WorksheetView {
+    /// Creates an empty worksheet evaluated with the given evaluator.
+    pub fn new(evaluator: Evaluator) -> Self {
+        WorksheetView {
+            sheet: Worksheet::new(evaluator),
+            row: 0,
+            column: 0,
+            scroll: 0,
+            path: None,
+            modified: false,
+            status: None,
+        }
+    }
+
+    /// Opens the worksheet in a file, which is created when it is first saved if it does
+    /// not exist yet.
+    pub fn open(&mut self, path: &Path) -> io::Result<()> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("{}: {}", path.display(), e),
+                ));
+            }
+        };
+        self.sheet = Worksheet::from_text(self.sheet.evaluator().clone(), &text);
+        self.path = Some(path.to_path_buf());
+        self.row = 0;
+        self.column = 0;
+        self.modified = false;
+        Ok(())
+    }
+
+    /// Handles a key press: characters are typed into the line, Enter splits it, Backspace
+    /// and Delete join lines at their ends, the arrows, Home, End, Page Up and Page Down
+    /// move the cursor, and Ctrl-S saves.
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        self.status = None;
+        let length = self.line().chars().count();
+        match key.code {
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => self.save(),
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                let mut line = self.line().to_string();
+                line.insert(self.offset(), c);
+                self.edit(line);
+                self.column += 1;
+            }
+            KeyCode::Enter if self.column == 0 => {
+                // Inserted above, so that the references to this line follow it down
+                self.sheet.insert_line(self.row, String::new());
+                self.modified = true;
+                self.row += 1;
+            }
+            KeyCode::Enter => {
+                let mut line = self.line().to_string();
+                let rest = line.split_off(self.offset());
+                self.edit(line);
+                self.sheet.insert_line(self.row + 1, rest);
+                self.row += 1;
+                self.column = 0;
+            }
+            KeyCode::Backspace if self.column > 0 => {
+                let mut line = self.line().to_string();
+                self.column -= 1;
+                line.remove(self.offset());
+                self.edit(line);
+            }
+            KeyCode::Backspace if self.row > 0 => {
+                self.row -= 1;
+                self.column = self.line().chars().count();
+                self.join();
+            }
+            KeyCode::Delete if self.column < length => {
+                let mut line = self.line().to_string();
+                line.remove(self.offset());
+                self.edit(line);
+            }
+            KeyCode::Delete if self.row + 1 < self.sheet.lines().len() => self.join(),
+            KeyCode::Left if self.column > 0 => self.column -= 1,
+            KeyCode::Left if self.row > 0 => {
+                self.row -= 1;
+                self.column = self.line().chars().count();
+            }
+            KeyCode::Right if self.column < length => self.column += 1,
+            KeyCode::Right if self.row + 1 < self.sheet.lines().len() => {
+                self.row += 1;
+                self.column = 0;
+            }
+            KeyCode::Up => self.move_to(self.row.saturating_sub(1)),
+            KeyCode::Down => self.move_to(self.row + 1),
+            KeyCode::PageUp => self.move_to(self.row.saturating_sub(10)),
+            KeyCode::PageDown => self.move_to(self.row + 10),
+            KeyCode::Home => self.column = 0,
+            KeyCode::End => self.column = length,
+            _ => {}
+        }
+    }
+
+    /// Writes the worksheet to its file, or to `worksheet.txt` if it was not opened from
+    /// one.
+    fn save(&mut self) {
+        let path = self
+            .path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(WORKSHEET_FILE));
+        self.status = Some(match std::fs::write(&path, self.sheet.text()) {
+            Ok(()) => {
+                self.modified = false;
+                self.path = Some(path.clone());
+                format!("Wrote {}", path.display())
+            }
+            Err(e) => format!("Error: cannot write {}: {}", path.display(), e),
+        });
+    }
+
+    /// Returns the line of the cursor.
+    fn line(&self) -> &str {
+        &self.sheet.lines()[self.row]
+    }
+
+    /// Returns the byte offset of the cursor in its line.
+    fn offset(&self) -> usize {
+        self.line()
+            .char_indices()
+            .nth(self.column)
+            .map_or(self.line().len(), |(i, _)| i)
+    }
+
+    /// Replaces the line of the cursor, which evaluates it and the lines below it again.
+    fn edit(&mut self, line: String) {
+        self.sheet.set_line(self.row, line);
+        self.modified = true;
+    }
+
+    /// Joins the next line to the line of the cursor.
+    fn join(&mut self) {
+        self.sheet.join_lines(self.row);
+        self.modified = true;
+        // Renumbering a reference before the cursor may shorten the line
+        self.move_to(self.row);
+    }
+
+    /// Moves the cursor to another line, keeping its column where the line is long enough.
+    fn move_to(&mut self, row: usize) {
+        self.row = row.min(self.sheet.lines().len() - 1);
+        self.column = self.column.min(self.line().chars().count());
+    }
+
+    /// Draws the worksheet, scrolling it so that the cursor is visible.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(area);
+        let title = format!(
+            "Worksheet: {}{}",
+            self.path
+                .as_deref()
+                .map_or("unsaved".to_string(), |path| path.display().to_string()),
+            if self.modified { " [+]" } else { "" }
+        );
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let inner = block.inner(chunks[0]);
+        frame.render_widget(block, chunks[0]);
+
+        let height = (inner.height as usize).max(1);
+        if self.row < self.scroll {
+            self.scroll = self.row;
+        } else if self.row >= self.scroll + height {
+            self.scroll = self.row + 1 - height;
+        }
+        let evaluator = self.sheet.evaluator();
+        let visible = self.scroll..(self.scroll + height).min(self.sheet.lines().len());
+        let results: Vec<String> = self.sheet.outcomes()[visible.clone()]
+            .iter()
+            .map(|outcome| match outcome {
+                Outcome::Value(entry) => evaluator.format_computed(&entry.computed),
+                Outcome::Blank | Outcome::Error(_) => String::new(),
+            })
+            .collect();
+        let gutter = self.sheet.lines().len().to_string().len() + 1;
+        let result_width = results.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        let text_width = (inner.width as usize).saturating_sub(gutter + result_width + 1);
+
+        let muted = Style::default().fg(theme.muted);
+        let error = Style::default().fg(theme.error);
+        let lines: Vec<Line> = visible
+            .clone()
+            .zip(&results)
+            .map(|(i, result)| {
+                let number_style = match self.sheet.outcomes()[i] {
+                    Outcome::Error(_) => error,
+                    _ => muted,
+                };
+                let text: String = self.sheet.lines()[i].chars().take(text_width).collect();
+                let padding = text_width - text.chars().count() + 1;
+                Line::from(vec![
+                    Span::styled(format!("{:>1$} ", i + 1, gutter - 1), number_style),
+                    Span::raw(text),
+                    Span::raw(" ".repeat(padding)),
+                    Span::raw(format!("{:>1$}", result, result_width)),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), inner);
+        frame.set_cursor_position((
+            inner.x + (gutter + self.column.min(text_width)) as u16,
+            inner.y + (self.row - self.scroll) as u16,
+        ));
+
+        let footer = match (&self.status, &self.sheet.outcomes()[self.row]) {
+            (Some(status), _) => Line::styled(status.as_str(), muted),
+            (None, Outcome::Error(e)) => Line::styled(format!("Error: {}", e), error),
+            (None, _) => Line::styled(
+                "Esc: calculator  Ctrl-S: save  line3 is the result of line 3",
+                muted,
+            ),
+        };
+        frame.render_widget(Paragraph::new(footer), chunks[1]);
+    }
+}